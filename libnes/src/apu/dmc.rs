@@ -0,0 +1,295 @@
+use crate::apu::timer::Timer;
+use crate::apu::Region;
+
+// Timer periods in cpu cycles
+const NTSC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+const PAL_RATES: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+const IRQ_ENABLE: u8 = 0x80;
+const LOOP: u8 = 0x40;
+
+const SAMPLE_ADDR_START: u16 = 0xc000;
+const MAX_OUTPUT_LEVEL: u8 = 127;
+
+// $4010-$4013: plays 1-bit delta-encoded samples out of cpu memory. The dmc
+// can't read memory itself; instead it asks for each byte through
+// pending_fetch, and whoever owns the bus reads it (stalling the cpu) and
+// hands it back with load_sample,
+// see https://wiki.nesdev.com/w/index.php/APU_DMC
+pub struct Dmc {
+    region: Region,
+    control: u8,
+    timer: Timer,
+
+    sample_addr: u16,
+    sample_length: u16,
+    current_addr: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    // the output unit shifts out one bit of its current byte per timer period
+    shift: u8,
+    bits_remaining: u8,
+    is_silent: bool,
+    output_level: u8,
+
+    is_irq_pending: bool,
+}
+
+impl Dmc {
+    pub fn new(region: Region) -> Self {
+        let mut dmc = Dmc {
+            region,
+            control: 0,
+            timer: Timer::new(),
+            sample_addr: SAMPLE_ADDR_START,
+            sample_length: 1,
+            current_addr: SAMPLE_ADDR_START,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            is_silent: true,
+            output_level: 0,
+            is_irq_pending: false,
+        };
+
+        dmc.update_rate();
+
+        dmc
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.update_rate();
+    }
+
+    fn update_rate(&mut self) {
+        let rates = match self.region {
+            Region::Ntsc => &NTSC_RATES,
+            Region::Pal => &PAL_RATES,
+        };
+
+        self.timer.period = rates[(self.control & 0x0f) as usize] - 1;
+    }
+
+    pub fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0 => {
+                self.control = val;
+                self.update_rate();
+
+                if val & IRQ_ENABLE == 0 {
+                    self.is_irq_pending = false;
+                }
+            }
+            1 => self.output_level = val & 0x7f,
+            2 => self.sample_addr = SAMPLE_ADDR_START | ((val as u16) << 6),
+            _ => self.sample_length = ((val as u16) << 4) + 1,
+        }
+    }
+
+    // $4015 restarts the sample if it has finished, or stops it; either way
+    // the irq is acknowledged
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_irq_pending = false;
+
+        match is_enabled {
+            true if self.bytes_remaining == 0 => self.restart(),
+            true => {}
+            false => self.bytes_remaining = 0,
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_addr = self.sample_addr;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn is_irq_pending(&self) -> bool {
+        self.is_irq_pending
+    }
+
+    // The address of the next sample byte, once the buffer has emptied
+    pub fn pending_fetch(&self) -> Option<u16> {
+        match (self.sample_buffer, self.bytes_remaining) {
+            (None, remaining) if remaining > 0 => Some(self.current_addr),
+            _ => None,
+        }
+    }
+
+    pub fn load_sample(&mut self, val: u8) {
+        self.sample_buffer = Some(val);
+
+        // the address wraps around to $8000, not $0000
+        self.current_addr = match self.current_addr {
+            0xffff => 0x8000,
+            addr => addr + 1,
+        };
+
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            match (self.control & LOOP != 0, self.control & IRQ_ENABLE != 0) {
+                (true, _) => self.restart(),
+                (false, true) => self.is_irq_pending = true,
+                (false, false) => {}
+            }
+        }
+    }
+
+    // Called once per cpu cycle, as the rates are in cpu cycles
+    pub fn clock_timer(&mut self) {
+        if !self.timer.clock() {
+            return;
+        }
+
+        // each bit moves the level up or down by 2, if there's room
+        if !self.is_silent {
+            match self.shift & 0x01 {
+                1 if self.output_level <= MAX_OUTPUT_LEVEL - 2 => self.output_level += 2,
+                0 if self.output_level >= 2 => self.output_level -= 2,
+                _ => {}
+            }
+        }
+
+        self.shift >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+
+            match self.sample_buffer.take() {
+                Some(val) => {
+                    self.shift = val;
+                    self.is_silent = false;
+                }
+                None => self.is_silent = true,
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.output_level
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Feeds the dmc from memory until it stops asking, returning the addresses it read
+    fn run(dmc: &mut Dmc, memory: &[u8], cycles: usize) -> Vec<u16> {
+        let mut fetches = vec![];
+
+        for _ in 0..cycles {
+            if let Some(addr) = dmc.pending_fetch() {
+                fetches.push(addr);
+                dmc.load_sample(memory[(addr - SAMPLE_ADDR_START) as usize]);
+            }
+
+            dmc.clock_timer();
+        }
+
+        fetches
+    }
+
+    #[test]
+    fn playback() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+
+        // the fastest rate, one byte from $c040, starting from a level of 64
+        dmc.write_register(0, 0x0f);
+        dmc.write_register(1, 0x40);
+        dmc.write_register(2, 0x01);
+        dmc.write_register(3, 0x00);
+        dmc.set_enabled(true);
+
+        let memory = [0x00, 0x00, 0x00, 0x00];
+        let mut memory = memory.repeat(0x20);
+        memory[0x40] = 0x0f;
+
+        let fetches = run(&mut dmc, &memory, 1);
+        assert_eq!(fetches, vec![0xc040]);
+        assert_eq!(dmc.is_active(), false);
+
+        // the byte only starts playing once the output unit's silent cycle is over;
+        // then it's 4 bits up and 4 bits down, lsb first
+        let mut levels = vec![];
+        for _ in 0..16 {
+            run(&mut dmc, &memory, 54);
+            levels.push(dmc.output());
+        }
+
+        assert_eq!(levels[7..15], [66, 68, 70, 72, 70, 68, 66, 64]);
+    }
+
+    #[test]
+    fn looping() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+
+        // loop a 17-byte sample
+        dmc.write_register(0, LOOP | 0x0f);
+        dmc.write_register(3, 0x01);
+        dmc.set_enabled(true);
+
+        let memory = vec![0xaa; 0x20];
+        let fetches = run(&mut dmc, &memory, 54 * 8 * 20);
+
+        assert_eq!(fetches[..3], [0xc000, 0xc001, 0xc002]);
+        assert_eq!(fetches[16..19], [0xc010, 0xc000, 0xc001]);
+        assert_eq!(dmc.is_active(), true);
+        assert_eq!(dmc.is_irq_pending(), false);
+    }
+
+    #[test]
+    fn irq() {
+        let mut dmc = Dmc::new(Region::Ntsc);
+
+        dmc.write_register(0, IRQ_ENABLE | 0x0f);
+        dmc.set_enabled(true);
+
+        run(&mut dmc, &[0x00], 1);
+        assert_eq!(dmc.is_irq_pending(), true);
+
+        // clearing the enable flag acknowledges it
+        dmc.write_register(0, 0x0f);
+        assert_eq!(dmc.is_irq_pending(), false);
+
+        // as does writing $4015
+        dmc.write_register(0, IRQ_ENABLE | 0x0f);
+        dmc.set_enabled(true);
+        run(&mut dmc, &[0x00], 54 * 8);
+        assert_eq!(dmc.is_irq_pending(), true);
+
+        dmc.set_enabled(false);
+        assert_eq!(dmc.is_irq_pending(), false);
+    }
+
+    #[test]
+    fn address_wraps() {
+        let mut dmc = Dmc::new(Region::Pal);
+
+        dmc.write_register(2, 0xff);
+        dmc.write_register(3, 0x04);
+        dmc.set_enabled(true);
+
+        assert_eq!(dmc.pending_fetch(), Some(0xffc0));
+
+        for _ in 0..0x40 {
+            dmc.load_sample(0x00);
+            dmc.sample_buffer = None;
+        }
+
+        assert_eq!(dmc.pending_fetch(), Some(0x8000));
+        assert_eq!(dmc.timer.period, 397);
+    }
+}
@@ -0,0 +1,105 @@
+const ENVELOPE_LOOP: u8 = 0x20;
+const ENVELOPE_CONSTANT_VOLUME: u8 = 0x10;
+
+const MAX_DECAY: u8 = 15;
+
+// Either a constant volume, or a sawtooth that decays from 15 once per period
+// (clocked by the frame counter's quarter frames),
+// see https://wiki.nesdev.com/w/index.php/APU_Envelope
+#[derive(Default)]
+pub struct Envelope {
+    control: u8,
+    is_start: bool,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Envelope::default()
+    }
+
+    // The low 6 bits of a channel's first register
+    pub fn write_control(&mut self, val: u8) {
+        self.control = val & 0x3f;
+    }
+
+    pub fn restart(&mut self) {
+        self.is_start = true;
+    }
+
+    fn period(&self) -> u8 {
+        self.control & 0x0f
+    }
+
+    pub fn clock(&mut self) {
+        if self.is_start {
+            self.is_start = false;
+            self.decay = MAX_DECAY;
+            self.divider = self.period();
+            return;
+        }
+
+        match self.divider {
+            0 => {
+                self.divider = self.period();
+
+                let is_looping = self.control & ENVELOPE_LOOP != 0;
+                match (self.decay, is_looping) {
+                    (0, true) => self.decay = MAX_DECAY,
+                    (0, false) => {}
+                    (_, _) => self.decay -= 1,
+                }
+            }
+            _ => self.divider -= 1,
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        match self.control & ENVELOPE_CONSTANT_VOLUME {
+            0 => self.decay,
+            _ => self.control & 0x0f,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decay() {
+        let mut envelope = Envelope::new();
+
+        // a period of 1 takes two clocks per step
+        envelope.write_control(0x01);
+        envelope.restart();
+
+        let mut outputs = vec![];
+        for _ in 0..34 {
+            envelope.clock();
+            outputs.push(envelope.output());
+        }
+
+        assert_eq!(outputs[..5], [15, 15, 14, 14, 13]);
+        assert_eq!(outputs[30..], [0, 0, 0, 0]);
+
+        // looping goes back to 15
+        envelope.write_control(ENVELOPE_LOOP | 0x01);
+        envelope.clock();
+        envelope.clock();
+        assert_eq!(envelope.output(), 15);
+    }
+
+    #[test]
+    fn constant_volume() {
+        let mut envelope = Envelope::new();
+
+        envelope.write_control(ENVELOPE_CONSTANT_VOLUME | 0x07);
+        envelope.restart();
+        envelope.clock();
+        envelope.clock();
+
+        assert_eq!(envelope.output(), 7);
+    }
+}
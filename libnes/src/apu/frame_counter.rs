@@ -0,0 +1,153 @@
+const FIVE_STEP_MODE: u8 = 0x80;
+const IRQ_INHIBIT: u8 = 0x40;
+
+// The cpu cycles (ntsc) at which each mode's steps land
+const FOUR_STEP_SEQUENCE: [u16; 4] = [7457, 14913, 22371, 29829];
+const FIVE_STEP_SEQUENCE: [u16; 5] = [7457, 14913, 22371, 29829, 37281];
+
+// Each sequence starts over the cycle after its last step
+const FOUR_STEP_LENGTH: u16 = 29830;
+const FIVE_STEP_LENGTH: u16 = 37282;
+
+// Which of the channels' units a frame counter step clocks
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameEvent {
+    pub quarter_frame: bool,
+    pub half_frame: bool,
+}
+
+// Drives the envelopes, sweeps and length counters at (roughly) 240Hz,
+// and raises an irq at the end of each 4-step sequence,
+// see https://wiki.nesdev.com/w/index.php/APU_Frame_Counter
+#[derive(Default)]
+pub struct FrameCounter {
+    cycles: u16,
+    is_five_step: bool,
+    is_irq_inhibited: bool,
+    is_irq_pending: bool,
+}
+
+impl FrameCounter {
+    pub fn new() -> Self {
+        FrameCounter::default()
+    }
+
+    // $4017; the 5-step mode clocks every unit straight away
+    pub fn write_control(&mut self, val: u8) -> FrameEvent {
+        self.is_five_step = val & FIVE_STEP_MODE != 0;
+        self.is_irq_inhibited = val & IRQ_INHIBIT != 0;
+        self.cycles = 0;
+
+        if self.is_irq_inhibited {
+            self.is_irq_pending = false;
+        }
+
+        FrameEvent {
+            quarter_frame: self.is_five_step,
+            half_frame: self.is_five_step,
+        }
+    }
+
+    pub fn is_irq_pending(&self) -> bool {
+        self.is_irq_pending
+    }
+
+    // Reading $4015 acknowledges the irq
+    pub fn acknowledge_irq(&mut self) {
+        self.is_irq_pending = false;
+    }
+
+    // Called once per cpu cycle
+    pub fn clock(&mut self) -> FrameEvent {
+        let (sequence, length): (&[u16], u16) = match self.is_five_step {
+            true => (&FIVE_STEP_SEQUENCE, FIVE_STEP_LENGTH),
+            false => (&FOUR_STEP_SEQUENCE, FOUR_STEP_LENGTH),
+        };
+
+        self.cycles = (self.cycles + 1) % length;
+
+        let step = match sequence.iter().position(|cycle| *cycle == self.cycles) {
+            Some(step) => step,
+            None => return FrameEvent::default(),
+        };
+
+        let is_last_step = step == sequence.len() - 1;
+
+        if is_last_step && !self.is_five_step && !self.is_irq_inhibited {
+            self.is_irq_pending = true;
+        }
+
+        // the 5-step mode's fourth step does nothing
+        match (self.is_five_step, step) {
+            (true, 3) => FrameEvent::default(),
+            (_, step) => FrameEvent {
+                quarter_frame: true,
+                half_frame: step == 1 || is_last_step,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Clocks the frame counter, returning the cycle and event of each step it hits
+    fn run_sequence(frame_counter: &mut FrameCounter, cycles: u16) -> Vec<(u16, FrameEvent)> {
+        (1..=cycles)
+            .map(|cycle| (cycle, frame_counter.clock()))
+            .filter(|(_, event)| *event != FrameEvent::default())
+            .collect()
+    }
+
+    #[test]
+    fn four_step() {
+        let mut frame_counter = FrameCounter::new();
+
+        let steps = run_sequence(&mut frame_counter, 29829);
+        let cycles: Vec<u16> = steps.iter().map(|(cycle, _)| *cycle).collect();
+        let half_frames: Vec<bool> = steps.iter().map(|(_, event)| event.half_frame).collect();
+
+        assert_eq!(cycles, vec![7457, 14913, 22371, 29829]);
+        assert_eq!(half_frames, vec![false, true, false, true]);
+        assert_eq!(frame_counter.is_irq_pending(), true);
+
+        frame_counter.acknowledge_irq();
+        assert_eq!(frame_counter.is_irq_pending(), false);
+
+        // the next sequence starts at 29830, so its first step lands a cycle later
+        let steps = run_sequence(&mut frame_counter, 1 + 7457);
+        let cycles: Vec<u16> = steps.iter().map(|(cycle, _)| 29829 + *cycle).collect();
+
+        assert_eq!(cycles, vec![29830 + 7457]);
+    }
+
+    #[test]
+    fn five_step() {
+        let mut frame_counter = FrameCounter::new();
+
+        let event = frame_counter.write_control(FIVE_STEP_MODE);
+        assert_eq!(event.half_frame, true);
+
+        let steps = run_sequence(&mut frame_counter, 37281);
+        let cycles: Vec<u16> = steps.iter().map(|(cycle, _)| *cycle).collect();
+
+        assert_eq!(cycles, vec![7457, 14913, 22371, 37281]);
+        assert_eq!(frame_counter.is_irq_pending(), false);
+    }
+
+    #[test]
+    fn irq_inhibit() {
+        let mut frame_counter = FrameCounter::new();
+
+        run_sequence(&mut frame_counter, 29829);
+        assert_eq!(frame_counter.is_irq_pending(), true);
+
+        // setting the inhibit flag clears the irq too
+        frame_counter.write_control(IRQ_INHIBIT);
+        assert_eq!(frame_counter.is_irq_pending(), false);
+
+        run_sequence(&mut frame_counter, 29829);
+        assert_eq!(frame_counter.is_irq_pending(), false);
+    }
+}
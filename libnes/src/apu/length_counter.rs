@@ -0,0 +1,91 @@
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+// Silences a channel once it has counted down (on the frame counter's half
+// frames), unless halted; it can only be loaded while its channel is enabled,
+// see https://wiki.nesdev.com/w/index.php/APU_Length_Counter
+#[derive(Default)]
+pub struct LengthCounter {
+    counter: u8,
+    is_enabled: bool,
+    is_halted: bool,
+}
+
+impl LengthCounter {
+    pub fn new() -> Self {
+        LengthCounter::default()
+    }
+
+    // Disabling the channel ($4015) clears the counter
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+
+        if !is_enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub fn set_halted(&mut self, is_halted: bool) {
+        self.is_halted = is_halted;
+    }
+
+    // Loads the counter from the top 5 bits of a channel's last register
+    pub fn load(&mut self, val: u8) {
+        if self.is_enabled {
+            self.counter = LENGTH_TABLE[(val >> 3) as usize];
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.counter > 0 && !self.is_halted {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts_down() {
+        let mut length = LengthCounter::new();
+
+        // index 3 is a length of 2
+        length.set_enabled(true);
+        length.load(0x18);
+
+        length.clock();
+        assert_eq!(length.is_active(), true);
+
+        length.clock();
+        assert_eq!(length.is_active(), false);
+    }
+
+    #[test]
+    fn halt_and_enable() {
+        let mut length = LengthCounter::new();
+
+        // loading while disabled does nothing
+        length.load(0x08);
+        assert_eq!(length.is_active(), false);
+
+        length.set_enabled(true);
+        length.load(0x08);
+        length.set_halted(true);
+
+        for _ in 0..300 {
+            length.clock();
+        }
+        assert_eq!(length.is_active(), true);
+
+        length.set_enabled(false);
+        assert_eq!(length.is_active(), false);
+    }
+}
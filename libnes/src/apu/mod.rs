@@ -0,0 +1,499 @@
+pub mod dmc;
+pub mod envelope;
+pub mod frame_counter;
+pub mod length_counter;
+pub mod noise;
+pub mod pulse;
+pub mod sweep;
+pub mod timer;
+pub mod triangle;
+
+use crate::cart::mappers::Mapper;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use dmc::Dmc;
+use frame_counter::{FrameCounter, FrameEvent};
+use noise::Noise;
+use pulse::Pulse;
+use triangle::Triangle;
+
+pub const SQ1_VOL: u16 = 0x4000;
+pub const SQ1_SWEEP: u16 = 0x4001;
+pub const SQ1_LO: u16 = 0x4002;
+pub const SQ1_HI: u16 = 0x4003;
+pub const SQ2_VOL: u16 = 0x4004;
+pub const SQ2_SWEEP: u16 = 0x4005;
+pub const SQ2_LO: u16 = 0x4006;
+pub const SQ2_HI: u16 = 0x4007;
+pub const TRI_LINEAR: u16 = 0x4008;
+pub const TRI_LO: u16 = 0x400a;
+pub const TRI_HI: u16 = 0x400b;
+pub const NOISE_VOL: u16 = 0x400c;
+pub const NOISE_LO: u16 = 0x400e;
+pub const NOISE_HI: u16 = 0x400f;
+pub const DMC_FREQ: u16 = 0x4010;
+pub const DMC_RAW: u16 = 0x4011;
+pub const DMC_START: u16 = 0x4012;
+pub const DMC_LEN: u16 = 0x4013;
+pub const SND_CHN: u16 = 0x4015;
+pub const FRAME_COUNTER: u16 = 0x4017;
+
+const STATUS_PULSE_1: u8 = 0x01;
+const STATUS_PULSE_2: u8 = 0x02;
+const STATUS_TRIANGLE: u8 = 0x04;
+const STATUS_NOISE: u8 = 0x08;
+const STATUS_DMC: u8 = 0x10;
+const STATUS_FRAME_IRQ: u8 = 0x40;
+const STATUS_DMC_IRQ: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+}
+
+pub trait Apu {
+    // Called once per cpu cycle
+    fn clock(&mut self);
+
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>);
+    fn set_region(&mut self, region: Region);
+
+    // Reads SND_CHN, or writes one of the registers at SQ1_VOL..=FRAME_COUNTER
+    fn read_register(&mut self, addr: u16) -> u8;
+    fn write_register(&mut self, addr: u16, val: u8);
+
+    // The mix of every channel (and the cart's expansion audio), from 0.0 up
+    fn output(&self) -> f32;
+
+    // State of the apu's /IRQ output
+    fn is_irq_asserted(&self) -> bool;
+
+    // The address of the sample byte the dmc is waiting on, if any
+    fn pending_dmc_fetch(&self) -> Option<u16>;
+    // Hands the dmc the byte it asked for
+    fn load_dmc_sample(&mut self, val: u8);
+}
+
+// see https://wiki.nesdev.com/w/index.php/APU
+pub struct DefaultApu {
+    pulses: [Pulse; 2],
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+
+    // the pulses' timers only tick every other cpu cycle
+    is_odd_cycle: bool,
+
+    cart: Option<Rc<RefCell<Mapper>>>,
+    expansion_audio: f32,
+}
+
+impl Apu for DefaultApu {
+    fn clock(&mut self) {
+        let event = self.frame_counter.clock();
+        self.clock_frame_event(event);
+
+        if self.is_odd_cycle {
+            self.pulses[0].clock_timer();
+            self.pulses[1].clock_timer();
+        }
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+
+        self.is_odd_cycle = !self.is_odd_cycle;
+
+        if let Some(cart) = &self.cart {
+            self.expansion_audio = cart.borrow().audio_output();
+        }
+    }
+
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>) {
+        self.cart = Some(cart);
+    }
+
+    fn set_region(&mut self, region: Region) {
+        self.noise.set_region(region);
+        self.dmc.set_region(region);
+    }
+
+    fn read_register(&mut self, addr: u16) -> u8 {
+        match addr {
+            SND_CHN => {
+                let mut status = 0;
+
+                if self.pulses[0].is_active() {
+                    status |= STATUS_PULSE_1;
+                }
+
+                if self.pulses[1].is_active() {
+                    status |= STATUS_PULSE_2;
+                }
+
+                if self.triangle.is_active() {
+                    status |= STATUS_TRIANGLE;
+                }
+
+                if self.noise.is_active() {
+                    status |= STATUS_NOISE;
+                }
+
+                if self.dmc.is_active() {
+                    status |= STATUS_DMC;
+                }
+
+                if self.frame_counter.is_irq_pending() {
+                    status |= STATUS_FRAME_IRQ;
+                }
+
+                if self.dmc.is_irq_pending() {
+                    status |= STATUS_DMC_IRQ;
+                }
+
+                self.frame_counter.acknowledge_irq();
+
+                status
+            }
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        match addr {
+            SQ1_VOL...SQ1_HI => self.pulses[0].write_register(addr & 0x03, val),
+            SQ2_VOL...SQ2_HI => self.pulses[1].write_register(addr & 0x03, val),
+            TRI_LINEAR...TRI_HI => self.triangle.write_register(addr & 0x03, val),
+            NOISE_VOL...NOISE_HI => self.noise.write_register(addr & 0x03, val),
+            DMC_FREQ...DMC_LEN => self.dmc.write_register(addr & 0x03, val),
+            SND_CHN => {
+                self.pulses[0].set_enabled(val & STATUS_PULSE_1 != 0);
+                self.pulses[1].set_enabled(val & STATUS_PULSE_2 != 0);
+                self.triangle.set_enabled(val & STATUS_TRIANGLE != 0);
+                self.noise.set_enabled(val & STATUS_NOISE != 0);
+                self.dmc.set_enabled(val & STATUS_DMC != 0);
+            }
+            FRAME_COUNTER => {
+                let event = self.frame_counter.write_control(val);
+                self.clock_frame_event(event);
+            }
+            _ => {}
+        }
+    }
+
+    // The pulses and the triangle/noise/dmc are mixed non-linearly, as two groups,
+    // see https://wiki.nesdev.com/w/index.php/APU_Mixer
+    fn output(&self) -> f32 {
+        let pulses = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+
+        let pulse_out = match pulses as u8 {
+            0 => 0.0,
+            _ => 95.88 / (8128.0 / pulses + 100.0),
+        };
+
+        let triangle = self.triangle.output() as f32;
+        let noise = self.noise.output() as f32;
+        let dmc = self.dmc.output() as f32;
+
+        let tnd_out = match (triangle + noise + dmc) as u16 {
+            0 => 0.0,
+            _ => {
+                let tnd = triangle / 8227.0 + noise / 12241.0 + dmc / 22638.0;
+
+                159.79 / (1.0 / tnd + 100.0)
+            }
+        };
+
+        pulse_out + tnd_out + self.expansion_audio
+    }
+
+    fn is_irq_asserted(&self) -> bool {
+        self.frame_counter.is_irq_pending() || self.dmc.is_irq_pending()
+    }
+
+    fn pending_dmc_fetch(&self) -> Option<u16> {
+        self.dmc.pending_fetch()
+    }
+
+    fn load_dmc_sample(&mut self, val: u8) {
+        self.dmc.load_sample(val);
+    }
+}
+
+impl Default for DefaultApu {
+    fn default() -> Self {
+        DefaultApu {
+            pulses: [Pulse::new(true), Pulse::new(false)],
+            triangle: Triangle::new(),
+            noise: Noise::new(Region::Ntsc),
+            dmc: Dmc::new(Region::Ntsc),
+            frame_counter: FrameCounter::new(),
+            is_odd_cycle: false,
+            cart: None,
+            expansion_audio: 0.0,
+        }
+    }
+}
+
+impl DefaultApu {
+    pub fn new() -> Self {
+        DefaultApu::default()
+    }
+
+    fn clock_frame_event(&mut self, event: FrameEvent) {
+        for pulse in self.pulses.iter_mut() {
+            if event.quarter_frame {
+                pulse.clock_quarter_frame();
+            }
+
+            if event.half_frame {
+                pulse.clock_half_frame();
+            }
+        }
+
+        if event.quarter_frame {
+            self.triangle.clock_quarter_frame();
+            self.noise.clock_quarter_frame();
+        }
+
+        if event.half_frame {
+            self.triangle.clock_half_frame();
+            self.noise.clock_half_frame();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(apu: &mut DefaultApu, registers: &[(u16, u8)]) {
+        for (addr, val) in registers.iter() {
+            apu.write_register(*addr, *val);
+        }
+    }
+
+    // The pulses' outputs after every 2 cpu cycles (one apu cycle)
+    fn pulse_samples(apu: &mut DefaultApu, pulse: usize, num_samples: usize) -> Vec<u8> {
+        (0..num_samples)
+            .map(|_| {
+                apu.clock();
+                apu.clock();
+
+                apu.pulses[pulse].output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pulse_duty_cycles() {
+        let mut apu = DefaultApu::new();
+
+        // 50% duty at a constant volume of 12, with a period of 8
+        // (so 9 apu cycles per step)
+        write(
+            &mut apu,
+            &[
+                (SND_CHN, 0x01),
+                (SQ1_VOL, 0xbc),
+                (SQ1_LO, 0x08),
+                (SQ1_HI, 0x08),
+            ],
+        );
+
+        let samples = pulse_samples(&mut apu, 0, 9 * 8);
+        let steps: Vec<u8> = samples.chunks(9).map(|step| step[0]).collect();
+
+        assert_eq!(steps, vec![12, 12, 12, 12, 0, 0, 0, 0]);
+
+        // 25% negated
+        write(&mut apu, &[(SQ1_VOL, 0xfc), (SQ1_HI, 0x08)]);
+
+        let samples = pulse_samples(&mut apu, 0, 9 * 8);
+        let steps: Vec<u8> = samples.chunks(9).map(|step| step[0]).collect();
+
+        assert_eq!(steps, vec![0, 0, 12, 12, 12, 12, 12, 12]);
+    }
+
+    #[test]
+    fn pulse_envelope() {
+        let mut apu = DefaultApu::new();
+
+        // 12.5% duty with a decaying envelope, period 0 (one step per quarter frame)
+        write(
+            &mut apu,
+            &[
+                (SND_CHN, 0x02),
+                (SQ2_VOL, 0x00),
+                (SQ2_LO, 0x10),
+                (SQ2_HI, 0x00),
+            ],
+        );
+
+        // the volume steps down on each quarter frame
+        let mut volumes: Vec<u8> = pulse_samples(&mut apu, 1, 15000)
+            .into_iter()
+            .filter(|sample| *sample != 0)
+            .collect();
+        volumes.dedup();
+
+        assert_eq!(volumes, vec![15, 14, 13, 12]);
+    }
+
+    #[test]
+    fn pulse_length_counter() {
+        let mut apu = DefaultApu::new();
+
+        // a length of 2 runs out after two half frames
+        write(
+            &mut apu,
+            &[
+                (SND_CHN, 0x01),
+                (SQ1_VOL, 0x1f),
+                (SQ1_LO, 0x10),
+                (SQ1_HI, 0x18),
+            ],
+        );
+        assert_eq!(apu.read_register(SND_CHN), STATUS_PULSE_1);
+
+        pulse_samples(&mut apu, 0, 15000);
+        assert_eq!(apu.read_register(SND_CHN) & STATUS_PULSE_1, 0);
+
+        // disabling the channel silences it straight away
+        write(&mut apu, &[(SQ1_HI, 0x08)]);
+        assert_eq!(apu.read_register(SND_CHN), STATUS_PULSE_1);
+
+        write(&mut apu, &[(SND_CHN, 0x00)]);
+        assert_eq!(apu.read_register(SND_CHN), 0);
+    }
+
+    #[test]
+    fn pulse_sweep() {
+        let mut apu = DefaultApu::new();
+
+        // pulse 1 sweeping down by half on each half frame, with the 5-step
+        // sequence's immediate clock kicking it off
+        write(
+            &mut apu,
+            &[
+                (SND_CHN, 0x01),
+                (SQ1_VOL, 0xbf),
+                (SQ1_SWEEP, 0x89),
+                (SQ1_LO, 0x00),
+                (SQ1_HI, 0x09),
+                (FRAME_COUNTER, 0x80),
+            ],
+        );
+
+        assert!(pulse_samples(&mut apu, 0, 100)
+            .iter()
+            .any(|sample| *sample != 0));
+
+        // which ends up muting it once the period drops under 8
+        pulse_samples(&mut apu, 0, 37281 * 3);
+        assert!(pulse_samples(&mut apu, 0, 100)
+            .iter()
+            .all(|sample| *sample == 0));
+
+        // a target period past $7ff mutes pulse 2, even with its sweep disabled
+        write(
+            &mut apu,
+            &[
+                (SND_CHN, 0x03),
+                (SQ2_VOL, 0xbf),
+                (SQ2_SWEEP, 0x00),
+                (SQ2_LO, 0x00),
+                (SQ2_HI, 0x0c),
+            ],
+        );
+        assert!(pulse_samples(&mut apu, 1, 1000)
+            .iter()
+            .all(|sample| *sample == 0));
+    }
+
+    #[test]
+    fn frame_irq() {
+        let mut apu = DefaultApu::new();
+
+        for _ in 0..29829 {
+            apu.clock();
+        }
+
+        assert_eq!(apu.is_irq_asserted(), true);
+
+        // reading the status acknowledges it
+        assert_eq!(apu.read_register(SND_CHN), STATUS_FRAME_IRQ);
+        assert_eq!(apu.is_irq_asserted(), false);
+    }
+
+    #[test]
+    fn status() {
+        let mut apu = DefaultApu::new();
+
+        // the triangle and noise report their length counters, the dmc its bytes remaining
+        write(
+            &mut apu,
+            &[
+                (SND_CHN, 0x1c),
+                (TRI_LINEAR, 0x7f),
+                (TRI_HI, 0x08),
+                (NOISE_VOL, 0x1f),
+                (NOISE_HI, 0x08),
+                (DMC_FREQ, 0x8f),
+                (DMC_START, 0x00),
+                (DMC_LEN, 0x00),
+            ],
+        );
+        assert_eq!(
+            apu.read_register(SND_CHN),
+            STATUS_TRIANGLE | STATUS_NOISE | STATUS_DMC
+        );
+        assert_eq!(apu.pending_dmc_fetch(), Some(0xc000));
+
+        // once the dmc's only byte is loaded, it raises its irq
+        apu.load_dmc_sample(0x00);
+        assert_eq!(apu.is_irq_asserted(), true);
+        assert_eq!(
+            apu.read_register(SND_CHN),
+            STATUS_TRIANGLE | STATUS_NOISE | STATUS_DMC_IRQ
+        );
+
+        // which, unlike the frame irq, is only acknowledged by writing the status
+        assert_eq!(apu.is_irq_asserted(), true);
+
+        write(&mut apu, &[(SND_CHN, 0x00)]);
+        assert_eq!(apu.is_irq_asserted(), false);
+        assert_eq!(apu.read_register(SND_CHN), 0);
+    }
+
+    #[test]
+    fn mixer() {
+        let mut apu = DefaultApu::new();
+
+        // the triangle idles on the first step of its sequence, which still drives the mixer
+        let idle = apu.output();
+        assert!((idle - 0.2464).abs() < 0.0001);
+
+        // both pulses at a constant 15, 50% duty
+        write(
+            &mut apu,
+            &[
+                (SND_CHN, 0x03),
+                (SQ1_VOL, 0xbf),
+                (SQ1_LO, 0x10),
+                (SQ1_HI, 0x08),
+                (SQ2_VOL, 0xbf),
+                (SQ2_LO, 0x10),
+                (SQ2_HI, 0x08),
+            ],
+        );
+
+        apu.clock();
+        apu.clock();
+
+        assert!((apu.output() - idle - 0.2585).abs() < 0.0001);
+    }
+}
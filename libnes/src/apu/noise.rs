@@ -0,0 +1,179 @@
+use crate::apu::envelope::Envelope;
+use crate::apu::length_counter::LengthCounter;
+use crate::apu::timer::Timer;
+use crate::apu::Region;
+
+// Timer periods in cpu cycles
+const NTSC_PERIODS: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+const PAL_PERIODS: [u16; 16] = [
+    4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+
+const LENGTH_COUNTER_HALT: u8 = 0x20;
+
+// Feeds back from bit 6 instead of bit 1, for a short, metallic loop
+const SHORT_MODE: u8 = 0x80;
+
+// $400c-$400f: a 15-bit lfsr played through an envelope,
+// see https://wiki.nesdev.com/w/index.php/APU_Noise
+pub struct Noise {
+    region: Region,
+    control: u8,
+    lfsr: u16,
+
+    timer: Timer,
+    envelope: Envelope,
+    length: LengthCounter,
+}
+
+impl Noise {
+    pub fn new(region: Region) -> Self {
+        let mut noise = Noise {
+            region,
+            control: 0,
+            lfsr: 1,
+            timer: Timer::new(),
+            envelope: Envelope::new(),
+            length: LengthCounter::new(),
+        };
+
+        noise.update_period();
+
+        noise
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.update_period();
+    }
+
+    fn update_period(&mut self) {
+        let periods = match self.region {
+            Region::Ntsc => &NTSC_PERIODS,
+            Region::Pal => &PAL_PERIODS,
+        };
+
+        self.timer.period = periods[(self.control & 0x0f) as usize] - 1;
+    }
+
+    pub fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0 => {
+                self.envelope.write_control(val);
+                self.length.set_halted(val & LENGTH_COUNTER_HALT != 0);
+            }
+            2 => {
+                self.control = val;
+                self.update_period();
+            }
+            3 => {
+                self.length.load(val);
+                self.envelope.restart();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.length.set_enabled(is_enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length.is_active()
+    }
+
+    // Called once per cpu cycle, as the periods are in cpu cycles
+    pub fn clock_timer(&mut self) {
+        if !self.timer.clock() {
+            return;
+        }
+
+        let tap = match self.control & SHORT_MODE {
+            0 => 1,
+            _ => 6,
+        };
+
+        let feedback = (self.lfsr ^ (self.lfsr >> tap)) & 0x01;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        match !self.length.is_active() || self.lfsr & 0x01 != 0 {
+            true => 0,
+            false => self.envelope.output(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Runs the lfsr until it's back where it started
+    fn lfsr_period(noise: &mut Noise) -> usize {
+        let start = noise.lfsr;
+        let mut steps = 0;
+
+        loop {
+            for _ in 0..4 {
+                noise.clock_timer();
+            }
+
+            steps += 1;
+
+            if noise.lfsr == start {
+                return steps;
+            }
+        }
+    }
+
+    #[test]
+    fn lfsr_modes() {
+        let mut noise = Noise::new(Region::Ntsc);
+
+        // the shortest period, of 4 cpu cycles
+        noise.write_register(2, 0x00);
+        noise.clock_timer();
+        assert_eq!(lfsr_period(&mut noise), 32767);
+
+        noise.write_register(2, SHORT_MODE);
+        assert_eq!(lfsr_period(&mut noise), 93);
+    }
+
+    #[test]
+    fn output() {
+        let mut noise = Noise::new(Region::Ntsc);
+
+        noise.set_enabled(true);
+        noise.write_register(0, 0x1a);
+        noise.write_register(3, 0x08);
+
+        // bit 0 of the lfsr silences the channel
+        assert_eq!(noise.output(), 0);
+
+        noise.clock_timer();
+        assert_eq!(noise.lfsr, 0x4000);
+        assert_eq!(noise.output(), 10);
+    }
+
+    #[test]
+    fn periods() {
+        let mut noise = Noise::new(Region::Ntsc);
+
+        noise.write_register(2, 0x0f);
+        assert_eq!(noise.timer.period, 4067);
+
+        noise.set_region(Region::Pal);
+        assert_eq!(noise.timer.period, 3777);
+    }
+}
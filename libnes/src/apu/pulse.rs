@@ -0,0 +1,149 @@
+use crate::apu::envelope::Envelope;
+use crate::apu::length_counter::LengthCounter;
+use crate::apu::sweep::Sweep;
+use crate::apu::timer::Timer;
+
+// The 8-step waveform of each duty cycle (12.5%, 25%, 50% and 25% negated)
+pub const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+const LENGTH_COUNTER_HALT: u8 = 0x20;
+
+// $4000-$4003 and $4004-$4007,
+// see https://wiki.nesdev.com/w/index.php/APU_Pulse
+pub struct Pulse {
+    duty: u8,
+    step: usize,
+
+    timer: Timer,
+    envelope: Envelope,
+    sweep: Sweep,
+    length: LengthCounter,
+}
+
+impl Pulse {
+    // Pulse 1's sweep negates a little differently from pulse 2's
+    pub fn new(is_pulse_1: bool) -> Self {
+        Pulse {
+            duty: 0,
+            step: 0,
+            timer: Timer::new(),
+            envelope: Envelope::new(),
+            sweep: Sweep::new(is_pulse_1),
+            length: LengthCounter::new(),
+        }
+    }
+
+    pub fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0 => {
+                self.duty = val >> 6;
+                self.envelope.write_control(val);
+                self.length.set_halted(val & LENGTH_COUNTER_HALT != 0);
+            }
+            1 => self.sweep.write_control(val),
+            2 => self.timer.write_period_lo(val),
+            _ => {
+                self.timer.write_period_hi(val);
+                self.length.load(val);
+
+                self.step = 0;
+                self.envelope.restart();
+            }
+        }
+    }
+
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.length.set_enabled(is_enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length.is_active()
+    }
+
+    // Called once per apu cycle (every other cpu cycle)
+    pub fn clock_timer(&mut self) {
+        if self.timer.clock() {
+            self.step = (self.step + 1) % 8;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+        self.sweep.clock(&mut self.timer.period);
+    }
+
+    pub fn output(&self) -> u8 {
+        let is_silent = !self.length.is_active()
+            || self.sweep.is_muting(self.timer.period)
+            || DUTY_SEQUENCES[self.duty as usize][self.step] == 0;
+
+        match is_silent {
+            true => 0,
+            false => self.envelope.output(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pulse(is_pulse_1: bool, period: u16) -> Pulse {
+        let mut pulse = Pulse::new(is_pulse_1);
+
+        pulse.set_enabled(true);
+        pulse.write_register(0, 0xbf);
+        pulse.write_register(2, period as u8);
+        pulse.write_register(3, 0x08 | (period >> 8) as u8);
+
+        pulse
+    }
+
+    #[test]
+    fn sweep_negate() {
+        // negated, with a shift of 2
+        let mut pulse_1 = pulse(true, 0x200);
+        let mut pulse_2 = pulse(false, 0x200);
+
+        pulse_1.write_register(1, 0x8a);
+        pulse_2.write_register(1, 0x8a);
+
+        pulse_1.clock_half_frame();
+        pulse_2.clock_half_frame();
+
+        // pulse 1 subtracts one more than pulse 2
+        assert_eq!(pulse_1.timer.period, 0x17f);
+        assert_eq!(pulse_2.timer.period, 0x180);
+    }
+
+    #[test]
+    fn sweep_mutes() {
+        let mut pulse = pulse(false, 0x10);
+
+        pulse.clock_timer();
+        assert_eq!(pulse.output(), 15);
+
+        // sweeping down by half each time
+        pulse.write_register(1, 0x89);
+        pulse.clock_half_frame();
+        assert_eq!(pulse.timer.period, 0x08);
+        assert_eq!(pulse.output(), 15);
+
+        pulse.clock_half_frame();
+        assert_eq!(pulse.timer.period, 0x04);
+        assert_eq!(pulse.output(), 0);
+
+        // and then stops, as it's muted
+        pulse.clock_half_frame();
+        assert_eq!(pulse.timer.period, 0x04);
+    }
+}
@@ -0,0 +1,124 @@
+const SWEEP_ENABLE: u8 = 0x80;
+const SWEEP_NEGATE: u8 = 0x08;
+
+// Periods past this (or under 8) silence the channel
+const MAX_TARGET_PERIOD: u16 = 0x07ff;
+const MIN_PERIOD: u16 = 8;
+
+// Bends a pulse channel's period up or down on the frame counter's half frames,
+// see https://wiki.nesdev.com/w/index.php/APU_Sweep
+pub struct Sweep {
+    control: u8,
+    divider: u8,
+    is_reload: bool,
+
+    // pulse 1 negates with ones' complement (subtracting one more than pulse 2)
+    is_ones_complement: bool,
+}
+
+impl Sweep {
+    pub fn new(is_ones_complement: bool) -> Self {
+        Sweep {
+            control: 0,
+            divider: 0,
+            is_reload: false,
+            is_ones_complement,
+        }
+    }
+
+    // $4001/$4005
+    pub fn write_control(&mut self, val: u8) {
+        self.control = val;
+        self.is_reload = true;
+    }
+
+    fn period(&self) -> u8 {
+        (self.control >> 4) & 0x07
+    }
+
+    fn shift(&self) -> u8 {
+        self.control & 0x07
+    }
+
+    pub fn target_period(&self, period: u16) -> u16 {
+        let change = period >> self.shift();
+
+        match (self.control & SWEEP_NEGATE != 0, self.is_ones_complement) {
+            (false, _) => period + change,
+            (true, true) => period.saturating_sub(change + 1),
+            (true, false) => period.saturating_sub(change),
+        }
+    }
+
+    // The channel is silenced by these even with the sweep disabled
+    pub fn is_muting(&self, period: u16) -> bool {
+        period < MIN_PERIOD || self.target_period(period) > MAX_TARGET_PERIOD
+    }
+
+    pub fn clock(&mut self, period: &mut u16) {
+        let is_sweeping = self.control & SWEEP_ENABLE != 0 && self.shift() > 0;
+
+        if self.divider == 0 && is_sweeping && !self.is_muting(*period) {
+            *period = self.target_period(*period);
+        }
+
+        match (self.divider, self.is_reload) {
+            (0, _) | (_, true) => {
+                self.divider = self.period();
+                self.is_reload = false;
+            }
+            _ => self.divider -= 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negate() {
+        // shift 2, negated
+        let mut pulse_1 = Sweep::new(true);
+        pulse_1.write_control(SWEEP_NEGATE | 0x02);
+
+        let mut pulse_2 = Sweep::new(false);
+        pulse_2.write_control(SWEEP_NEGATE | 0x02);
+
+        assert_eq!(pulse_1.target_period(0x100), 0xbf);
+        assert_eq!(pulse_2.target_period(0x100), 0xc0);
+
+        pulse_1.write_control(0x02);
+        assert_eq!(pulse_1.target_period(0x100), 0x140);
+    }
+
+    #[test]
+    fn muting() {
+        let sweep = Sweep::new(false);
+
+        assert_eq!(sweep.is_muting(0x07), true);
+        assert_eq!(sweep.is_muting(0x08), false);
+
+        // a shift of 0 doubles the period, which overflows past $3ff
+        assert_eq!(sweep.is_muting(0x3ff), false);
+        assert_eq!(sweep.is_muting(0x400), true);
+    }
+
+    #[test]
+    fn sweeps_once_per_period() {
+        let mut sweep = Sweep::new(false);
+
+        // enabled, a divider period of 1, shift 1
+        sweep.write_control(SWEEP_ENABLE | 0x10 | 0x01);
+
+        let mut period = 0x100;
+        let mut periods = vec![];
+        for _ in 0..5 {
+            sweep.clock(&mut period);
+            periods.push(period);
+        }
+
+        // the divider starts out at 0, so the first clock sweeps straight away
+        assert_eq!(periods, vec![0x180, 0x180, 0x240, 0x240, 0x360]);
+    }
+}
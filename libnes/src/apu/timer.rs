@@ -0,0 +1,36 @@
+// The divider that sets a channel's frequency: counts down from its period,
+// and clocks the channel's sequencer each time it reloads
+#[derive(Default)]
+pub struct Timer {
+    pub period: u16,
+    counter: u16,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer::default()
+    }
+
+    pub fn write_period_lo(&mut self, val: u8) {
+        self.period = (self.period & 0x0700) | val as u16;
+    }
+
+    // The low 3 bits of a channel's last register
+    pub fn write_period_hi(&mut self, val: u8) {
+        self.period = (self.period & 0x00ff) | (((val & 0x07) as u16) << 8);
+    }
+
+    // Returns whether the timer ran out and reloaded
+    pub fn clock(&mut self) -> bool {
+        match self.counter {
+            0 => {
+                self.counter = self.period;
+                true
+            }
+            _ => {
+                self.counter -= 1;
+                false
+            }
+        }
+    }
+}
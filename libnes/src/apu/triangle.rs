@@ -0,0 +1,160 @@
+use crate::apu::length_counter::LengthCounter;
+use crate::apu::timer::Timer;
+
+const TRIANGLE_SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+// Halts the length counter, and keeps the linear counter reloading
+const LINEAR_COUNTER_CONTROL: u8 = 0x80;
+
+// $4008-$400b; the triangle has no volume control, and runs at twice the
+// pulses' rate (its timer ticks every cpu cycle),
+// see https://wiki.nesdev.com/w/index.php/APU_Triangle
+#[derive(Default)]
+pub struct Triangle {
+    step: usize,
+    timer: Timer,
+    length: LengthCounter,
+
+    linear_control: u8,
+    linear_counter: u8,
+    is_linear_reload: bool,
+}
+
+impl Triangle {
+    pub fn new() -> Self {
+        Triangle::default()
+    }
+
+    pub fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0 => {
+                self.linear_control = val;
+                self.length.set_halted(val & LINEAR_COUNTER_CONTROL != 0);
+            }
+            2 => self.timer.write_period_lo(val),
+            3 => {
+                self.timer.write_period_hi(val);
+                self.length.load(val);
+                self.is_linear_reload = true;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.length.set_enabled(is_enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length.is_active()
+    }
+
+    // The sequencer only moves while both counters are running, so a silenced
+    // triangle holds its last output rather than dropping to 0
+    pub fn clock_timer(&mut self) {
+        if self.timer.clock() && self.length.is_active() && self.linear_counter > 0 {
+            self.step = (self.step + 1) % TRIANGLE_SEQUENCE.len();
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        match self.is_linear_reload {
+            true => self.linear_counter = self.linear_control & 0x7f,
+            false => self.linear_counter = self.linear_counter.saturating_sub(1),
+        }
+
+        if self.linear_control & LINEAR_COUNTER_CONTROL == 0 {
+            self.is_linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        TRIANGLE_SEQUENCE[self.step]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A triangle with a period of 1 (two cycles per step)
+    fn new_triangle(linear_control: u8) -> Triangle {
+        let mut triangle = Triangle::new();
+
+        triangle.set_enabled(true);
+        triangle.write_register(0, linear_control);
+        triangle.write_register(2, 0x01);
+        triangle.write_register(3, 0x08);
+
+        triangle
+    }
+
+    fn samples(triangle: &mut Triangle, num_samples: usize) -> Vec<u8> {
+        (0..num_samples)
+            .map(|_| {
+                triangle.clock_timer();
+                triangle.clock_timer();
+
+                triangle.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sequence() {
+        let mut triangle = new_triangle(0x7f);
+
+        // nothing moves until a quarter frame loads the linear counter
+        assert_eq!(samples(&mut triangle, 4), vec![15, 15, 15, 15]);
+
+        triangle.clock_quarter_frame();
+
+        let samples = samples(&mut triangle, 34);
+        assert_eq!(samples[..4], [14, 13, 12, 11]);
+        assert_eq!(samples[13..19], [1, 0, 0, 1, 2, 3]);
+        assert_eq!(samples[29..], [14, 15, 15, 14, 13]);
+    }
+
+    #[test]
+    fn linear_counter() {
+        // a linear counter of 2, which stops reloading once it's been loaded
+        let mut triangle = new_triangle(0x02);
+
+        triangle.clock_quarter_frame();
+        triangle.clock_quarter_frame();
+        assert_eq!(samples(&mut triangle, 3), vec![14, 13, 12]);
+
+        // it runs out and holds the output where it was
+        triangle.clock_quarter_frame();
+        assert_eq!(samples(&mut triangle, 3), vec![12, 12, 12]);
+
+        // with the control flag set, it reloads on every quarter frame
+        let mut triangle = new_triangle(0x82);
+
+        for _ in 0..10 {
+            triangle.clock_quarter_frame();
+        }
+        assert_eq!(samples(&mut triangle, 3), vec![14, 13, 12]);
+    }
+
+    #[test]
+    fn length_counter() {
+        // index 3 is a length of 2
+        let mut triangle = new_triangle(0x7f);
+        triangle.write_register(3, 0x18);
+        triangle.clock_quarter_frame();
+
+        triangle.clock_half_frame();
+        triangle.clock_half_frame();
+
+        assert_eq!(triangle.is_active(), false);
+        assert_eq!(samples(&mut triangle, 3), vec![15, 15, 15]);
+    }
+}
@@ -1,5 +1,3 @@
-use byteorder::{ByteOrder, LittleEndian};
-
 pub fn get_bit_val(num: u8, bit_num: usize) -> bool {
     guard_bit_op(bit_num);
 
@@ -22,10 +20,6 @@ pub fn set_bit_val(num: u8, bit_num: usize, val: bool) -> u8 {
     }
 }
 
-pub fn u16_from_u8s(val_lo: u8, val_hi: u8) -> u16 {
-    LittleEndian::read_u16(&[val_lo, val_hi])
-}
-
 pub fn to_bytes<'a>(byte_str: &'a str) -> Vec<u8> {
     byte_str
         .split(" ")
@@ -1,11 +1,241 @@
-use crate::mem::{Address, MemoryMap};
+use crate::apu::{Apu, SND_CHN};
+use crate::cart::mappers::Mapper;
+use crate::cpu::mem::{Address, CpuMemoryAccessEvent, CpuMemoryMap};
+use crate::ev::{Observable, Subject};
+use crate::ppu::{Ppu, OAMDMA};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
+pub const RAM_SIZE: usize = 0x0800;
+
+pub const PPU_REGISTERS_START_ADDR: u16 = 0x2000;
+
+// The controller ports sit in the middle of the apu's registers
+pub const JOY1: u16 = 0x4016;
+
+// The cpu's view of the system: decodes each address to the device behind it,
+// see https://wiki.nesdev.com/w/index.php/CPU_memory_map
 pub trait Bus {
+    fn read(&self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, val: u8);
 }
 
 pub struct DefaultBus {
-    pub memory: Box<MemoryMap>,
+    ram: [u8; RAM_SIZE],
+    ppu: Rc<RefCell<Ppu>>,
+    apu: Rc<RefCell<Apu>>,
+
+    cart: Option<Rc<RefCell<Mapper>>>,
+
+    // The last value driven onto the data bus, which unmapped reads return
+    open_bus: Cell<u8>,
+
+    subject: Subject<CpuMemoryAccessEvent>,
+}
+
+impl DefaultBus {
+    pub fn new(ppu: Rc<RefCell<Ppu>>, apu: Rc<RefCell<Apu>>) -> Self {
+        DefaultBus {
+            ram: [0; RAM_SIZE],
+            ppu,
+            apu,
+            cart: None,
+            open_bus: Cell::new(0),
+            subject: Subject::new(),
+        }
+    }
+
+    pub fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>) {
+        self.cart = Some(cart);
+    }
 }
 
 impl Bus for DefaultBus {
-}
\ No newline at end of file
+    fn read(&self, addr: u16) -> u8 {
+        let val = match addr {
+            0x0000...0x1fff => self.ram[(addr as usize) % RAM_SIZE],
+            0x2000...0x3fff => self
+                .ppu
+                .borrow_mut()
+                .read_register(PPU_REGISTERS_START_ADDR | (addr & 0x07)),
+            SND_CHN => self.apu.borrow_mut().read_register(addr),
+            // the controller ports aren't emulated yet, and the rest are write-only
+            0x4000...0x401f => self.open_bus.get(),
+            _ => match &self.cart {
+                Some(cart) => cart
+                    .borrow_mut()
+                    .cpu_read(addr)
+                    .unwrap_or(self.open_bus.get()),
+                None => self.open_bus.get(),
+            },
+        };
+
+        self.open_bus.set(val);
+
+        val
+    }
+
+    fn write(&mut self, addr: u16, val: u8) {
+        self.open_bus.set(val);
+
+        match addr {
+            0x0000...0x1fff => self.ram[(addr as usize) % RAM_SIZE] = val,
+            0x2000...0x3fff => {
+                let addr = PPU_REGISTERS_START_ADDR | (addr & 0x07);
+
+                self.ppu.borrow_mut().write_register(addr, val);
+
+                if let Some(cart) = &self.cart {
+                    cart.borrow_mut().ppu_register_write(addr, val);
+                }
+            }
+            OAMDMA => self.ppu.borrow_mut().request_oam_dma(val),
+            JOY1 => {}
+            0x4000...0x4017 => self.apu.borrow_mut().write_register(addr, val),
+            0x4018...0x401f => {}
+            _ => {
+                if let Some(cart) = &self.cart {
+                    cart.borrow_mut().cpu_write(addr, val);
+                }
+            }
+        }
+    }
+}
+
+impl CpuMemoryMap for DefaultBus {
+    fn get(&self, addr: &Address) -> u8 {
+        let val = self.read(addr.into());
+
+        // Notify subscribers
+        self.subject
+            .next(CpuMemoryAccessEvent::Get(addr.clone(), val));
+
+        val
+    }
+
+    fn set(&mut self, addr: &Address, val: u8) {
+        self.write(addr.into(), val);
+
+        // Notify subscribers
+        self.subject
+            .next(CpuMemoryAccessEvent::Set(addr.clone(), val));
+    }
+
+    fn subscribe(&mut self, handler: Box<FnMut(&CpuMemoryAccessEvent)>) {
+        self.subject.subscribe(handler);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::apu::{DefaultApu, SQ1_HI, SQ1_VOL};
+    use crate::cart::mappers::{MapperOptions, NROMMapper};
+    use crate::ppu::mem::Mirroring;
+    use crate::ppu::{DefaultPpu, OAMADDR, OAMDATA, PPUADDR, PPUDATA};
+    use crate::util::rc_ref;
+
+    fn new_bus() -> (DefaultBus, Rc<RefCell<DefaultPpu>>) {
+        let ppu = rc_ref(DefaultPpu::new());
+        let apu = rc_ref(DefaultApu::new());
+
+        (DefaultBus::new(ppu.clone(), apu), ppu)
+    }
+
+    #[test]
+    fn ram_mirrors() {
+        let (mut bus, _) = new_bus();
+
+        bus.write(0x0012, 0x34);
+        assert_eq!(bus.read(0x0812), 0x34);
+        assert_eq!(bus.read(0x1012), 0x34);
+        assert_eq!(bus.read(0x1812), 0x34);
+
+        bus.write(0x1fff, 0x56);
+        assert_eq!(bus.read(0x07ff), 0x56);
+    }
+
+    #[test]
+    fn ppu_register_mirrors() {
+        let (mut bus, ppu) = new_bus();
+
+        // $3456 mirrors PPUADDR and $3fff mirrors PPUDATA
+        bus.write(0x3456, 0x21);
+        bus.write(0x3456, 0x00);
+        bus.write(0x3fff, 0xab);
+
+        assert_eq!(ppu.borrow().read_bytes(&0x2100u16.into(), 1), vec![0xab]);
+
+        // reading PPUDATA has side effects on the ppu's read buffer
+        bus.write(PPUADDR, 0x21);
+        bus.write(PPUADDR, 0x00);
+        bus.read(0x200f);
+        assert_eq!(bus.read(PPUDATA), 0xab);
+
+        bus.write(OAMADDR + 0x08, 0x10);
+        bus.write(OAMDATA, 0x42);
+        bus.write(OAMADDR, 0x10);
+        assert_eq!(bus.read(OAMDATA + 0x1ff8), 0x42);
+    }
+
+    #[test]
+    fn oam_dma_request() {
+        let (mut bus, ppu) = new_bus();
+
+        bus.write(OAMDMA, 0x02);
+
+        assert_eq!(ppu.borrow_mut().take_oam_dma(), Some(0x02));
+    }
+
+    #[test]
+    fn apu_registers() {
+        let (mut bus, _) = new_bus();
+
+        bus.write(SND_CHN, 0x01);
+        bus.write(SQ1_VOL, 0x3f);
+        bus.write(SQ1_HI, 0x08);
+
+        // pulse 1's length counter is running
+        assert_eq!(bus.read(SND_CHN), 0x01);
+
+        // the rest of the apu's registers can't be read
+        assert_eq!(bus.read(SQ1_VOL), 0x01);
+    }
+
+    #[test]
+    fn cart_space() {
+        let (mut bus, _) = new_bus();
+
+        let mut prg_rom = vec![0; 0x4000];
+        prg_rom[0x3fff] = 0x02;
+
+        bus.insert_cart(rc_ref(NROMMapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &[],
+            prg_ram_size: 0x2000,
+            mirroring: Mirroring::Horizontal,
+            ..Default::default()
+        })));
+
+        bus.write(0x6000, 0x01);
+
+        assert_eq!(bus.read(0x6000), 0x01);
+        assert_eq!(bus.read(0xffff), 0x02);
+
+        // nothing on the cart answers at $4020
+        assert_eq!(bus.read(0x4020), 0x02);
+    }
+
+    #[test]
+    fn open_bus() {
+        let (mut bus, _) = new_bus();
+
+        bus.write(0x0000, 0x5a);
+        bus.read(0x0000);
+
+        assert_eq!(bus.read(0x4018), 0x5a);
+
+        // with no cart inserted
+        assert_eq!(bus.read(0x8000), 0x5a);
+    }
+}
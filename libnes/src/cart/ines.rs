@@ -2,10 +2,10 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::bits::get_bit_val;
-use crate::cart::mappers::{get_mapper, Mapper, MapperOptions};
+use crate::cart::mappers::{get_mapper, MapperOptions, PRG_RAM_UNIT_SIZE};
 use crate::cart::CartLoader;
-use crate::cpu::Cpu;
 use crate::nes::Nes;
+use crate::ppu::mem::Mirroring;
 use crate::util::take_elems;
 
 const HEADER_SIZE: usize = 16;
@@ -26,16 +26,8 @@ where
     T: Nes + 'static,
 {
     fn load(&self, nes_ref: Rc<RefCell<T>>, cart_data: &[u8]) -> Result<(), String> {
-        let cpu = nes_ref.borrow_mut().get_cpu();
-
         let header = read_header(cart_data)?;
 
-        if header.has_trainer {
-            let trainer = take_elems(cart_data, 16, TRAINER_SIZE)?;
-
-            cpu.borrow_mut().write_bytes_to(&0x7000u16.into(), trainer);
-        }
-
         let rom_addr_offset = match header.has_trainer {
             true => TRAINER_SIZE,
             false => 0,
@@ -53,16 +45,28 @@ where
 
         let chr_rom = &cart_data[chr_rom_start_addr..chr_rom_end_addr];
 
-        let mapper = get_mapper(header.mapper_id)?;
-        mapper.map(
-            nes_ref,
-            MapperOptions {
+        let trainer = match header.has_trainer {
+            true => take_elems(cart_data, HEADER_SIZE, TRAINER_SIZE)?,
+            false => &[],
+        };
+
+        let mapper = get_mapper(
+            header.mapper_id,
+            &MapperOptions {
                 cart_data,
                 prg_rom,
                 chr_rom,
+                prg_ram_size: header.prg_ram_size,
+                mirroring: header.mirroring,
+                submapper: header.submapper,
+                bus_conflicts: has_bus_conflicts(header.mapper_id, header.submapper),
+                trainer,
+                ..Default::default()
             },
         )?;
 
+        nes_ref.borrow_mut().insert_cart(mapper);
+
         Ok(())
     }
 }
@@ -99,8 +103,13 @@ fn read_header(cart_data: &[u8]) -> Result<iNESHeader, String> {
         _ => return Err(format!("")),
     };
 
-    let num_ram_banks = match take_elems(cart_data, 8, 1) {
-        Ok(num_banks) => num_banks[0],
+    let flags_8 = match take_elems(cart_data, 8, 1) {
+        Ok(byte) => byte[0],
+        _ => return Err(format!("")),
+    };
+
+    let flags_10 = match take_elems(cart_data, 10, 1) {
+        Ok(byte) => byte[0],
         _ => return Err(format!("")),
     };
 
@@ -114,22 +123,183 @@ fn read_header(cart_data: &[u8]) -> Result<iNESHeader, String> {
     let mapper_id_hi = control_byte_2 & 0b11110000;
     let mapper_id = mapper_id_hi | mapper_id_lo;
 
+    // see https://wiki.nesdev.com/w/index.php/NES_2.0
+    let is_nes_2 = control_byte_2 & 0b00001100 == 0b00001000;
+
+    let (submapper, prg_ram_size) = match is_nes_2 {
+        true => {
+            // mapper numbers past 255 aren't supported
+            if flags_8 & 0x0f != 0 {
+                let mapper_id = (((flags_8 & 0x0f) as u16) << 8) | mapper_id as u16;
+
+                return Err(format!("Unsupported Mapper '{}'", mapper_id));
+            }
+
+            // volatile and battery-backed prg ram both sit at $6000
+            let prg_ram_size = nes_2_ram_size(flags_10 & 0x0f) + nes_2_ram_size(flags_10 >> 4);
+
+            (flags_8 >> 4, prg_ram_size)
+        }
+        // a ram size of 0 means 8K, for compatibility with older dumps
+        false => (0, std::cmp::max(1, flags_8 as usize) * PRG_RAM_UNIT_SIZE),
+    };
+
     let has_trainer = get_bit_val(control_byte, 2);
 
+    // four-screen carts ignore the horizontal/vertical bit
+    let mirroring = match (get_bit_val(control_byte, 3), get_bit_val(control_byte, 0)) {
+        (true, _) => Mirroring::FourScreen,
+        (false, true) => Mirroring::Vertical,
+        (false, false) => Mirroring::Horizontal,
+    };
+
     Ok(iNESHeader {
         num_prg_rom_banks: num_prg_rom_banks,
         num_chr_rom_banks: num_chr_rom_banks,
+        prg_ram_size: prg_ram_size,
         mapper_id: mapper_id,
+        submapper: submapper,
         has_trainer: has_trainer,
+        mirroring: mirroring,
     })
 }
 
+// UxROM, CNROM and AxROM use submapper 1 for boards without bus conflicts and 2 for
+// boards with them; otherwise we assume none, as code written for a board with
+// conflicts already writes values that match the rom
+fn has_bus_conflicts(mapper_id: u8, submapper: u8) -> bool {
+    match (mapper_id, submapper) {
+        (2, 2) | (3, 2) | (7, 2) => true,
+        _ => false,
+    }
+}
+
+// nes 2.0 gives ram sizes as a shift count, with 0 meaning none
+fn nes_2_ram_size(shift: u8) -> usize {
+    match shift {
+        0 => 0,
+        _ => 64 << shift,
+    }
+}
+
 struct iNESHeader {
     pub num_prg_rom_banks: u8,
     pub num_chr_rom_banks: u8,
+    pub prg_ram_size: usize,
     pub mapper_id: u8,
+    pub submapper: u8,
     pub has_trainer: bool,
+    pub mirroring: Mirroring,
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+    use crate::cpu::{Cpu, DefaultCpu};
+    use crate::nes::DefaultNes;
+    use crate::ppu::{DefaultPpu, Ppu};
+    use crate::util::rc_ref;
+
+    fn header_with_flags_6(flags_6: u8) -> Vec<u8> {
+        vec![
+            0x4E, 0x45, 0x53, 0x1A, 1, 1, flags_6, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ]
+    }
+
+    #[test]
+    fn reads_mirroring() {
+        let mirroring = |flags_6: u8| {
+            read_header(&header_with_flags_6(flags_6))
+                .unwrap()
+                .mirroring
+        };
+
+        assert_eq!(mirroring(0b0000_0000), Mirroring::Horizontal);
+        assert_eq!(mirroring(0b0000_0001), Mirroring::Vertical);
+        assert_eq!(mirroring(0b0000_1000), Mirroring::FourScreen);
+        assert_eq!(mirroring(0b0000_1001), Mirroring::FourScreen);
+
+        // the rest of the flags don't matter
+        assert_eq!(mirroring(0b1111_0111), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn reads_nes_2_header() {
+        let mut header = header_with_flags_6(0x70);
+        header[7] = 0x18;
+        header[8] = 0x20;
+        header[10] = 0x77;
+
+        let header = read_header(&header).unwrap();
+        assert_eq!(header.mapper_id, 23);
+        assert_eq!(header.submapper, 2);
+        assert_eq!(header.prg_ram_size, 16384);
+
+        // ines 1.0 headers have no submapper, and give prg ram in 8K units
+        let mut header = header_with_flags_6(0x70);
+        header[8] = 0x02;
+
+        let header = read_header(&header).unwrap();
+        assert_eq!(header.submapper, 0);
+        assert_eq!(header.prg_ram_size, 16384);
+    }
+
+    #[test]
+    fn loads_bus_conflicts() {
+        // a cnrom cart with 4 chr banks, each filled with its bank number
+        let chr_bank = |submapper: u8| {
+            let mut cart_data = header_with_flags_6(0x30);
+            cart_data[4] = 2;
+            cart_data[5] = 4;
+            cart_data[7] = 0x08;
+            cart_data[8] = submapper << 4;
+
+            let mut prg_rom = vec![0; 2 * PRG_ROM_UNIT_SIZE];
+            prg_rom[0] = 0x01;
+            cart_data.extend(prg_rom);
+            cart_data.extend(numbered_banks(4, CHR_ROM_UNIT_SIZE));
+
+            let cpu = rc_ref(DefaultCpu::new(false));
+            let ppu = rc_ref(DefaultPpu::new());
+            let nes = rc_ref(DefaultNes::new(cpu.clone(), ppu.clone()));
+
+            iNESLoader::new().load(nes, &cart_data).unwrap();
+
+            // selects chr bank 3, over the $01 in rom
+            cpu.borrow_mut().write_bytes_to(&0x8000u16.into(), &[0x03]);
+
+            let bank = ppu.borrow().read_bytes(&0x0000u16.into(), 1)[0];
+            bank
+        };
+
+        assert_eq!(chr_bank(2), 0x01);
+        assert_eq!(chr_bank(1), 0x03);
+        assert_eq!(chr_bank(0), 0x03);
+    }
+
+    #[test]
+    fn loads_trainer() {
+        // a vrc6 cart with a trainer, whose prg ram is disabled on power on
+        let mut cart_data = header_with_flags_6(0x84);
+        cart_data[4] = 2;
+        cart_data[7] = 0x10;
+
+        cart_data.extend((0..TRAINER_SIZE).map(|i| i as u8));
+        cart_data.extend(vec![0; 2 * PRG_ROM_UNIT_SIZE + CHR_ROM_UNIT_SIZE]);
+
+        let cpu = rc_ref(DefaultCpu::new(false));
+        let ppu = rc_ref(DefaultPpu::new());
+        let nes = rc_ref(DefaultNes::new(cpu.clone(), ppu.clone()));
+
+        iNESLoader::new().load(nes, &cart_data).unwrap();
+
+        cpu.borrow_mut().write_bytes_to(&0xb003u16.into(), &[0x80]);
+
+        let cpu = cpu.borrow();
+        assert_eq!(cpu.read_u8_at(&0x7000u16.into()), 0x00);
+        assert_eq!(cpu.read_u8_at(&0x7001u16.into()), 0x01);
+        assert_eq!(cpu.read_u8_at(&0x71ffu16.into()), 0xff);
+        assert_eq!(cpu.read_u8_at(&0x7200u16.into()), 0x00);
+    }
+}
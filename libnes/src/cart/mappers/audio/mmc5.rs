@@ -0,0 +1,255 @@
+use crate::apu::envelope::Envelope;
+use crate::apu::length_counter::LengthCounter;
+use crate::apu::pulse::DUTY_SEQUENCES;
+use crate::apu::timer::Timer;
+
+const PULSE_LENGTH_HALT: u8 = 0x20;
+
+const PCM_READ_MODE: u8 = 0x01;
+const PCM_IRQ_ENABLE: u8 = 0x80;
+const PCM_IRQ_PENDING: u8 = 0x80;
+
+// The envelopes and length counters are clocked at a fixed 240Hz
+const FRAME_PERIOD: u16 = 7457;
+
+// Roughly the apu's own levels for its pulses and dmc
+const PULSE_OUTPUT_SCALE: f32 = 0.00752;
+const PCM_OUTPUT_SCALE: f32 = 0.0017;
+
+// The apu's pulse, without its sweep unit (or the muting that goes with it)
+#[derive(Default)]
+struct Pulse {
+    duty: u8,
+    step: usize,
+
+    timer: Timer,
+    envelope: Envelope,
+    length: LengthCounter,
+}
+
+impl Pulse {
+    fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0 => {
+                self.duty = val >> 6;
+                self.envelope.write_control(val);
+                self.length.set_halted(val & PULSE_LENGTH_HALT != 0);
+            }
+            2 => self.timer.write_period_lo(val),
+            3 => {
+                self.timer.write_period_hi(val);
+                self.length.load(val);
+
+                self.step = 0;
+                self.envelope.restart();
+            }
+            // there's no sweep unit at $5001/$5005
+            _ => {}
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if self.timer.clock() {
+            self.step = (self.step + 1) % 8;
+        }
+    }
+
+    fn clock_frame(&mut self) {
+        self.length.clock();
+        self.envelope.clock();
+    }
+
+    fn output(&self) -> u8 {
+        match self.length.is_active() && DUTY_SEQUENCES[self.duty as usize][self.step] != 0 {
+            true => self.envelope.output(),
+            false => 0,
+        }
+    }
+}
+
+// Two pulse channels much like the apu's (without sweep), and an 8-bit pcm
+// channel that's either written directly or captured from prg reads,
+// see https://wiki.nesdev.com/w/index.php/MMC5_audio
+#[derive(Default)]
+pub struct MMC5Audio {
+    pulses: [Pulse; 2],
+    pcm_mode: u8,
+    pcm: u8,
+    is_pcm_irq_pending: bool,
+
+    frame_counter: u16,
+    is_odd_cycle: bool,
+}
+
+impl MMC5Audio {
+    pub fn new() -> Self {
+        MMC5Audio::default()
+    }
+
+    // $5000-$5015
+    pub fn write_register(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x5000...0x5003 => self.pulses[0].write_register(addr & 0x03, val),
+            0x5004...0x5007 => self.pulses[1].write_register(addr & 0x03, val),
+            0x5010 => self.pcm_mode = val,
+            // zero can't be written, only read
+            0x5011 if self.pcm_mode & PCM_READ_MODE == 0 && val != 0 => self.pcm = val,
+            0x5015 => {
+                self.pulses[0].length.set_enabled(val & 0x01 != 0);
+                self.pulses[1].length.set_enabled(val & 0x02 != 0);
+            }
+            _ => {}
+        }
+    }
+
+    // $5010; reading acknowledges the irq
+    pub fn read_pcm_status(&mut self) -> u8 {
+        let status = match self.is_pcm_irq_pending {
+            true => PCM_IRQ_PENDING,
+            false => 0,
+        };
+
+        self.is_pcm_irq_pending = false;
+
+        status | (self.pcm_mode & PCM_READ_MODE)
+    }
+
+    // $5015
+    pub fn read_status(&self) -> u8 {
+        self.pulses[0].length.is_active() as u8 | (self.pulses[1].length.is_active() as u8) << 1
+    }
+
+    // In read mode, the pcm channel takes whatever the cpu reads from $8000-$bfff,
+    // and a zero raises the irq instead
+    pub fn capture_prg_read(&mut self, addr: u16, val: u8) {
+        if self.pcm_mode & PCM_READ_MODE == 0 || !(0x8000..=0xbfff).contains(&addr) {
+            return;
+        }
+
+        match val {
+            0 => self.is_pcm_irq_pending = true,
+            _ => self.pcm = val,
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.is_pcm_irq_pending && self.pcm_mode & PCM_IRQ_ENABLE != 0
+    }
+
+    pub fn clock(&mut self) {
+        // like the apu, the pulse timers tick every other cpu cycle
+        if self.is_odd_cycle {
+            self.pulses[0].clock_timer();
+            self.pulses[1].clock_timer();
+        }
+
+        self.is_odd_cycle = !self.is_odd_cycle;
+
+        self.frame_counter += 1;
+        if self.frame_counter == FRAME_PERIOD {
+            self.frame_counter = 0;
+
+            self.pulses[0].clock_frame();
+            self.pulses[1].clock_frame();
+        }
+    }
+
+    pub fn output(&self) -> f32 {
+        let pulses = self.pulses[0].output() + self.pulses[1].output();
+
+        pulses as f32 * PULSE_OUTPUT_SCALE + self.pcm as f32 * PCM_OUTPUT_SCALE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pulse_sequence() {
+        let mut audio = MMC5Audio::new();
+
+        // 25% duty at a constant volume of 9, with a period of 0 (one step per two cycles)
+        audio.write_register(0x5015, 0x01);
+        audio.write_register(0x5000, 0x59);
+        audio.write_register(0x5002, 0x00);
+        audio.write_register(0x5003, 0x08);
+
+        let mut samples = vec![];
+        for _ in 0..8 {
+            audio.clock();
+            audio.clock();
+
+            samples.push(audio.pulses[0].output());
+        }
+
+        assert_eq!(samples, vec![9, 9, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(audio.read_status(), 0x01);
+    }
+
+    #[test]
+    fn length_counter() {
+        let mut audio = MMC5Audio::new();
+
+        // a length of 2, which runs out after two 240Hz frames
+        audio.write_register(0x5015, 0x02);
+        audio.write_register(0x5007, 0x18);
+        assert_eq!(audio.read_status(), 0x02);
+
+        for _ in 0..FRAME_PERIOD * 2 {
+            audio.clock();
+        }
+        assert_eq!(audio.read_status(), 0x00);
+
+        // loading while disabled does nothing
+        audio.write_register(0x5015, 0x00);
+        audio.write_register(0x5007, 0x18);
+        assert_eq!(audio.read_status(), 0x00);
+    }
+
+    #[test]
+    fn envelope() {
+        let mut audio = MMC5Audio::new();
+
+        // decays every frame, from 15
+        audio.write_register(0x5015, 0x01);
+        audio.write_register(0x5000, 0xc0);
+        audio.write_register(0x5003, 0x00);
+
+        let mut decays = vec![];
+        for _ in 0..4 {
+            for _ in 0..FRAME_PERIOD {
+                audio.clock();
+            }
+
+            decays.push(audio.pulses[0].envelope.output());
+        }
+
+        assert_eq!(decays, vec![15, 14, 13, 12]);
+    }
+
+    #[test]
+    fn pcm() {
+        let mut audio = MMC5Audio::new();
+
+        audio.write_register(0x5011, 0x40);
+        assert_eq!(audio.output(), 0x40 as f32 * PCM_OUTPUT_SCALE);
+
+        // zero is ignored in write mode
+        audio.write_register(0x5011, 0x00);
+        assert_eq!(audio.pcm, 0x40);
+
+        // read mode captures prg reads, and zeros raise the irq
+        audio.write_register(0x5010, PCM_READ_MODE | PCM_IRQ_ENABLE);
+        audio.capture_prg_read(0xc000, 0x12);
+        audio.capture_prg_read(0x8000, 0x34);
+        assert_eq!(audio.pcm, 0x34);
+
+        audio.capture_prg_read(0x9000, 0x00);
+        assert_eq!(audio.pcm, 0x34);
+        assert_eq!(audio.irq(), true);
+
+        assert_eq!(audio.read_pcm_status(), PCM_IRQ_PENDING | PCM_READ_MODE);
+        assert_eq!(audio.irq(), false);
+    }
+}
@@ -0,0 +1,11 @@
+mod mmc5;
+mod n163;
+mod sunsoft_5b;
+mod vrc6;
+mod vrc7;
+
+pub use mmc5::*;
+pub use n163::*;
+pub use sunsoft_5b::*;
+pub use vrc6::*;
+pub use vrc7::*;
@@ -0,0 +1,152 @@
+// The channel registers are at the top of the chip's ram, channel 7 last
+const CHANNEL_REGISTERS_START: usize = 0x40;
+const CHANNEL_REGISTERS_SIZE: usize = 8;
+const NUM_CHANNELS: usize = 8;
+
+// $7f's upper bits are the number of enabled channels (minus one)
+const NUM_CHANNELS_REGISTER: usize = 0x7f;
+
+// One channel is updated every 15 cpu cycles
+const CHANNEL_UPDATE_CYCLES: u8 = 15;
+
+// The n163 is noticeably louder than the apu
+const OUTPUT_SCALE: f32 = 0.0025;
+
+// Up to 8 wavetable channels, whose samples and registers all live in the
+// chip's ram; the hardware cycles through the enabled channels one at a time,
+// which is approximated here by averaging them,
+// see https://wiki.nesdev.com/w/index.php/Namco_163_audio
+pub struct N163Audio {
+    cycles: u8,
+    channel: usize,
+    outputs: [i16; NUM_CHANNELS],
+}
+
+impl Default for N163Audio {
+    fn default() -> Self {
+        N163Audio {
+            cycles: 0,
+            channel: NUM_CHANNELS - 1,
+            outputs: [0; NUM_CHANNELS],
+        }
+    }
+}
+
+impl N163Audio {
+    pub fn new() -> Self {
+        N163Audio::default()
+    }
+
+    fn num_channels(ram: &[u8]) -> usize {
+        ((ram[NUM_CHANNELS_REGISTER] >> 4) & 0x07) as usize + 1
+    }
+
+    // The chip's phase counters are stored back into its ram
+    pub fn clock(&mut self, ram: &mut [u8]) {
+        self.cycles += 1;
+
+        if self.cycles < CHANNEL_UPDATE_CYCLES {
+            return;
+        }
+
+        self.cycles = 0;
+
+        self.update_channel(ram, self.channel);
+
+        let first_channel = NUM_CHANNELS - N163Audio::num_channels(ram);
+        self.channel = match self.channel {
+            channel if channel <= first_channel => NUM_CHANNELS - 1,
+            channel => channel - 1,
+        };
+    }
+
+    fn update_channel(&mut self, ram: &mut [u8], channel: usize) {
+        let base = CHANNEL_REGISTERS_START + channel * CHANNEL_REGISTERS_SIZE;
+        let registers = &mut ram[base..base + CHANNEL_REGISTERS_SIZE];
+
+        let frequency =
+            registers[0] as u32 | (registers[2] as u32) << 8 | ((registers[4] & 0x03) as u32) << 16;
+
+        let phase = registers[1] as u32 | (registers[3] as u32) << 8 | (registers[5] as u32) << 16;
+
+        // the length is in 4-bit samples
+        let length = (0x100 - (registers[4] & 0xfc) as u32) << 16;
+        let phase = (phase + frequency) % length;
+
+        registers[1] = phase as u8;
+        registers[3] = (phase >> 8) as u8;
+        registers[5] = (phase >> 16) as u8;
+
+        let wave_addr = registers[6] as u32;
+        let volume = (registers[7] & 0x0f) as i16;
+
+        // samples are packed two to a byte, low nibble first
+        let sample_addr = (wave_addr + (phase >> 16)) & 0xff;
+        let sample = match sample_addr & 0x01 {
+            0 => ram[(sample_addr >> 1) as usize] & 0x0f,
+            _ => ram[(sample_addr >> 1) as usize] >> 4,
+        };
+
+        self.outputs[channel] = (sample as i16 - 8) * volume;
+    }
+
+    pub fn output(&self, ram: &[u8]) -> f32 {
+        let num_channels = N163Audio::num_channels(ram);
+
+        let sum: i16 = self.outputs[NUM_CHANNELS - num_channels..].iter().sum();
+
+        (sum as f32 / num_channels as f32) * OUTPUT_SCALE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wavetable() {
+        let mut audio = N163Audio::new();
+        let mut ram = [0u8; 0x80];
+
+        // a 4-sample wave at $00: 0, 4, 8, 15
+        ram[0x00] = 0x40;
+        ram[0x01] = 0xf8;
+
+        // channel 7 steps one sample per update, with volume 2 and one channel enabled
+        ram[0x7c] = 0xfc | 0x01;
+        ram[0x7f] = 0x02;
+
+        let mut samples = vec![];
+        for _ in 0..5 {
+            for _ in 0..CHANNEL_UPDATE_CYCLES {
+                audio.clock(&mut ram);
+            }
+
+            samples.push(audio.outputs[7]);
+        }
+
+        // the first update has already moved past sample 0
+        assert_eq!(samples, vec![-8, 0, 14, -16, -8]);
+        assert_eq!(audio.output(&ram), -8.0 * OUTPUT_SCALE);
+    }
+
+    #[test]
+    fn channel_rotation() {
+        let mut audio = N163Audio::new();
+        let mut ram = [0u8; 0x80];
+
+        // channels 6 and 7 are enabled
+        ram[0x7f] = 0x10;
+
+        let mut channels = vec![];
+        for _ in 0..4 {
+            channels.push(audio.channel);
+
+            for _ in 0..CHANNEL_UPDATE_CYCLES {
+                audio.clock(&mut ram);
+            }
+        }
+
+        assert_eq!(channels, vec![7, 6, 7, 6]);
+    }
+}
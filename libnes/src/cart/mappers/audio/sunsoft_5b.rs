@@ -0,0 +1,315 @@
+const NUM_CHANNELS: usize = 3;
+
+const MIXER_TONE_DISABLE: u8 = 0x01;
+const MIXER_NOISE_DISABLE: u8 = 0x08;
+
+const VOLUME_ENVELOPE_MODE: u8 = 0x10;
+
+const ENVELOPE_CONTINUE: u8 = 0x08;
+const ENVELOPE_ATTACK: u8 = 0x04;
+const ENVELOPE_ALTERNATE: u8 = 0x02;
+const ENVELOPE_HOLD: u8 = 0x01;
+const ENVELOPE_STEPS: u8 = 32;
+
+// The tone and noise generators run at cpu/16, the envelope at twice that
+const TONE_DIVIDER: u8 = 16;
+const ENVELOPE_DIVIDER: u8 = 8;
+
+// Each level is 1.5dB quieter than the next
+const LEVEL_STEP_DB: f32 = 1.5;
+
+const OUTPUT_SCALE: f32 = 0.12;
+
+// Three square channels with a shared noise generator and envelope; the 5b
+// is a yamaha ym2149f, itself a clone of the ay-3-8910,
+// see https://wiki.nesdev.com/w/index.php/Sunsoft_5B_audio
+pub struct Sunsoft5BAudio {
+    registers: [u8; 16],
+    register_select: u8,
+
+    divider: u8,
+    envelope_divider: u8,
+
+    tone_counters: [u16; NUM_CHANNELS],
+    tone_outputs: [bool; NUM_CHANNELS],
+
+    noise_counter: u8,
+    noise_lfsr: u32,
+
+    envelope_counter: u16,
+    envelope_step: u8,
+    is_envelope_attack: bool,
+    is_envelope_holding: bool,
+
+    levels: [f32; ENVELOPE_STEPS as usize],
+}
+
+impl Default for Sunsoft5BAudio {
+    fn default() -> Self {
+        let mut levels = [0.0; ENVELOPE_STEPS as usize];
+        for (level, amplitude) in levels.iter_mut().enumerate().skip(1) {
+            let db = (ENVELOPE_STEPS as usize - 1 - level) as f32 * LEVEL_STEP_DB;
+
+            *amplitude = 10f32.powf(-db / 20.0);
+        }
+
+        Sunsoft5BAudio {
+            registers: [0; 16],
+            register_select: 0,
+            divider: 0,
+            envelope_divider: 0,
+            tone_counters: [0; NUM_CHANNELS],
+            tone_outputs: [false; NUM_CHANNELS],
+            noise_counter: 0,
+            noise_lfsr: 1,
+            envelope_counter: 0,
+            envelope_step: 0,
+            is_envelope_attack: false,
+            is_envelope_holding: false,
+            levels,
+        }
+    }
+}
+
+impl Sunsoft5BAudio {
+    pub fn new() -> Self {
+        Sunsoft5BAudio::default()
+    }
+
+    // $c000-$dfff
+    pub fn write_register_select(&mut self, val: u8) {
+        self.register_select = val;
+    }
+
+    // $e000-$ffff; the upper bits of the select must be clear
+    pub fn write_register(&mut self, val: u8) {
+        if self.register_select & 0xf0 != 0 {
+            return;
+        }
+
+        let register = self.register_select as usize;
+        self.registers[register] = val;
+
+        // writing the shape restarts the envelope
+        if register == 0x0d {
+            self.envelope_step = 0;
+            self.is_envelope_attack = val & ENVELOPE_ATTACK != 0;
+            self.is_envelope_holding = false;
+        }
+    }
+
+    fn tone_period(&self, channel: usize) -> u16 {
+        let lo = self.registers[channel * 2] as u16;
+        let hi = (self.registers[channel * 2 + 1] & 0x0f) as u16;
+
+        (hi << 8) | lo
+    }
+
+    fn envelope_period(&self) -> u16 {
+        (self.registers[0x0c] as u16) << 8 | self.registers[0x0b] as u16
+    }
+
+    pub fn clock(&mut self) {
+        self.envelope_divider += 1;
+        if self.envelope_divider == ENVELOPE_DIVIDER {
+            self.envelope_divider = 0;
+            self.clock_envelope();
+        }
+
+        self.divider += 1;
+        if self.divider < TONE_DIVIDER {
+            return;
+        }
+
+        self.divider = 0;
+
+        for channel in 0..NUM_CHANNELS {
+            self.tone_counters[channel] += 1;
+
+            if self.tone_counters[channel] >= self.tone_period(channel) {
+                self.tone_counters[channel] = 0;
+                self.tone_outputs[channel] = !self.tone_outputs[channel];
+            }
+        }
+
+        self.noise_counter += 1;
+        if self.noise_counter >= self.registers[0x06] & 0x1f {
+            self.noise_counter = 0;
+
+            let feedback = (self.noise_lfsr ^ (self.noise_lfsr >> 3)) & 0x01;
+            self.noise_lfsr = (self.noise_lfsr >> 1) | (feedback << 16);
+        }
+    }
+
+    fn clock_envelope(&mut self) {
+        if self.is_envelope_holding {
+            return;
+        }
+
+        self.envelope_counter += 1;
+        if self.envelope_counter < self.envelope_period() {
+            return;
+        }
+
+        self.envelope_counter = 0;
+
+        if self.envelope_step < ENVELOPE_STEPS - 1 {
+            self.envelope_step += 1;
+            return;
+        }
+
+        // the end of a ramp: stop at 0, hold, or go round again
+        let shape = self.registers[0x0d];
+        match (shape & ENVELOPE_CONTINUE != 0, shape & ENVELOPE_HOLD != 0) {
+            (false, _) => {
+                self.is_envelope_holding = true;
+                self.is_envelope_attack = false;
+            }
+            (true, true) => {
+                self.is_envelope_holding = true;
+
+                if shape & ENVELOPE_ALTERNATE != 0 {
+                    self.is_envelope_attack = !self.is_envelope_attack;
+                }
+            }
+            (true, false) => {
+                self.envelope_step = 0;
+
+                if shape & ENVELOPE_ALTERNATE != 0 {
+                    self.is_envelope_attack = !self.is_envelope_attack;
+                }
+            }
+        }
+    }
+
+    fn envelope_level(&self) -> u8 {
+        match self.is_envelope_attack {
+            true => self.envelope_step,
+            false => ENVELOPE_STEPS - 1 - self.envelope_step,
+        }
+    }
+
+    // Fixed volumes use the odd levels, so a volume of 15 is the envelope's loudest
+    fn channel_level(&self, channel: usize) -> u8 {
+        let volume = self.registers[0x08 + channel];
+
+        match (volume & VOLUME_ENVELOPE_MODE != 0, volume & 0x0f) {
+            (true, _) => self.envelope_level(),
+            (false, 0) => 0,
+            (false, volume) => volume * 2 + 1,
+        }
+    }
+
+    fn is_channel_high(&self, channel: usize) -> bool {
+        let mixer = self.registers[0x07];
+
+        let tone = self.tone_outputs[channel] || mixer & (MIXER_TONE_DISABLE << channel) != 0;
+        let noise = self.noise_lfsr & 0x01 != 0 || mixer & (MIXER_NOISE_DISABLE << channel) != 0;
+
+        tone && noise
+    }
+
+    pub fn output(&self) -> f32 {
+        let sum: f32 = (0..NUM_CHANNELS)
+            .filter(|channel| self.is_channel_high(*channel))
+            .map(|channel| self.levels[self.channel_level(channel) as usize])
+            .sum();
+
+        sum * OUTPUT_SCALE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(audio: &mut Sunsoft5BAudio, register: u8, val: u8) {
+        audio.write_register_select(register);
+        audio.write_register(val);
+    }
+
+    #[test]
+    fn tone() {
+        let mut audio = Sunsoft5BAudio::new();
+
+        // channel a, tone only, at full volume with a period of 2
+        write(&mut audio, 0x00, 0x02);
+        write(&mut audio, 0x07, 0x3e);
+        write(&mut audio, 0x08, 0x0f);
+
+        let mut samples = vec![];
+        for _ in 0..8 {
+            for _ in 0..TONE_DIVIDER {
+                audio.clock();
+            }
+
+            samples.push(audio.output() > 0.0);
+        }
+
+        assert_eq!(
+            samples,
+            vec![false, true, true, false, false, true, true, false]
+        );
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn volume() {
+        let mut audio = Sunsoft5BAudio::new();
+
+        // every channel disabled is always high
+        write(&mut audio, 0x07, 0x3f);
+
+        write(&mut audio, 0x08, 0x0f);
+        assert_eq!(audio.output(), OUTPUT_SCALE);
+
+        // each volume step is 3dB
+        write(&mut audio, 0x08, 0x0d);
+        let db = 20.0 * (audio.output() / OUTPUT_SCALE).log10();
+        assert!((db + 6.0).abs() < 0.001);
+
+        write(&mut audio, 0x08, 0x00);
+        assert_eq!(audio.output(), 0.0);
+    }
+
+    #[test]
+    fn envelope() {
+        let mut audio = Sunsoft5BAudio::new();
+
+        // a single decay, then silence
+        write(&mut audio, 0x0b, 0x01);
+        write(&mut audio, 0x0d, 0x00);
+
+        let mut levels = vec![audio.envelope_level()];
+        for _ in 0..ENVELOPE_STEPS {
+            for _ in 0..ENVELOPE_DIVIDER {
+                audio.clock();
+            }
+
+            levels.push(audio.envelope_level());
+        }
+
+        assert_eq!(levels[..3], [31, 30, 29]);
+        assert_eq!(levels[31..], [0, 0]);
+
+        // attack, alternate and hold ramps up, then holds at 0
+        write(&mut audio, 0x0d, 0x0f);
+        for _ in 0..ENVELOPE_STEPS * 2 {
+            for _ in 0..ENVELOPE_DIVIDER {
+                audio.clock();
+            }
+        }
+
+        assert_eq!(audio.envelope_level(), 0);
+        assert_eq!(audio.is_envelope_holding, true);
+
+        // continue with alternate makes a triangle
+        write(&mut audio, 0x0d, 0x0e);
+        for _ in 0..ENVELOPE_STEPS as usize * ENVELOPE_DIVIDER as usize {
+            audio.clock();
+        }
+
+        assert_eq!(audio.envelope_level(), 31);
+        assert_eq!(audio.is_envelope_attack, false);
+    }
+}
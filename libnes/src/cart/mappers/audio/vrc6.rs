@@ -0,0 +1,257 @@
+const PULSE_MODE: u8 = 0x80;
+const CHANNEL_ENABLE: u8 = 0x80;
+
+const FREQUENCY_HALT: u8 = 0x01;
+const FREQUENCY_SHIFT_4: u8 = 0x02;
+const FREQUENCY_SHIFT_8: u8 = 0x04;
+
+const PULSE_STEPS: u8 = 16;
+const SAW_STEPS: u8 = 14;
+
+// At full volume a vrc6 pulse is about as loud as one of the apu's
+const OUTPUT_SCALE: f32 = 0.01;
+
+// The timer shared by the vrc6's three channels, clocked every cpu cycle
+#[derive(Default)]
+struct Timer {
+    period: u16,
+    counter: u16,
+    is_enabled: bool,
+}
+
+impl Timer {
+    fn write_period_lo(&mut self, val: u8) {
+        self.period = (self.period & 0x0f00) | val as u16;
+    }
+
+    fn write_period_hi(&mut self, val: u8) {
+        self.period = (self.period & 0x00ff) | (((val & 0x0f) as u16) << 8);
+        self.is_enabled = val & CHANNEL_ENABLE != 0;
+    }
+
+    // Returns whether the timer ran out and reloaded
+    fn clock(&mut self, shift: u8) -> bool {
+        match self.counter {
+            0 => {
+                self.counter = self.period >> shift;
+                true
+            }
+            _ => {
+                self.counter -= 1;
+                false
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Pulse {
+    timer: Timer,
+    control: u8,
+    step: u8,
+}
+
+impl Pulse {
+    fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0 => self.control = val,
+            1 => self.timer.write_period_lo(val),
+            _ => {
+                self.timer.write_period_hi(val);
+
+                // disabling the channel resets its duty cycle
+                if !self.timer.is_enabled {
+                    self.step = 0;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if self.timer.is_enabled && self.timer.clock(shift) {
+            self.step = (self.step + 1) % PULSE_STEPS;
+        }
+    }
+
+    // The channel is high for the last (duty + 1) of its 16 steps,
+    // or all of them in constant mode
+    fn output(&self) -> u8 {
+        let duty = (self.control >> 4) & 0x07;
+        let is_high = self.control & PULSE_MODE != 0 || PULSE_STEPS - 1 - self.step <= duty;
+
+        match self.timer.is_enabled && is_high {
+            true => self.control & 0x0f,
+            false => 0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Saw {
+    timer: Timer,
+    rate: u8,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Saw {
+    fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0 => self.rate = val & 0x3f,
+            1 => self.timer.write_period_lo(val),
+            _ => {
+                self.timer.write_period_hi(val);
+
+                if !self.timer.is_enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    // The accumulator takes on the rate every other step, and resets after 7 of them
+    fn clock(&mut self, shift: u8) {
+        if !self.timer.is_enabled || !self.timer.clock(shift) {
+            return;
+        }
+
+        self.step += 1;
+
+        match self.step {
+            SAW_STEPS => {
+                self.step = 0;
+                self.accumulator = 0;
+            }
+            step if step % 2 == 0 => self.accumulator = self.accumulator.wrapping_add(self.rate),
+            _ => {}
+        }
+    }
+
+    // Only the top 5 bits of the accumulator make it out
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+// Two pulse channels and a sawtooth,
+// see https://wiki.nesdev.com/w/index.php/VRC6_audio
+#[derive(Default)]
+pub struct Vrc6Audio {
+    pulses: [Pulse; 2],
+    saw: Saw,
+    frequency_control: u8,
+}
+
+impl Vrc6Audio {
+    pub fn new() -> Self {
+        Vrc6Audio::default()
+    }
+
+    // $9000-$b002, with the board's pins already decoded
+    pub fn write_register(&mut self, register: u16, val: u8) {
+        match register {
+            0x9003 => self.frequency_control = val,
+            0x9000...0x9002 => self.pulses[0].write_register(register & 0x03, val),
+            0xa000...0xa002 => self.pulses[1].write_register(register & 0x03, val),
+            0xb000...0xb002 => self.saw.write_register(register & 0x03, val),
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.frequency_control & FREQUENCY_HALT != 0 {
+            return;
+        }
+
+        let shift = match self.frequency_control {
+            control if control & FREQUENCY_SHIFT_8 != 0 => 8,
+            control if control & FREQUENCY_SHIFT_4 != 0 => 4,
+            _ => 0,
+        };
+
+        self.pulses[0].clock(shift);
+        self.pulses[1].clock(shift);
+        self.saw.clock(shift);
+    }
+
+    pub fn output(&self) -> f32 {
+        let sum = self.pulses[0].output() + self.pulses[1].output() + self.saw.output();
+
+        sum as f32 * OUTPUT_SCALE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn clock_times(audio: &mut Vrc6Audio, times: usize) {
+        for _ in 0..times {
+            audio.clock();
+        }
+    }
+
+    #[test]
+    fn pulse_duty() {
+        let mut audio = Vrc6Audio::new();
+
+        // duty 3 (4/16), volume 10, and a period of 1 (2 cycles per step)
+        audio.write_register(0x9000, 0x3a);
+        audio.write_register(0x9001, 0x01);
+        audio.write_register(0x9002, CHANNEL_ENABLE);
+
+        let mut samples = vec![];
+        for _ in 0..PULSE_STEPS {
+            clock_times(&mut audio, 2);
+            samples.push(audio.pulses[0].output());
+        }
+
+        assert_eq!(
+            samples,
+            vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 10, 10, 10, 0]
+        );
+
+        // constant mode ignores the duty
+        audio.write_register(0x9000, 0x8a);
+        assert_eq!(audio.pulses[0].output(), 10);
+
+        // as does a disabled channel
+        audio.write_register(0x9002, 0x00);
+        assert_eq!(audio.pulses[0].output(), 0);
+    }
+
+    #[test]
+    fn saw() {
+        let mut audio = Vrc6Audio::new();
+
+        audio.write_register(0xb000, 0x10);
+        audio.write_register(0xb001, 0x00);
+        audio.write_register(0xb002, CHANNEL_ENABLE);
+
+        // the accumulator builds up over 7 steps, then resets
+        let mut samples = vec![];
+        for _ in 0..SAW_STEPS {
+            audio.clock();
+            samples.push(audio.saw.output());
+        }
+
+        assert_eq!(samples, vec![0, 2, 2, 4, 4, 6, 6, 8, 8, 10, 10, 12, 12, 0]);
+    }
+
+    #[test]
+    fn frequency_control() {
+        let mut audio = Vrc6Audio::new();
+
+        audio.write_register(0xb000, 0x10);
+        audio.write_register(0xb002, CHANNEL_ENABLE);
+        audio.write_register(0x9003, FREQUENCY_HALT);
+
+        clock_times(&mut audio, 10);
+        assert_eq!(audio.output(), 0.0);
+
+        audio.write_register(0x9003, 0x00);
+        clock_times(&mut audio, 2);
+        assert_eq!(audio.output(), 2.0 * OUTPUT_SCALE);
+    }
+}
@@ -0,0 +1,520 @@
+use std::f32::consts::PI;
+
+const NUM_CHANNELS: usize = 6;
+const NUM_REGISTERS: usize = 0x40;
+
+// The chip makes one sample every 36 cpu cycles
+const SAMPLE_CYCLES: u8 = 36;
+const SAMPLE_RATE: f32 = 1_789_773.0 / SAMPLE_CYCLES as f32;
+
+const CHANNEL_SUSTAIN: u8 = 0x20;
+const CHANNEL_KEY_ON: u8 = 0x10;
+
+const PATCH_AM: u8 = 0x80;
+const PATCH_VIBRATO: u8 = 0x40;
+const PATCH_SUSTAINED: u8 = 0x20;
+const PATCH_KEY_SCALE_RATE: u8 = 0x10;
+
+// Attenuations in dB; the envelope generator bottoms out at 48dB
+const MAX_ATTENUATION: f32 = 48.0;
+const TOTAL_LEVEL_STEP: f32 = 0.75;
+const VOLUME_STEP: f32 = 3.0;
+const SUSTAIN_LEVEL_STEP: f32 = 3.0;
+
+// Key scale level at block 7, by the top 4 bits of the frequency
+const KEY_SCALE_LEVELS: [f32; 16] = [
+    0.0, 18.0, 24.0, 27.75, 30.0, 32.25, 33.75, 35.25, 36.0, 37.5, 38.25, 39.0, 39.75, 40.5, 41.25,
+    42.0,
+];
+
+// 0, 1.5, 3 and 6dB per octave
+const KEY_SCALE_LEVEL_SCALES: [f32; 4] = [0.0, 0.25, 0.5, 1.0];
+
+const MULTIPLIERS: [f32; 16] = [
+    0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0,
+];
+
+// Envelope times (in seconds) across the full range at rate 4, halving every 4 rates
+const ATTACK_TIME: f32 = 2.826;
+const DECAY_TIME: f32 = 39.28;
+
+// The rates used on key off, when the patch's own release rate doesn't apply
+const SUSTAIN_RELEASE_RATE: u8 = 5;
+const PERCUSSIVE_RELEASE_RATE: u8 = 7;
+
+const AM_FREQUENCY: f32 = 3.7;
+const AM_DEPTH: f32 = 4.8;
+const VIBRATO_FREQUENCY: f32 = 6.4;
+const VIBRATO_DEPTH: f32 = 0.008;
+
+// A modulator at full volume moves the carrier's phase by up to 2 cycles
+const MODULATION_DEPTH: f32 = 2.0;
+
+const OUTPUT_SCALE: f32 = 0.1;
+
+// The vrc7's built-in instruments (1-15); instrument 0 is the custom one in $00-$07
+const PATCHES: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xe8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0d, 0xd8, 0xf6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xfa, 0xb2, 0x20, 0x12],
+    [0x31, 0x61, 0x0c, 0x07, 0xa8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1e, 0x06, 0xe1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xa3, 0xe2, 0xf4, 0xf4],
+    [0x21, 0x61, 0x1d, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xa2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xb5, 0x01, 0x0f, 0x0f, 0xa8, 0xa5, 0x51, 0x02],
+    [0x17, 0xc1, 0x24, 0x07, 0xf8, 0xf8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xd3, 0x05, 0xc9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0c, 0x00, 0x94, 0xc0, 0x33, 0xf6],
+    [0x21, 0x72, 0x0d, 0x00, 0xc1, 0xd5, 0x56, 0x06],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Off,
+}
+
+#[derive(Clone, Copy)]
+struct Operator {
+    // in cycles, [0, 1)
+    phase: f32,
+
+    // in dB
+    envelope: f32,
+    state: EnvelopeState,
+
+    // the modulator's last two outputs, for its feedback
+    outputs: [f32; 2],
+}
+
+impl Operator {
+    fn new() -> Self {
+        Operator {
+            phase: 0.0,
+            envelope: MAX_ATTENUATION,
+            state: EnvelopeState::Off,
+            outputs: [0.0; 2],
+        }
+    }
+
+    fn key_on(&mut self) {
+        self.phase = 0.0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn key_off(&mut self) {
+        if self.state != EnvelopeState::Off {
+            self.state = EnvelopeState::Release;
+        }
+    }
+
+    fn clock_envelope(&mut self, rates: &EnvelopeRates) {
+        match self.state {
+            EnvelopeState::Attack => {
+                self.envelope -= attack_step(rates.attack, rates.key_scale);
+
+                if self.envelope <= 0.0 {
+                    self.envelope = 0.0;
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.envelope += decay_step(rates.decay, rates.key_scale);
+
+                if self.envelope >= rates.sustain_level {
+                    self.envelope = rates.sustain_level;
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            EnvelopeState::Sustain => {
+                // percussive patches keep on decaying
+                if !rates.is_sustained {
+                    self.envelope += decay_step(rates.release, rates.key_scale);
+                }
+            }
+            EnvelopeState::Release => {
+                self.envelope += decay_step(rates.key_off_release, rates.key_scale)
+            }
+            EnvelopeState::Off => {}
+        }
+
+        if self.envelope >= MAX_ATTENUATION {
+            self.envelope = MAX_ATTENUATION;
+
+            if self.state != EnvelopeState::Attack {
+                self.state = EnvelopeState::Off;
+            }
+        }
+    }
+
+    // Steps the phase and returns the operator's output for this sample
+    fn output(
+        &mut self,
+        increment: f32,
+        attenuation: f32,
+        modulation: f32,
+        is_rectified: bool,
+    ) -> f32 {
+        self.phase = (self.phase + increment).fract();
+
+        if self.state == EnvelopeState::Off {
+            return 0.0;
+        }
+
+        let attenuation = self.envelope + attenuation;
+        if attenuation >= MAX_ATTENUATION {
+            return 0.0;
+        }
+
+        let wave = (2.0 * PI * (self.phase + modulation)).sin();
+        let wave = match is_rectified && wave < 0.0 {
+            true => 0.0,
+            false => wave,
+        };
+
+        wave * 10f32.powf(-attenuation / 20.0)
+    }
+}
+
+// One operator's envelope settings, out of its patch and channel
+struct EnvelopeRates {
+    attack: u8,
+    decay: u8,
+    release: u8,
+    key_off_release: u8,
+    key_scale: u8,
+    sustain_level: f32,
+    is_sustained: bool,
+}
+
+// Each rate is 4 steps wide, plus the key scale; 0 never changes
+fn effective_rate(rate: u8, key_scale: u8) -> i32 {
+    match rate {
+        0 => 0,
+        _ => (rate as i32 * 4 + key_scale as i32).min(63),
+    }
+}
+
+fn rate_time(base: f32, rate: i32) -> f32 {
+    base / 2f32.powf((rate - 4) as f32 / 4.0)
+}
+
+fn attack_step(rate: u8, key_scale: u8) -> f32 {
+    match effective_rate(rate, key_scale) {
+        0 => 0.0,
+        rate if rate >= 60 => MAX_ATTENUATION,
+        rate => MAX_ATTENUATION / (rate_time(ATTACK_TIME, rate) * SAMPLE_RATE),
+    }
+}
+
+fn decay_step(rate: u8, key_scale: u8) -> f32 {
+    match effective_rate(rate, key_scale) {
+        0 => 0.0,
+        rate => MAX_ATTENUATION / (rate_time(DECAY_TIME, rate) * SAMPLE_RATE),
+    }
+}
+
+// The fm synthesiser: a subset of yamaha's ym2413 (opll) with 6 two-operator
+// channels and no rhythm mode; it's simulated with floats, not the chip's
+// log-sin and exponent tables,
+// see https://wiki.nesdev.com/w/index.php/VRC7_audio
+pub struct Vrc7Audio {
+    registers: [u8; NUM_REGISTERS],
+    register_select: u8,
+
+    // (modulator, carrier)
+    operators: [(Operator, Operator); NUM_CHANNELS],
+
+    cycles: u8,
+    am_phase: f32,
+    vibrato_phase: f32,
+    sample: f32,
+}
+
+impl Default for Vrc7Audio {
+    fn default() -> Self {
+        Vrc7Audio {
+            registers: [0; NUM_REGISTERS],
+            register_select: 0,
+            operators: [(Operator::new(), Operator::new()); NUM_CHANNELS],
+            cycles: 0,
+            am_phase: 0.0,
+            vibrato_phase: 0.0,
+            sample: 0.0,
+        }
+    }
+}
+
+impl Vrc7Audio {
+    pub fn new() -> Self {
+        Vrc7Audio::default()
+    }
+
+    // $9010
+    pub fn write_register_select(&mut self, val: u8) {
+        self.register_select = val;
+    }
+
+    // $9030
+    pub fn write_register(&mut self, val: u8) {
+        let register = self.register_select as usize;
+        if register >= NUM_REGISTERS {
+            return;
+        }
+
+        let channel = register & 0x0f;
+        if register & 0xf0 == 0x20 && channel < NUM_CHANNELS {
+            let was_key_on = self.registers[register] & CHANNEL_KEY_ON != 0;
+            let is_key_on = val & CHANNEL_KEY_ON != 0;
+
+            let (modulator, carrier) = &mut self.operators[channel];
+            match (was_key_on, is_key_on) {
+                (false, true) => {
+                    modulator.key_on();
+                    carrier.key_on();
+                }
+                (true, false) => {
+                    modulator.key_off();
+                    carrier.key_off();
+                }
+                _ => {}
+            }
+        }
+
+        self.registers[register] = val;
+    }
+
+    fn patch(&self, channel: usize) -> [u8; 8] {
+        match self.registers[0x30 + channel] >> 4 {
+            0 => {
+                let mut patch = [0; 8];
+                patch.copy_from_slice(&self.registers[..8]);
+
+                patch
+            }
+            instrument => PATCHES[instrument as usize - 1],
+        }
+    }
+
+    // (9-bit frequency, 3-bit block)
+    fn frequency(&self, channel: usize) -> (u16, u8) {
+        let hi = self.registers[0x20 + channel];
+        let fnum = ((hi & 0x01) as u16) << 8 | self.registers[0x10 + channel] as u16;
+
+        (fnum, (hi >> 1) & 0x07)
+    }
+
+    pub fn clock(&mut self) {
+        self.cycles += 1;
+
+        if self.cycles == SAMPLE_CYCLES {
+            self.cycles = 0;
+            self.sample = self.generate_sample();
+        }
+    }
+
+    fn generate_sample(&mut self) -> f32 {
+        self.am_phase = (self.am_phase + AM_FREQUENCY / SAMPLE_RATE).fract();
+        self.vibrato_phase = (self.vibrato_phase + VIBRATO_FREQUENCY / SAMPLE_RATE).fract();
+
+        (0..NUM_CHANNELS)
+            .map(|channel| self.generate_channel(channel))
+            .sum()
+    }
+
+    fn generate_channel(&mut self, channel: usize) -> f32 {
+        let patch = self.patch(channel);
+        let (fnum, block) = self.frequency(channel);
+        let channel_control = self.registers[0x20 + channel];
+        let volume = self.registers[0x30 + channel] & 0x0f;
+
+        let am = AM_DEPTH * (1.0 + (2.0 * PI * self.am_phase).sin()) / 2.0;
+        let vibrato = 1.0 + VIBRATO_DEPTH * (2.0 * PI * self.vibrato_phase).sin();
+
+        let key_scale_level = {
+            let level = KEY_SCALE_LEVELS[(fnum >> 5) as usize] - 6.0 * (7 - block) as f32;
+
+            level.max(0.0)
+        };
+
+        let operator_settings = |side: usize| {
+            let flags = patch[side];
+
+            let key_scale = match flags & PATCH_KEY_SCALE_RATE {
+                0 => (block << 1 | (fnum >> 8) as u8) >> 2,
+                _ => block << 1 | (fnum >> 8) as u8,
+            };
+
+            let is_sustained = flags & PATCH_SUSTAINED != 0;
+            let release = patch[6 + side] & 0x0f;
+
+            let key_off_release = match (channel_control & CHANNEL_SUSTAIN != 0, is_sustained) {
+                (true, _) => SUSTAIN_RELEASE_RATE,
+                (false, true) => release,
+                (false, false) => PERCUSSIVE_RELEASE_RATE,
+            };
+
+            let rates = EnvelopeRates {
+                attack: patch[4 + side] >> 4,
+                decay: patch[4 + side] & 0x0f,
+                release,
+                key_off_release,
+                key_scale,
+                sustain_level: (patch[6 + side] >> 4) as f32 * SUSTAIN_LEVEL_STEP,
+                is_sustained,
+            };
+
+            let increment = {
+                let increment =
+                    fnum as f32 * (1 << block) as f32 * MULTIPLIERS[(flags & 0x0f) as usize]
+                        / (1 << 19) as f32;
+
+                match flags & PATCH_VIBRATO {
+                    0 => increment,
+                    _ => increment * vibrato,
+                }
+            };
+
+            let attenuation = {
+                let ksl = KEY_SCALE_LEVEL_SCALES[(patch[2 + side] >> 6) as usize] * key_scale_level;
+
+                match flags & PATCH_AM {
+                    0 => ksl,
+                    _ => ksl + am,
+                }
+            };
+
+            (rates, increment, attenuation)
+        };
+
+        let (modulator_rates, modulator_increment, modulator_attenuation) = operator_settings(0);
+        let (carrier_rates, carrier_increment, carrier_attenuation) = operator_settings(1);
+
+        let total_level = (patch[2] & 0x3f) as f32 * TOTAL_LEVEL_STEP;
+        let feedback = patch[3] & 0x07;
+
+        let (modulator, carrier) = &mut self.operators[channel];
+
+        modulator.clock_envelope(&modulator_rates);
+        carrier.clock_envelope(&carrier_rates);
+
+        let feedback_modulation = match feedback {
+            0 => 0.0,
+            _ => {
+                (modulator.outputs[0] + modulator.outputs[1]) / 2.0 * 2f32.powi(feedback as i32 - 6)
+            }
+        };
+
+        let modulator_output = modulator.output(
+            modulator_increment,
+            modulator_attenuation + total_level,
+            feedback_modulation,
+            patch[3] & 0x08 != 0,
+        );
+
+        modulator.outputs = [modulator.outputs[1], modulator_output];
+
+        carrier.output(
+            carrier_increment,
+            carrier_attenuation + volume as f32 * VOLUME_STEP,
+            modulator_output * MODULATION_DEPTH,
+            patch[3] & 0x10 != 0,
+        )
+    }
+
+    pub fn output(&self) -> f32 {
+        self.sample * OUTPUT_SCALE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(audio: &mut Vrc7Audio, register: u8, val: u8) {
+        audio.write_register_select(register);
+        audio.write_register(val);
+    }
+
+    // A custom patch that's close to a plain sine: an instant attack, no decay,
+    // a quiet modulator, and a fast release
+    fn sine_patch(audio: &mut Vrc7Audio) {
+        let patch = [0x21, 0x21, 0x3f, 0x00, 0xf0, 0xf0, 0x0f, 0x0f];
+
+        for (register, val) in patch.iter().enumerate() {
+            write(audio, register as u8, *val);
+        }
+    }
+
+    fn samples(audio: &mut Vrc7Audio, num_samples: usize) -> Vec<f32> {
+        (0..num_samples)
+            .map(|_| {
+                for _ in 0..SAMPLE_CYCLES {
+                    audio.clock();
+                }
+
+                audio.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn patches() {
+        let mut audio = Vrc7Audio::new();
+
+        sine_patch(&mut audio);
+        assert_eq!(
+            audio.patch(0),
+            [0x21, 0x21, 0x3f, 0x00, 0xf0, 0xf0, 0x0f, 0x0f]
+        );
+
+        write(&mut audio, 0x31, 0x30);
+        assert_eq!(audio.patch(1), PATCHES[2]);
+    }
+
+    #[test]
+    fn pitch() {
+        let mut audio = Vrc7Audio::new();
+        sine_patch(&mut audio);
+
+        // an fnum of 256 in block 4 is about 388Hz
+        write(&mut audio, 0x10, 0x00);
+        write(&mut audio, 0x30, 0x00);
+        write(&mut audio, 0x20, CHANNEL_KEY_ON | (4 << 1) | 0x01);
+
+        let samples = samples(&mut audio, SAMPLE_RATE as usize / 4);
+
+        let rising_edges = samples
+            .windows(2)
+            .filter(|pair| pair[0] <= 0.0 && pair[1] > 0.0)
+            .count();
+
+        assert_eq!(rising_edges, 97);
+
+        let peak = samples.iter().cloned().fold(0.0, f32::max);
+        assert!((peak - OUTPUT_SCALE).abs() < 0.01);
+    }
+
+    #[test]
+    fn key_off() {
+        let mut audio = Vrc7Audio::new();
+        sine_patch(&mut audio);
+
+        write(&mut audio, 0x10, 0x00);
+        write(&mut audio, 0x20, CHANNEL_KEY_ON | (4 << 1) | 0x01);
+        assert!(samples(&mut audio, 100).iter().any(|sample| *sample != 0.0));
+
+        // the release rate of 15 is silent within a few milliseconds
+        write(&mut audio, 0x20, (4 << 1) | 0x01);
+        samples(&mut audio, 500);
+
+        assert_eq!(audio.operators[0].1.state, EnvelopeState::Off);
+        assert!(samples(&mut audio, 100).iter().all(|sample| *sample == 0.0));
+    }
+}
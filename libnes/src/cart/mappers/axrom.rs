@@ -0,0 +1,127 @@
+use crate::cart::mappers::{bus_conflict, BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x8000;
+const CHR_SIZE: usize = 0x2000;
+
+const PRG_BANK_MASK: u8 = 0x07;
+const SCREEN_SELECT_MASK: u8 = 0x10;
+
+// see https://wiki.nesdev.com/w/index.php/AxROM
+pub struct AxROMMapper {
+    prg_rom: BankedMemory,
+    chr: BankedMemory,
+    bus_conflicts: bool,
+
+    bank_select: u8,
+}
+
+impl AxROMMapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        AxROMMapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            chr: options.chr(),
+            bus_conflicts: options.bus_conflicts,
+            bank_select: 0,
+        }
+    }
+
+    fn prg_bank(&self) -> usize {
+        (self.bank_select & PRG_BANK_MASK) as usize
+    }
+}
+
+impl Mapper for AxROMMapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000...0xffff => Some(self.prg_rom.read(self.prg_bank(), PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x8000...0xffff => {
+                let rom_val = self.prg_rom.read(self.prg_bank(), PRG_BANK_SIZE, addr);
+
+                self.bank_select = bus_conflict(self.bus_conflicts, rom_val, val);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(0, CHR_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(0, CHR_SIZE, addr, val);
+    }
+
+    // The board picks which of the two nametables fills the screen
+    fn mirroring(&self) -> Mirroring {
+        match self.bank_select & SCREEN_SELECT_MASK {
+            0 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn axrom(bus_conflicts: bool) -> AxROMMapper {
+        // numbered banks with the top bits set, so bus conflicts don't clear them
+        let prg_rom: Vec<u8> = numbered_banks(8, PRG_BANK_SIZE)
+            .iter()
+            .map(|bank| 0xf0 | bank)
+            .collect();
+
+        AxROMMapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            bus_conflicts,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = axrom(false);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(0xf0));
+
+        mapper.cpu_write(0x8000, 0x03);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0xf3));
+        assert_eq!(mapper.cpu_read(0xffff), Some(0xf3));
+
+        // only the low 3 bits select a bank
+        mapper.cpu_write(0x8000, 0x0e);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0xf6));
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mapper = axrom(false);
+
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+
+        mapper.cpu_write(0x8000, 0x11);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0xf1));
+
+        mapper.cpu_write(0x8000, 0x01);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        let mut mapper = axrom(true);
+
+        // bank 0 is all 0xf0, so the screen select bit makes it through but the bank doesn't
+        mapper.cpu_write(0x8000, 0x13);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0xf0));
+    }
+}
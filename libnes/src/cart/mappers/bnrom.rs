@@ -0,0 +1,210 @@
+use crate::cart::mappers::{
+    bus_conflict, BankedMemory, Mapper, MapperOptions, CHR_RAM_SIZE, PRG_RAM_UNIT_SIZE,
+};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x8000;
+const CHR_SIZE: usize = 0x2000;
+const NINA_001_CHR_BANK_SIZE: usize = 0x1000;
+
+// Mapper 34 is two unrelated boards: nes 2.0 headers say which, and otherwise
+// only nina-001 has more chr than one 8K bank
+pub fn is_nina_001(options: &MapperOptions) -> bool {
+    match options.submapper {
+        1 => true,
+        2 => false,
+        _ => options.chr_rom.len() > CHR_RAM_SIZE,
+    }
+}
+
+// see https://wiki.nesdev.com/w/index.php/BNROM
+pub struct BNROMMapper {
+    prg_rom: BankedMemory,
+    chr: BankedMemory,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+
+    prg_bank: u8,
+}
+
+impl BNROMMapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        BNROMMapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            chr: options.chr(),
+            mirroring: options.mirroring,
+            bus_conflicts: options.bus_conflicts,
+            prg_bank: 0,
+        }
+    }
+}
+
+impl Mapper for BNROMMapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank as usize, PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x8000...0xffff => {
+                let rom_val = self
+                    .prg_rom
+                    .read(self.prg_bank as usize, PRG_BANK_SIZE, addr);
+
+                self.prg_bank = bus_conflict(self.bus_conflicts, rom_val, val);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(0, CHR_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(0, CHR_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+// The bank registers sit at the top of prg ram, and writes to them land in both,
+// see https://wiki.nesdev.com/w/index.php/NINA-001
+pub struct NINA001Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+    mirroring: Mirroring,
+
+    prg_bank: u8,
+    chr_banks: [u8; 2],
+}
+
+impl NINA001Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        NINA001Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            mirroring: options.mirroring,
+            prg_bank: 0,
+            chr_banks: [0; 2],
+        }
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        self.chr_banks[(addr as usize) / NINA_001_CHR_BANK_SIZE] as usize
+    }
+}
+
+impl Mapper for NINA001Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff => Some(self.prg_ram.read(0, PRG_RAM_UNIT_SIZE, addr)),
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank as usize, PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x7ffd => self.prg_bank = val & 0x01,
+            0x7ffe => self.chr_banks[0] = val & 0x0f,
+            0x7fff => self.chr_banks[1] = val & 0x0f,
+            _ => {}
+        }
+
+        match addr {
+            0x6000...0x7fff => self.prg_ram.write(0, PRG_RAM_UNIT_SIZE, addr, val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr
+            .read(self.chr_bank_at(addr), NINA_001_CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let bank = self.chr_bank_at(addr);
+
+        self.chr.write(bank, NINA_001_CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn options<'a>(prg_rom: &'a [u8], chr_rom: &'a [u8]) -> MapperOptions<'a> {
+        MapperOptions {
+            prg_rom,
+            chr_rom,
+            prg_ram_size: PRG_RAM_UNIT_SIZE,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn picks_board() {
+        let prg_rom = numbered_banks(2, PRG_BANK_SIZE);
+        let chr_rom = numbered_banks(4, NINA_001_CHR_BANK_SIZE);
+
+        assert_eq!(is_nina_001(&options(&prg_rom, &[])), false);
+        assert_eq!(is_nina_001(&options(&prg_rom, &chr_rom)), true);
+
+        let mut nes_2_options = options(&prg_rom, &chr_rom);
+        nes_2_options.submapper = 2;
+        assert_eq!(is_nina_001(&nes_2_options), false);
+    }
+
+    #[test]
+    fn bnrom_banks() {
+        let prg_rom = numbered_banks(4, PRG_BANK_SIZE);
+        let mut mapper = BNROMMapper::new(&options(&prg_rom, &[]));
+
+        mapper.cpu_write(0x8000, 0x03);
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xffff), Some(3));
+
+        mapper.ppu_write(0x1234, 0x56);
+        assert_eq!(mapper.ppu_read(0x1234), 0x56);
+    }
+
+    #[test]
+    fn nina_001_banks() {
+        let prg_rom = numbered_banks(2, PRG_BANK_SIZE);
+        let chr_rom = numbered_banks(16, NINA_001_CHR_BANK_SIZE);
+        let mut mapper = NINA001Mapper::new(&options(&prg_rom, &chr_rom));
+
+        mapper.cpu_write(0x7ffd, 0x01);
+        mapper.cpu_write(0x7ffe, 0x05);
+        mapper.cpu_write(0x7fff, 0x0a);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(1));
+        assert_eq!(mapper.ppu_read(0x0000), 5);
+        assert_eq!(mapper.ppu_read(0x1000), 10);
+
+        // the registers' writes land in prg ram too
+        assert_eq!(mapper.cpu_read(0x7fff), Some(0x0a));
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_SIZE: usize = 0x2000;
+
+const SCREEN_SELECT_MASK: u8 = 0x10;
+
+// Camerica's boards are UxROM-like, with the bank register at $c000,
+// see https://wiki.nesdev.com/w/index.php/INES_Mapper_071
+pub struct CamericaMapper {
+    prg_rom: BankedMemory,
+    chr: BankedMemory,
+    mirroring: Mirroring,
+
+    prg_bank: u8,
+}
+
+impl CamericaMapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        CamericaMapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            chr: options.chr(),
+            mirroring: options.mirroring,
+            prg_bank: 0,
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        match addr {
+            0x8000...0xbfff => self.prg_bank as usize,
+            _ => self.prg_rom.num_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+}
+
+impl Mapper for CamericaMapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            // only Fire Hawk's board (submapper 1) has the single-screen select, but
+            // ines 1.0 dumps can't say so; no other mapper 71 game writes here, so
+            // every cart gets it
+            0x9000...0x9fff => {
+                self.mirroring = match val & SCREEN_SELECT_MASK {
+                    0 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                }
+            }
+            0xc000...0xffff => self.prg_bank = val & 0x0f,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(0, CHR_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(0, CHR_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn camerica() -> CamericaMapper {
+        let prg_rom = numbered_banks(8, PRG_BANK_SIZE);
+
+        CamericaMapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            mirroring: Mirroring::Vertical,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = camerica();
+
+        assert_eq!(mapper.cpu_read(0xc000), Some(7));
+
+        // writes below $c000 don't touch the bank
+        mapper.cpu_write(0x8000, 0x03);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+
+        mapper.cpu_write(0xc000, 0x03);
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xffff), Some(7));
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mapper = camerica();
+        assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+
+        mapper.cpu_write(0x9000, 0x10);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+
+        mapper.cpu_write(0x9000, 0x00);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::cart::mappers::{bus_conflict, BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_ROM_SIZE: usize = 0x8000;
+const CHR_BANK_SIZE: usize = 0x2000;
+
+// see https://wiki.nesdev.com/w/index.php/CNROM
+pub struct CNROMMapper {
+    prg_rom: BankedMemory,
+    chr: BankedMemory,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+
+    chr_bank: u8,
+}
+
+impl CNROMMapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        CNROMMapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            chr: options.chr(),
+            mirroring: options.mirroring,
+            bus_conflicts: options.bus_conflicts,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for CNROMMapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            // 16K carts show up twice, like nrom
+            0x8000...0xffff => Some(self.prg_rom.read(0, PRG_ROM_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x8000...0xffff => {
+                let rom_val = self.prg_rom.read(0, PRG_ROM_SIZE, addr);
+
+                self.chr_bank = bus_conflict(self.bus_conflicts, rom_val, val);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank as usize, CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr
+            .write(self.chr_bank as usize, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn cnrom(prg_rom: &[u8], bus_conflicts: bool) -> CNROMMapper {
+        let chr_rom = numbered_banks(4, CHR_BANK_SIZE);
+
+        CNROMMapper::new(&MapperOptions {
+            prg_rom,
+            chr_rom: &chr_rom,
+            bus_conflicts,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut mapper = cnrom(&[0; 0x4000], false);
+
+        assert_eq!(mapper.ppu_read(0x1fff), 0);
+
+        mapper.cpu_write(0x8000, 0x02);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+        assert_eq!(mapper.ppu_read(0x1fff), 2);
+
+        // chr rom isn't writable
+        mapper.ppu_write(0x0000, 0xff);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        let mut prg_rom = vec![0xff; 0x8000];
+        prg_rom[0x0000] = 0x01;
+
+        let mut mapper = cnrom(&prg_rom, true);
+
+        mapper.cpu_write(0x8001, 0x03);
+        assert_eq!(mapper.ppu_read(0x0000), 3);
+
+        mapper.cpu_write(0x8000, 0x02);
+        assert_eq!(mapper.ppu_read(0x0000), 0);
+    }
+}
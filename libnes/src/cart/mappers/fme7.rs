@@ -0,0 +1,269 @@
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions, Sunsoft5BAudio};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const COMMAND_MASK: u8 = 0x0f;
+
+const PRG_BANK_MASK: u8 = 0x3f;
+const PRG_RAM_SELECT: u8 = 0x40;
+const PRG_RAM_ENABLE: u8 = 0x80;
+
+const IRQ_ENABLE: u8 = 0x01;
+const IRQ_COUNTER_ENABLE: u8 = 0x80;
+
+// Registers are written by picking a command at $8000 and then writing its
+// parameter to $a000; the sunsoft 5b is an fme-7 with expansion audio, and
+// shares its mapper number, see https://wiki.nesdev.com/w/index.php/Sunsoft_FME-7
+pub struct FME7Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+
+    command: u8,
+    chr_banks: [u8; 8],
+
+    // $6000, $8000, $a000 and $c000
+    prg_banks: [u8; 4],
+    mirroring: u8,
+
+    irq_control: u8,
+    irq_counter: u16,
+    irq_pending: bool,
+
+    audio: Sunsoft5BAudio,
+}
+
+impl FME7Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        FME7Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            command: 0,
+            chr_banks: [0; 8],
+            prg_banks: [0; 4],
+            mirroring: 0,
+            irq_control: 0,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: Sunsoft5BAudio::new(),
+        }
+    }
+
+    fn write_parameter(&mut self, val: u8) {
+        match self.command {
+            0x0...0x7 => self.chr_banks[self.command as usize] = val,
+            0x8...0xb => self.prg_banks[(self.command - 0x8) as usize] = val,
+            0xc => self.mirroring = val & 0x03,
+            0xd => {
+                self.irq_control = val;
+                self.irq_pending = false;
+            }
+            0xe => self.irq_counter = (self.irq_counter & 0xff00) | val as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00ff) | ((val as u16) << 8),
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        match addr {
+            0x6000...0xdfff => {
+                let register = self.prg_banks[((addr - 0x6000) as usize) / PRG_BANK_SIZE];
+
+                (register & PRG_BANK_MASK) as usize
+            }
+            _ => self.prg_rom.num_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+
+    fn is_prg_ram_selected(&self) -> bool {
+        self.prg_banks[0] & PRG_RAM_SELECT != 0
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.prg_banks[0] & PRG_RAM_ENABLE != 0
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        self.chr_banks[(addr as usize) / CHR_BANK_SIZE] as usize
+    }
+}
+
+impl Mapper for FME7Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        let bank = self.prg_bank_at(addr);
+
+        // $6000 can be rom, ram, or (disabled ram) nothing at all
+        match (addr, self.is_prg_ram_selected(), self.is_prg_ram_enabled()) {
+            (0x6000...0x7fff, false, _) => Some(self.prg_rom.read(bank, PRG_BANK_SIZE, addr)),
+            (0x6000...0x7fff, true, false) => None,
+            (0x6000...0x7fff, true, true) => Some(self.prg_ram.read(bank, PRG_BANK_SIZE, addr)),
+            (0x8000...0xffff, _, _) => Some(self.prg_rom.read(bank, PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        let is_ram_writable = self.is_prg_ram_selected() && self.is_prg_ram_enabled();
+
+        match addr {
+            0x6000...0x7fff if is_ram_writable => {
+                let bank = self.prg_bank_at(addr);
+
+                self.prg_ram.write(bank, PRG_BANK_SIZE, addr, val);
+            }
+            0x8000...0x9fff => self.command = val & COMMAND_MASK,
+            0xa000...0xbfff => self.write_parameter(val),
+            0xc000...0xdfff => self.audio.write_register_select(val),
+            0xe000...0xffff => self.audio.write_register(val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank_at(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let bank = self.chr_bank_at(addr);
+
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    // The counter counts down every cpu cycle, and fires as it wraps past 0
+    fn cpu_clock(&mut self) {
+        self.audio.clock();
+
+        if self.irq_control & IRQ_COUNTER_ENABLE == 0 {
+            return;
+        }
+
+        self.irq_counter = self.irq_counter.wrapping_sub(1);
+
+        if self.irq_counter == 0xffff && self.irq_control & IRQ_ENABLE != 0 {
+            self.irq_pending = true;
+        }
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn fme7() -> FME7Mapper {
+        let prg_rom = numbered_banks(32, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(256, CHR_BANK_SIZE);
+
+        FME7Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            prg_ram_size: PRG_BANK_SIZE,
+            ..Default::default()
+        })
+    }
+
+    fn write_command(mapper: &mut FME7Mapper, command: u8, val: u8) {
+        mapper.cpu_write(0x8000, command);
+        mapper.cpu_write(0xa000, val);
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = fme7();
+
+        write_command(&mut mapper, 0x9, 0x03);
+        write_command(&mut mapper, 0xa, 0x04);
+        write_command(&mut mapper, 0xb, 0x05);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xa000), Some(4));
+        assert_eq!(mapper.cpu_read(0xc000), Some(5));
+        assert_eq!(mapper.cpu_read(0xe000), Some(31));
+    }
+
+    #[test]
+    fn prg_ram_bank() {
+        let mut mapper = fme7();
+
+        // rom at $6000
+        write_command(&mut mapper, 0x8, 0x06);
+        assert_eq!(mapper.cpu_read(0x6000), Some(6));
+
+        // the enable bit only matters for ram
+        write_command(&mut mapper, 0x8, PRG_RAM_ENABLE | 0x06);
+        assert_eq!(mapper.cpu_read(0x6000), Some(6));
+
+        // disabled ram
+        write_command(&mut mapper, 0x8, PRG_RAM_SELECT);
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), None);
+
+        write_command(&mut mapper, 0x8, PRG_RAM_SELECT | PRG_RAM_ENABLE);
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+    }
+
+    #[test]
+    fn chr_banks_and_mirroring() {
+        let mut mapper = fme7();
+
+        for i in 0..8 {
+            write_command(&mut mapper, i, 0x40 + i);
+        }
+
+        let banks: Vec<u8> = (0..8).map(|i| mapper.ppu_read(i * 0x400)).collect();
+        assert_eq!(banks, vec![0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47]);
+
+        write_command(&mut mapper, 0xc, 0x01);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        write_command(&mut mapper, 0xc, 0x02);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut mapper = fme7();
+
+        write_command(&mut mapper, 0xe, 0x01);
+        write_command(&mut mapper, 0xf, 0x00);
+        write_command(&mut mapper, 0xd, IRQ_ENABLE | IRQ_COUNTER_ENABLE);
+
+        mapper.cpu_clock();
+        assert_eq!(mapper.irq(), false);
+
+        // fires on the way from 0 to $ffff
+        mapper.cpu_clock();
+        assert_eq!(mapper.irq(), true);
+
+        // writing the control register acknowledges it
+        write_command(&mut mapper, 0xd, IRQ_COUNTER_ENABLE);
+        assert_eq!(mapper.irq(), false);
+
+        // and the counter keeps going without firing
+        for _ in 0..0x10000 {
+            mapper.cpu_clock();
+        }
+        assert_eq!(mapper.irq(), false);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::cart::mappers::{bus_conflict, BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x8000;
+const CHR_BANK_SIZE: usize = 0x2000;
+
+const PRG_BANK_MASK: u8 = 0x30;
+const CHR_BANK_MASK: u8 = 0x03;
+
+// see https://wiki.nesdev.com/w/index.php/GxROM
+pub struct GxROMMapper {
+    prg_rom: BankedMemory,
+    chr: BankedMemory,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+
+    bank_select: u8,
+}
+
+impl GxROMMapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        GxROMMapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            chr: options.chr(),
+            mirroring: options.mirroring,
+            bus_conflicts: options.bus_conflicts,
+            bank_select: 0,
+        }
+    }
+
+    fn prg_bank(&self) -> usize {
+        ((self.bank_select & PRG_BANK_MASK) >> 4) as usize
+    }
+
+    fn chr_bank(&self) -> usize {
+        (self.bank_select & CHR_BANK_MASK) as usize
+    }
+}
+
+impl Mapper for GxROMMapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000...0xffff => Some(self.prg_rom.read(self.prg_bank(), PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x8000...0xffff => {
+                let rom_val = self.prg_rom.read(self.prg_bank(), PRG_BANK_SIZE, addr);
+
+                self.bank_select = bus_conflict(self.bus_conflicts, rom_val, val);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank(), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(self.chr_bank(), CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn gxrom(bus_conflicts: bool) -> GxROMMapper {
+        let prg_rom = numbered_banks(4, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(4, CHR_BANK_SIZE);
+
+        GxROMMapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            bus_conflicts,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn banks() {
+        let mut mapper = gxrom(false);
+
+        mapper.cpu_write(0x8000, 0x21);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(2));
+        assert_eq!(mapper.cpu_read(0xffff), Some(2));
+        assert_eq!(mapper.ppu_read(0x0000), 1);
+        assert_eq!(mapper.ppu_read(0x1fff), 1);
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        let mut mapper = gxrom(true);
+
+        // bank 0 is all zeroes, so nothing gets through
+        mapper.cpu_write(0x8000, 0x33);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.ppu_read(0x0000), 0);
+    }
+}
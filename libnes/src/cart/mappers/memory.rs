@@ -0,0 +1,102 @@
+// A rom or ram chip on the cart, addressed as a number of equally sized banks
+pub struct BankedMemory {
+    data: Vec<u8>,
+    writable: bool,
+}
+
+impl BankedMemory {
+    pub fn rom(data: &[u8]) -> Self {
+        BankedMemory {
+            data: data.to_vec(),
+            writable: false,
+        }
+    }
+
+    pub fn ram(size: usize) -> Self {
+        BankedMemory {
+            data: vec![0; size],
+            writable: true,
+        }
+    }
+
+    // Copies bytes in from offset, for ram that powers on holding something;
+    // whatever doesn't fit is dropped
+    pub fn load(&mut self, offset: usize, bytes: &[u8]) {
+        for (byte, val) in self.data.iter_mut().skip(offset).zip(bytes.iter()) {
+            *byte = *val;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn num_banks(&self, bank_size: usize) -> usize {
+        std::cmp::max(1, self.data.len() / bank_size)
+    }
+
+    // Reads addr within the given bank; bank numbers past the end of the chip
+    // wrap around, the same way unconnected address lines would
+    pub fn read(&self, bank: usize, bank_size: usize, addr: u16) -> u8 {
+        match self.offset(bank, bank_size, addr) {
+            Some(offset) => self.data[offset],
+            None => 0,
+        }
+    }
+
+    // Writes to rom are ignored
+    pub fn write(&mut self, bank: usize, bank_size: usize, addr: u16, val: u8) {
+        if !self.writable {
+            return;
+        }
+
+        if let Some(offset) = self.offset(bank, bank_size, addr) {
+            self.data[offset] = val;
+        }
+    }
+
+    fn offset(&self, bank: usize, bank_size: usize, addr: u16) -> Option<usize> {
+        match self.data.len() {
+            0 => None,
+            len => Some((bank * bank_size + (addr as usize % bank_size)) % len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn banks() {
+        let data: Vec<u8> = (0..4).flat_map(|bank| vec![bank; 0x1000]).collect();
+        let rom = BankedMemory::rom(&data);
+
+        assert_eq!(rom.num_banks(0x1000), 4);
+        assert_eq!(rom.read(2, 0x1000, 0x8123), 2);
+
+        // bank numbers wrap around
+        assert_eq!(rom.read(5, 0x1000, 0x8000), 1);
+
+        // a bank bigger than the chip mirrors it
+        assert_eq!(rom.read(0, 0x8000, 0x8000 + 0x3fff), 3);
+        assert_eq!(rom.read(0, 0x8000, 0x8000 + 0x4000), 0);
+    }
+
+    #[test]
+    fn writes() {
+        let mut rom = BankedMemory::rom(&[0; 0x10]);
+        rom.write(0, 0x10, 0x0001, 0xff);
+        assert_eq!(rom.read(0, 0x10, 0x0001), 0x00);
+
+        let mut ram = BankedMemory::ram(0x20);
+        ram.write(1, 0x10, 0x0001, 0xff);
+        assert_eq!(ram.read(1, 0x10, 0x0001), 0xff);
+        assert_eq!(ram.read(0, 0x10, 0x0001), 0x00);
+
+        // chips that aren't there read as 0
+        let mut none = BankedMemory::ram(0);
+        none.write(0, 0x10, 0x0000, 0xff);
+        assert_eq!(none.read(0, 0x10, 0x0000), 0x00);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x1000;
+
+// SUROM boards use the high chr bank bit to pick a 256K half of prg rom
+const PRG_OUTER_BANK_SIZE: usize = 0x40000;
+
+const CONTROL_POWER_ON: u8 = 0x0c;
+
+// see https://wiki.nesdev.com/w/index.php/MMC1
+pub struct MMC1Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+
+    // writes are shifted in a bit at a time, landing in a register on the fifth
+    shift: u8,
+    num_shifted: u8,
+
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+}
+
+impl MMC1Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        MMC1Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            shift: 0,
+            num_shifted: 0,
+            control: CONTROL_POWER_ON,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    fn write_shift_register(&mut self, addr: u16, val: u8) {
+        // writing a 1 to bit 7 resets the shift register and locks the last prg bank in
+        if val & 0x80 != 0 {
+            self.shift = 0;
+            self.num_shifted = 0;
+            self.control |= CONTROL_POWER_ON;
+
+            return;
+        }
+
+        self.shift |= (val & 0x01) << self.num_shifted;
+        self.num_shifted += 1;
+
+        if self.num_shifted < 5 {
+            return;
+        }
+
+        let val = self.shift;
+
+        match addr {
+            0x8000...0x9fff => self.control = val,
+            0xa000...0xbfff => self.chr_bank_0 = val,
+            0xc000...0xdfff => self.chr_bank_1 = val,
+            _ => self.prg_bank = val,
+        }
+
+        self.shift = 0;
+        self.num_shifted = 0;
+    }
+
+    fn prg_mode(&self) -> u8 {
+        (self.control >> 2) & 0x03
+    }
+
+    fn is_chr_4k_mode(&self) -> bool {
+        self.control & 0x10 != 0
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.prg_bank & 0x10 == 0
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        let outer_bank = match self.prg_rom.len() > PRG_OUTER_BANK_SIZE {
+            true => (self.chr_bank_0 & 0x10) as usize,
+            false => 0,
+        };
+
+        let bank = (self.prg_bank & 0x0f) as usize;
+        let last_bank = 0x0f;
+
+        let inner_bank = match (self.prg_mode(), addr) {
+            // 32K mode ignores the low bit
+            (0, 0x8000...0xbfff) | (1, 0x8000...0xbfff) => bank & !0x01,
+            (0, _) | (1, _) => bank | 0x01,
+            (2, 0x8000...0xbfff) => 0,
+            (2, _) => bank,
+            (_, 0x8000...0xbfff) => bank,
+            (_, _) => last_bank,
+        };
+
+        outer_bank | inner_bank
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        match (self.is_chr_4k_mode(), addr) {
+            (true, 0x0000...0x0fff) => self.chr_bank_0 as usize,
+            (true, _) => self.chr_bank_1 as usize,
+            // 8K mode ignores the low bit
+            (false, 0x0000...0x0fff) => (self.chr_bank_0 & !0x01) as usize,
+            (false, _) => (self.chr_bank_0 | 0x01) as usize,
+        }
+    }
+}
+
+impl Mapper for MMC1Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff if self.is_prg_ram_enabled() => {
+                Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr))
+            }
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x6000...0x7fff if self.is_prg_ram_enabled() => {
+                self.prg_ram.write(0, PRG_RAM_SIZE, addr, val)
+            }
+            0x8000...0xffff => self.write_shift_register(addr, val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank_at(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let bank = self.chr_bank_at(addr);
+
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn mmc1(num_prg_banks: usize, num_chr_banks: usize) -> MMC1Mapper {
+        let prg_rom = numbered_banks(num_prg_banks, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(num_chr_banks, CHR_BANK_SIZE);
+
+        MMC1Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            prg_ram_size: PRG_RAM_SIZE,
+            mirroring: Mirroring::Horizontal,
+            ..Default::default()
+        })
+    }
+
+    // Shifts val into the register at addr, lsb first
+    fn write_register(mapper: &mut MMC1Mapper, addr: u16, val: u8) {
+        for bit in 0..5 {
+            mapper.cpu_write(addr, (val >> bit) & 0x01);
+        }
+    }
+
+    #[test]
+    fn shift_register() {
+        let mut mapper = mmc1(2, 2);
+        write_register(&mut mapper, 0x8000, 0x03);
+
+        // nothing changes until the fifth write
+        for _ in 0..4 {
+            mapper.cpu_write(0x8000, 0x00);
+        }
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        mapper.cpu_write(0x8000, 0x00);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+
+        // only bit 0 of each write is shifted in, and only the address of the last write matters
+        for val in &[0x7e, 0x7f, 0x00, 0x00] {
+            mapper.cpu_write(0xe000, *val);
+        }
+        mapper.cpu_write(0x9fff, 0x00);
+        assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn reset() {
+        let mut mapper = mmc1(8, 2);
+
+        // switch $8000 in 32K mode
+        write_register(&mut mapper, 0x8000, 0x00);
+        assert_eq!(mapper.cpu_read(0xc000), Some(1));
+
+        // resetting partway through a write drops the shifted bits,
+        // and fixes the last bank at $c000
+        mapper.cpu_write(0x8000, 0x01);
+        mapper.cpu_write(0x8000, 0x01);
+        mapper.cpu_write(0x8000, 0x80);
+        assert_eq!(mapper.cpu_read(0xc000), Some(7));
+
+        write_register(&mut mapper, 0xe000, 0x02);
+        assert_eq!(mapper.cpu_read(0x8000), Some(2));
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mapper = mmc1(2, 2);
+
+        let mut mirroring_for = |control: u8| {
+            write_register(&mut mapper, 0x8000, control);
+            mapper.mirroring()
+        };
+
+        assert_eq!(mirroring_for(0x00), Mirroring::SingleScreenLower);
+        assert_eq!(mirroring_for(0x01), Mirroring::SingleScreenUpper);
+        assert_eq!(mirroring_for(0x02), Mirroring::Vertical);
+        assert_eq!(mirroring_for(0x03), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn prg_modes() {
+        let mut mapper = mmc1(8, 2);
+
+        // the last bank is fixed at $c000 on power on
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xffff), Some(7));
+
+        write_register(&mut mapper, 0xe000, 0x05);
+        assert_eq!(mapper.cpu_read(0x8000), Some(5));
+        assert_eq!(mapper.cpu_read(0xc000), Some(7));
+
+        // 32K mode ignores the low bit of the bank
+        write_register(&mut mapper, 0x8000, 0x00);
+        assert_eq!(mapper.cpu_read(0x8000), Some(4));
+        assert_eq!(mapper.cpu_read(0xc000), Some(5));
+
+        write_register(&mut mapper, 0x8000, 0x04);
+        assert_eq!(mapper.cpu_read(0x8000), Some(4));
+
+        // first bank fixed at $8000
+        write_register(&mut mapper, 0x8000, 0x08);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xc000), Some(5));
+
+        // last bank fixed at $c000
+        write_register(&mut mapper, 0x8000, 0x0c);
+        assert_eq!(mapper.cpu_read(0x8000), Some(5));
+        assert_eq!(mapper.cpu_read(0xc000), Some(7));
+    }
+
+    #[test]
+    fn surom_prg_banks() {
+        let mut mapper = mmc1(32, 0);
+
+        // the last bank of the selected 256K half
+        assert_eq!(mapper.cpu_read(0xc000), Some(15));
+
+        write_register(&mut mapper, 0xa000, 0x10);
+        write_register(&mut mapper, 0xe000, 0x02);
+        assert_eq!(mapper.cpu_read(0x8000), Some(18));
+        assert_eq!(mapper.cpu_read(0xc000), Some(31));
+    }
+
+    #[test]
+    fn chr_modes() {
+        let mut mapper = mmc1(2, 8);
+
+        write_register(&mut mapper, 0xa000, 0x03);
+        write_register(&mut mapper, 0xc000, 0x06);
+
+        // 8K mode ignores chr bank 1 and the low bit of chr bank 0
+        write_register(&mut mapper, 0x8000, 0x00);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+        assert_eq!(mapper.ppu_read(0x1000), 3);
+
+        write_register(&mut mapper, 0x8000, 0x10);
+        assert_eq!(mapper.ppu_read(0x0000), 3);
+        assert_eq!(mapper.ppu_read(0x1fff), 6);
+    }
+
+    #[test]
+    fn chr_ram() {
+        let mut mapper = mmc1(2, 0);
+
+        write_register(&mut mapper, 0x8000, 0x10);
+        write_register(&mut mapper, 0xc000, 0x00);
+
+        // both 4K windows pointing at the same bank
+        mapper.ppu_write(0x1123, 0x45);
+        assert_eq!(mapper.ppu_read(0x0123), 0x45);
+    }
+
+    #[test]
+    fn prg_ram_enable() {
+        let mut mapper = mmc1(2, 2);
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+
+        write_register(&mut mapper, 0xe000, 0x10);
+        assert_eq!(mapper.cpu_read(0x6000), None);
+
+        mapper.cpu_write(0x6000, 0x34);
+        write_register(&mut mapper, 0xe000, 0x00);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x1000;
+
+const LATCH_FD: usize = 0;
+const LATCH_FE: usize = 1;
+
+// Each 4K half of chr has two banks, picked by a latch that flips when the ppu
+// fetches the high plane of tile $fd or $fe from that half
+pub struct ChrLatches {
+    chr: BankedMemory,
+    banks: [[u8; 2]; 2],
+    latches: [usize; 2],
+
+    // mmc2 only watches a single address for the lower half's latch, mmc4 watches all 8 rows
+    exact_lower_triggers: bool,
+}
+
+impl ChrLatches {
+    pub fn new(chr: BankedMemory, exact_lower_triggers: bool) -> Self {
+        ChrLatches {
+            chr,
+            banks: [[0; 2]; 2],
+            latches: [LATCH_FE; 2],
+            exact_lower_triggers,
+        }
+    }
+
+    pub fn set_bank(&mut self, half: usize, latch: usize, bank: u8) {
+        self.banks[half][latch] = bank & 0x1f;
+    }
+
+    // The latch flips after the fetch, so the triggering byte still comes from the old bank
+    pub fn read(&mut self, addr: u16) -> u8 {
+        let val = self.chr.read(self.bank_at(addr), CHR_BANK_SIZE, addr);
+
+        let half = ((addr >> 12) & 0x01) as usize;
+        let is_trigger_row = match (half, self.exact_lower_triggers) {
+            (0, true) => addr & 0x0007 == 0,
+            _ => true,
+        };
+
+        match addr & 0x0ff8 {
+            0x0fd8 if is_trigger_row => self.latches[half] = LATCH_FD,
+            0x0fe8 if is_trigger_row => self.latches[half] = LATCH_FE,
+            _ => {}
+        }
+
+        val
+    }
+
+    pub fn write(&mut self, addr: u16, val: u8) {
+        let bank = self.bank_at(addr);
+
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn bank_at(&self, addr: u16) -> usize {
+        let half = ((addr >> 12) & 0x01) as usize;
+
+        self.banks[half][self.latches[half]] as usize
+    }
+}
+
+// Writes to $b000-$f000 shared by mmc2 and mmc4, returning false for other addresses
+pub fn write_chr_latch_register(
+    chr: &mut ChrLatches,
+    is_horizontal_mirroring: &mut bool,
+    addr: u16,
+    val: u8,
+) -> bool {
+    match addr {
+        0xb000...0xbfff => chr.set_bank(0, LATCH_FD, val),
+        0xc000...0xcfff => chr.set_bank(0, LATCH_FE, val),
+        0xd000...0xdfff => chr.set_bank(1, LATCH_FD, val),
+        0xe000...0xefff => chr.set_bank(1, LATCH_FE, val),
+        0xf000...0xffff => *is_horizontal_mirroring = val & 0x01 != 0,
+        _ => return false,
+    }
+
+    true
+}
+
+// see https://wiki.nesdev.com/w/index.php/MMC2
+pub struct MMC2Mapper {
+    prg_rom: BankedMemory,
+    chr: ChrLatches,
+
+    prg_bank: u8,
+    is_horizontal_mirroring: bool,
+}
+
+impl MMC2Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        MMC2Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            chr: ChrLatches::new(options.chr(), true),
+            prg_bank: 0,
+            is_horizontal_mirroring: options.mirroring == Mirroring::Horizontal,
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        let num_banks = self.prg_rom.num_banks(PRG_BANK_SIZE);
+
+        match addr {
+            0x8000...0x9fff => self.prg_bank as usize,
+            // the last three banks are fixed at $a000-$ffff
+            _ => num_banks - 4 + ((addr - 0x8000) / PRG_BANK_SIZE as u16) as usize,
+        }
+    }
+}
+
+impl Mapper for MMC2Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        if write_chr_latch_register(&mut self.chr, &mut self.is_horizontal_mirroring, addr, val) {
+            return;
+        }
+
+        match addr {
+            0xa000...0xafff => self.prg_bank = val & 0x0f,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.is_horizontal_mirroring {
+            true => Mirroring::Horizontal,
+            false => Mirroring::Vertical,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn mmc2() -> MMC2Mapper {
+        let prg_rom = numbered_banks(16, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(32, CHR_BANK_SIZE);
+
+        MMC2Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            ..Default::default()
+        })
+    }
+
+    // Fetches both planes of one row of a tile, like the ppu does each scanline
+    fn fetch_row(mapper: &mut MMC2Mapper, table_addr: u16, tile: u8, row: u16) -> (u8, u8) {
+        let tile_addr = table_addr + tile as u16 * 16 + row;
+
+        (mapper.ppu_read(tile_addr), mapper.ppu_read(tile_addr + 8))
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = mmc2();
+
+        mapper.cpu_write(0xa000, 0x05);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(5));
+        assert_eq!(mapper.cpu_read(0xa000), Some(13));
+        assert_eq!(mapper.cpu_read(0xc000), Some(14));
+        assert_eq!(mapper.cpu_read(0xe000), Some(15));
+    }
+
+    #[test]
+    fn latches() {
+        let mut mapper = mmc2();
+
+        mapper.cpu_write(0xb000, 1);
+        mapper.cpu_write(0xc000, 2);
+        mapper.cpu_write(0xd000, 3);
+        mapper.cpu_write(0xe000, 4);
+
+        // both latches start out on $fe
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+        assert_eq!(mapper.ppu_read(0x1000), 4);
+
+        // tile $fd itself still comes from the $fe bank, then the latch flips
+        assert_eq!(fetch_row(&mut mapper, 0x0000, 0xfd, 0), (2, 2));
+        assert_eq!(fetch_row(&mut mapper, 0x0000, 0x00, 0), (1, 1));
+
+        // the upper half's latch is separate
+        assert_eq!(mapper.ppu_read(0x1000), 4);
+
+        assert_eq!(fetch_row(&mut mapper, 0x1000, 0xfd, 5), (4, 4));
+        assert_eq!(mapper.ppu_read(0x1000), 3);
+
+        assert_eq!(fetch_row(&mut mapper, 0x1000, 0xfe, 2), (3, 3));
+        assert_eq!(mapper.ppu_read(0x1000), 4);
+
+        fetch_row(&mut mapper, 0x0000, 0xfe, 0);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+    }
+
+    #[test]
+    fn lower_latch_triggers_on_first_row_only() {
+        let mut mapper = mmc2();
+
+        mapper.cpu_write(0xb000, 1);
+        mapper.cpu_write(0xc000, 2);
+
+        // rows past the first of tile $fd don't flip the lower latch
+        mapper.ppu_read(0x0fd9);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+
+        mapper.ppu_read(0x0fd8);
+        assert_eq!(mapper.ppu_read(0x0000), 1);
+
+        // but the upper half's triggers cover every row
+        mapper.cpu_write(0xd000, 3);
+        mapper.ppu_read(0x1fdf);
+        assert_eq!(mapper.ppu_read(0x1000), 3);
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mapper = mmc2();
+
+        mapper.cpu_write(0xf000, 0x01);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        mapper.cpu_write(0xf000, 0x00);
+        assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+    }
+}
@@ -0,0 +1,398 @@
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const BANK_SELECT_REGISTER_MASK: u8 = 0x07;
+const BANK_SELECT_PRG_MODE: u8 = 0x40;
+const BANK_SELECT_CHR_INVERSION: u8 = 0x80;
+
+const PRG_RAM_ENABLE: u8 = 0x80;
+const PRG_RAM_WRITE_PROTECT: u8 = 0x40;
+
+// A12 has to have been low for a few M2 cycles before a rise clocks the
+// counter, which filters out the short dips between sprite pattern fetches
+const A12_LOW_CYCLES_FILTER: u8 = 3;
+
+// see https://wiki.nesdev.com/w/index.php/MMC3
+pub struct MMC3Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+    is_four_screen: bool,
+
+    bank_select: u8,
+    banks: [u8; 8],
+    is_horizontal_mirroring: bool,
+    prg_ram_protect: u8,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+
+    a12: bool,
+    a12_low_cycles: u8,
+}
+
+impl MMC3Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        MMC3Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            is_four_screen: options.mirroring == Mirroring::FourScreen,
+            bank_select: 0,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            is_horizontal_mirroring: options.mirroring == Mirroring::Horizontal,
+            prg_ram_protect: PRG_RAM_ENABLE,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12: false,
+            a12_low_cycles: 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        let is_even = addr & 0x01 == 0;
+
+        match (addr, is_even) {
+            (0x8000...0x9fff, true) => self.bank_select = val,
+            (0x8000...0x9fff, false) => {
+                let register = (self.bank_select & BANK_SELECT_REGISTER_MASK) as usize;
+
+                self.banks[register] = val;
+            }
+            (0xa000...0xbfff, true) => self.is_horizontal_mirroring = val & 0x01 != 0,
+            (0xa000...0xbfff, false) => self.prg_ram_protect = val,
+            (0xc000...0xdfff, true) => self.irq_latch = val,
+            (0xc000...0xdfff, false) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (_, true) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            (_, false) => self.irq_enabled = true,
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        let second_last_bank = self.prg_rom.num_banks(PRG_BANK_SIZE) - 2;
+        let last_bank = second_last_bank + 1;
+
+        let is_swapped = self.bank_select & BANK_SELECT_PRG_MODE != 0;
+
+        match (addr, is_swapped) {
+            (0x8000...0x9fff, false) | (0xc000...0xdfff, true) => (self.banks[6] & 0x3f) as usize,
+            (0x8000...0x9fff, true) | (0xc000...0xdfff, false) => second_last_bank,
+            (0xa000...0xbfff, _) => (self.banks[7] & 0x3f) as usize,
+            _ => last_bank,
+        }
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        // inversion swaps the 2K banks at $0000 with the 1K banks at $1000
+        let addr = match self.bank_select & BANK_SELECT_CHR_INVERSION {
+            0 => addr,
+            _ => addr ^ 0x1000,
+        };
+
+        let bank = match addr {
+            0x0000...0x07ff => (self.banks[0] & 0xfe) | ((addr >> 10) & 0x01) as u8,
+            0x0800...0x0fff => (self.banks[1] & 0xfe) | ((addr >> 10) & 0x01) as u8,
+            0x1000...0x13ff => self.banks[2],
+            0x1400...0x17ff => self.banks[3],
+            0x1800...0x1bff => self.banks[4],
+            _ => self.banks[5],
+        };
+
+        bank as usize
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.prg_ram_protect & PRG_RAM_ENABLE != 0
+    }
+
+    // The counter is clocked by rises of the ppu's A12 address line, which happen
+    // once per scanline when the background and sprites use different pattern tables
+    fn watch_a12(&mut self, addr: u16) {
+        let a12 = addr & 0x1000 != 0;
+
+        if a12 && !self.a12 && self.a12_low_cycles >= A12_LOW_CYCLES_FILTER {
+            self.clock_irq_counter();
+        }
+
+        if !a12 && self.a12 {
+            self.a12_low_cycles = 0;
+        }
+
+        self.a12 = a12;
+    }
+
+    fn clock_irq_counter(&mut self) {
+        match self.irq_counter == 0 || self.irq_reload {
+            true => {
+                self.irq_counter = self.irq_latch;
+                self.irq_reload = false;
+            }
+            false => self.irq_counter -= 1,
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for MMC3Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff if self.is_prg_ram_enabled() => {
+                Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr))
+            }
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x6000...0x7fff => {
+                if self.is_prg_ram_enabled() && self.prg_ram_protect & PRG_RAM_WRITE_PROTECT == 0 {
+                    self.prg_ram.write(0, PRG_RAM_SIZE, addr, val);
+                }
+            }
+            0x8000...0xffff => self.write_register(addr, val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.watch_a12(addr);
+
+        self.chr.read(self.chr_bank_at(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.watch_a12(addr);
+
+        let bank = self.chr_bank_at(addr);
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.is_four_screen, self.is_horizontal_mirroring) {
+            (true, _) => Mirroring::FourScreen,
+            (false, true) => Mirroring::Horizontal,
+            (false, false) => Mirroring::Vertical,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn cpu_clock(&mut self) {
+        if !self.a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+    use crate::ppu::{DefaultPpu, Ppu, PPUCTRL, PPUMASK};
+    use crate::util::rc_ref;
+
+    fn mmc3() -> MMC3Mapper {
+        let prg_rom = numbered_banks(16, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(64, CHR_BANK_SIZE);
+
+        MMC3Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            prg_ram_size: PRG_RAM_SIZE,
+            mirroring: Mirroring::Vertical,
+            ..Default::default()
+        })
+    }
+
+    fn set_bank(mapper: &mut MMC3Mapper, bank_select: u8, bank: u8) {
+        mapper.cpu_write(0x8000, bank_select);
+        mapper.cpu_write(0x8001, bank);
+    }
+
+    // Toggles A12 the way a scanline's fetches would, with time for the filter in between
+    fn clock_scanline(mapper: &mut MMC3Mapper) {
+        mapper.ppu_read(0x0000);
+
+        for _ in 0..A12_LOW_CYCLES_FILTER {
+            mapper.cpu_clock();
+        }
+
+        mapper.ppu_read(0x1000);
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = mmc3();
+
+        set_bank(&mut mapper, 0x06, 0x03);
+        set_bank(&mut mapper, 0x07, 0x05);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xa000), Some(5));
+        assert_eq!(mapper.cpu_read(0xc000), Some(14));
+        assert_eq!(mapper.cpu_read(0xe000), Some(15));
+
+        // prg mode 1 swaps $8000 and $c000
+        set_bank(&mut mapper, 0x46, 0x03);
+        assert_eq!(mapper.cpu_read(0x8000), Some(14));
+        assert_eq!(mapper.cpu_read(0xa000), Some(5));
+        assert_eq!(mapper.cpu_read(0xc000), Some(3));
+        assert_eq!(mapper.cpu_read(0xe000), Some(15));
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut mapper = mmc3();
+
+        for (register, bank) in [9u8, 20, 30, 31, 32, 33].iter().enumerate() {
+            set_bank(&mut mapper, register as u8, *bank);
+        }
+
+        // the 2K banks ignore their low bit
+        let banks: Vec<u8> = (0..8).map(|i| mapper.ppu_read(i * 0x400)).collect();
+        assert_eq!(banks, vec![8, 9, 20, 21, 30, 31, 32, 33]);
+
+        mapper.cpu_write(0x8000, 0x80);
+
+        let banks: Vec<u8> = (0..8).map(|i| mapper.ppu_read(i * 0x400)).collect();
+        assert_eq!(banks, vec![30, 31, 32, 33, 8, 9, 20, 21]);
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mapper = mmc3();
+        assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+
+        mapper.cpu_write(0xa000, 0x01);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        mapper.cpu_write(0xa000, 0x00);
+        assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn prg_ram_protect() {
+        let mut mapper = mmc3();
+
+        mapper.cpu_write(0x6000, 0x01);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x01));
+
+        // write protected
+        mapper.cpu_write(0xa001, 0xc0);
+        mapper.cpu_write(0x6000, 0x02);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x01));
+
+        // disabled
+        mapper.cpu_write(0xa001, 0x00);
+        assert_eq!(mapper.cpu_read(0x6000), None);
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut mapper = mmc3();
+
+        mapper.cpu_write(0xc000, 0x02);
+        mapper.cpu_write(0xc001, 0x00);
+        mapper.cpu_write(0xe001, 0x00);
+
+        // the first clock reloads the counter, the next two count it down
+        clock_scanline(&mut mapper);
+        clock_scanline(&mut mapper);
+        assert_eq!(mapper.irq(), false);
+
+        clock_scanline(&mut mapper);
+        assert_eq!(mapper.irq(), true);
+
+        // acknowledging
+        mapper.cpu_write(0xe000, 0x00);
+        assert_eq!(mapper.irq(), false);
+
+        // the counter reloads after hitting 0, but stays quiet while disabled
+        clock_scanline(&mut mapper);
+        clock_scanline(&mut mapper);
+        clock_scanline(&mut mapper);
+        assert_eq!(mapper.irq(), false);
+    }
+
+    #[test]
+    fn a12_filter() {
+        let mut mapper = mmc3();
+
+        mapper.cpu_write(0xc000, 0x00);
+        mapper.cpu_write(0xe001, 0x00);
+
+        // A12 going high again too soon doesn't count
+        mapper.ppu_read(0x1000);
+        mapper.ppu_read(0x0000);
+        mapper.cpu_clock();
+        mapper.ppu_read(0x1000);
+        assert_eq!(mapper.irq(), false);
+
+        clock_scanline(&mut mapper);
+        assert_eq!(mapper.irq(), true);
+    }
+
+    #[test]
+    fn irq_from_rendering() {
+        let mapper = rc_ref(mmc3());
+        let mut ppu = DefaultPpu::new();
+        ppu.insert_cart(mapper.clone());
+
+        // irq after 10 scanlines, with the background at $0000 and sprites at $1000
+        {
+            let mut mapper = mapper.borrow_mut();
+            mapper.cpu_write(0xc000, 10);
+            mapper.cpu_write(0xc001, 0x00);
+            mapper.cpu_write(0xe001, 0x00);
+        }
+
+        ppu.write_register(PPUCTRL, 0x08);
+        ppu.write_register(PPUMASK, 0x18);
+        ppu.start();
+
+        let mut dots = 0;
+        while !mapper.borrow().irq() {
+            ppu.clock();
+            dots += 1;
+
+            if dots % 3 == 0 {
+                mapper.borrow_mut().cpu_clock();
+            }
+        }
+
+        // the pre-render line reloads the counter, then it counts down at
+        // the sprite fetches at the end of each visible scanline
+        let scanline = dots / 341 - 1;
+        let dot = dots % 341;
+
+        assert_eq!(scanline, 9);
+        assert!(dot > 256 && dot < 320);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::cart::mappers::{
+    write_chr_latch_register, BankedMemory, ChrLatches, Mapper, MapperOptions,
+};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const PRG_RAM_SIZE: usize = 0x2000;
+
+// Like mmc2, but with 16K prg banks and prg ram,
+// see https://wiki.nesdev.com/w/index.php/MMC4
+pub struct MMC4Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: ChrLatches,
+
+    prg_bank: u8,
+    is_horizontal_mirroring: bool,
+}
+
+impl MMC4Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        MMC4Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: ChrLatches::new(options.chr(), false),
+            prg_bank: 0,
+            is_horizontal_mirroring: options.mirroring == Mirroring::Horizontal,
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        match addr {
+            0x8000...0xbfff => self.prg_bank as usize,
+            _ => self.prg_rom.num_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+}
+
+impl Mapper for MMC4Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff => Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr)),
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        if write_chr_latch_register(&mut self.chr, &mut self.is_horizontal_mirroring, addr, val) {
+            return;
+        }
+
+        match addr {
+            0x6000...0x7fff => self.prg_ram.write(0, PRG_RAM_SIZE, addr, val),
+            0xa000...0xafff => self.prg_bank = val & 0x0f,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.is_horizontal_mirroring {
+            true => Mirroring::Horizontal,
+            false => Mirroring::Vertical,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    const CHR_BANK_SIZE: usize = 0x1000;
+
+    fn mmc4() -> MMC4Mapper {
+        let prg_rom = numbered_banks(8, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(32, CHR_BANK_SIZE);
+
+        MMC4Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            prg_ram_size: PRG_RAM_SIZE,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = mmc4();
+
+        mapper.cpu_write(0xa000, 0x03);
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xc000), Some(7));
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+    }
+
+    #[test]
+    fn latches() {
+        let mut mapper = mmc4();
+
+        mapper.cpu_write(0xb000, 1);
+        mapper.cpu_write(0xc000, 2);
+        mapper.cpu_write(0xd000, 3);
+        mapper.cpu_write(0xe000, 4);
+
+        // any row of tile $fd/$fe's high plane flips the lower latch, unlike mmc2
+        assert_eq!(mapper.ppu_read(0x0fdb), 2);
+        assert_eq!(mapper.ppu_read(0x0000), 1);
+
+        assert_eq!(mapper.ppu_read(0x0fef), 1);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+
+        // the low plane doesn't
+        mapper.ppu_read(0x0fd0);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+
+        mapper.ppu_read(0x1fd8);
+        assert_eq!(mapper.ppu_read(0x1000), 3);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+    }
+}
@@ -0,0 +1,776 @@
+use crate::cart::mappers::{BankedMemory, MMC5Audio, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_SIZE: usize = 0x2000;
+const EXT_ATTRIBUTE_CHR_BANK_SIZE: usize = 0x1000;
+const EXRAM_SIZE: usize = 0x0400;
+
+const NAMETABLE_SIZE: u16 = 0x0400;
+const ATTRIBUTE_TABLE_OFFSET: usize = 0x03c0;
+
+// $5104
+const EXRAM_MODE_NAMETABLE: u8 = 0;
+const EXRAM_MODE_EXT_ATTRIBUTES: u8 = 1;
+const EXRAM_MODE_READ_ONLY: u8 = 3;
+
+// $5105, two bits per nametable (values 0 and 1 are the console's vram pages)
+const NAMETABLE_EXRAM: u8 = 2;
+const NAMETABLE_FILL: u8 = 3;
+
+const PRG_BANK_ROM: u8 = 0x80;
+
+const IRQ_ENABLE: u8 = 0x80;
+const STATUS_IRQ_PENDING: u8 = 0x80;
+const STATUS_IN_FRAME: u8 = 0x40;
+
+// ppuctrl/ppumask bits mmc5 snoops on
+const PPUCTRL_SPRITE_SIZE: u8 = 0x20;
+const PPUMASK_RENDERING: u8 = 0x18;
+
+// The ppu reads every few dots while rendering, so going this many M2 cycles
+// without a read means it's stopped (in vblank, or with rendering off)
+const PPU_IDLE_CYCLES_FILTER: u8 = 3;
+
+// Each scanline's pattern fetches are 32 tiles' worth of background,
+// then 8 sprites', then the first two tiles of the next line
+const BG_PATTERN_FETCHES: u8 = 64;
+const SPRITE_PATTERN_FETCHES: u8 = 16;
+
+// see https://wiki.nesdev.com/w/index.php/MMC5
+pub struct MMC5Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+    exram: [u8; EXRAM_SIZE],
+
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+
+    // $5113-$5117
+    prg_banks: [u8; 5],
+
+    // $5120-$5127 are for sprites and $5128-$512b for the background, but
+    // only with 8x16 sprites; otherwise the last set written is used for both
+    chr_banks: [u16; 8],
+    bg_chr_banks: [u16; 4],
+    chr_bank_upper_bits: u8,
+    is_bg_chr_last_written: bool,
+
+    is_8x16_sprites: bool,
+    is_rendering: bool,
+
+    irq_scanline: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+
+    // Scanlines are counted by watching the ppu's fetches: it reads the same
+    // nametable address three times in a row at the start of each line
+    in_frame: bool,
+    scanline: u8,
+    last_nametable_addr: Option<u16>,
+    nametable_repeats: u8,
+    pattern_fetches: u8,
+    ppu_idle_cycles: u8,
+
+    // The exram byte of the tile being fetched, in extended attribute mode
+    ext_attribute: u8,
+
+    multiplicand: u8,
+    multiplier: u8,
+
+    audio: MMC5Audio,
+}
+
+impl MMC5Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        MMC5Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            exram: [0; EXRAM_SIZE],
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect: [0; 2],
+            exram_mode: EXRAM_MODE_NAMETABLE,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0, 0, 0, 0, 0xff],
+            chr_banks: [0; 8],
+            bg_chr_banks: [0; 4],
+            chr_bank_upper_bits: 0,
+            is_bg_chr_last_written: false,
+            is_8x16_sprites: false,
+            is_rendering: false,
+            irq_scanline: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            in_frame: false,
+            scanline: 0,
+            last_nametable_addr: None,
+            nametable_repeats: 0,
+            pattern_fetches: 0,
+            ppu_idle_cycles: 0,
+            ext_attribute: 0,
+            multiplicand: 0xff,
+            multiplier: 0xff,
+            audio: MMC5Audio::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x5000...0x5015 => self.audio.write_register(addr, val),
+            0x5100 => self.prg_mode = val & 0x03,
+            0x5101 => self.chr_mode = val & 0x03,
+            0x5102 | 0x5103 => self.prg_ram_protect[(addr - 0x5102) as usize] = val & 0x03,
+            0x5104 => self.exram_mode = val & 0x03,
+            0x5105 => self.nametable_mapping = val,
+            0x5106 => self.fill_tile = val,
+            0x5107 => self.fill_attribute = val & 0x03,
+            0x5113...0x5117 => self.prg_banks[(addr - 0x5113) as usize] = val,
+            0x5120...0x5127 => {
+                self.chr_banks[(addr - 0x5120) as usize] = self.chr_bank(val);
+                self.is_bg_chr_last_written = false;
+            }
+            0x5128...0x512b => {
+                self.bg_chr_banks[(addr - 0x5128) as usize] = self.chr_bank(val);
+                self.is_bg_chr_last_written = true;
+            }
+            0x5130 => self.chr_bank_upper_bits = val & 0x03,
+            0x5203 => self.irq_scanline = val,
+            0x5204 => self.irq_enabled = val & IRQ_ENABLE != 0,
+            0x5205 => self.multiplicand = val,
+            0x5206 => self.multiplier = val,
+            _ => {}
+        }
+    }
+
+    // $5130 is latched into a chr bank register when it's written
+    fn chr_bank(&self, val: u8) -> u16 {
+        ((self.chr_bank_upper_bits as u16) << 8) | val as u16
+    }
+
+    fn read_status(&mut self) -> u8 {
+        let mut status = 0;
+
+        if self.irq_pending {
+            status |= STATUS_IRQ_PENDING;
+        }
+
+        if self.in_frame {
+            status |= STATUS_IN_FRAME;
+        }
+
+        // reading acknowledges the irq
+        self.irq_pending = false;
+
+        status
+    }
+
+    fn product(&self) -> u16 {
+        self.multiplicand as u16 * self.multiplier as u16
+    }
+
+    // Returns the 8K bank at a $8000-$ffff address, and whether it's rom
+    fn prg_bank_at(&self, addr: u16) -> (usize, bool) {
+        let window = ((addr - 0x8000) as usize) / PRG_BANK_SIZE;
+
+        // (index into prg_banks, size of the bank in 8K units)
+        let (index, num_pages) = match (self.prg_mode, addr) {
+            (0, _) => (4, 4),
+            (1, 0x8000...0xbfff) | (2, 0x8000...0xbfff) => (2, 2),
+            (1, _) => (4, 2),
+            (2, 0xc000...0xdfff) => (3, 1),
+            (2, _) => (4, 1),
+            (_, _) => (1 + window, 1),
+        };
+
+        let register = self.prg_banks[index];
+        let bank =
+            (register & 0x7f & !(num_pages - 1)) as usize | (window & (num_pages - 1) as usize);
+
+        // $5117 always selects rom, whichever windows it drives
+        let is_rom = index == 4 || register & PRG_BANK_ROM != 0;
+
+        (bank, is_rom)
+    }
+
+    fn is_prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0x02, 0x01]
+    }
+
+    fn prg_ram_bank(&self) -> usize {
+        (self.prg_banks[0] & 0x07) as usize
+    }
+
+    // Returns the chr bank at an address and its size
+    fn chr_bank_at(&self, addr: u16) -> (usize, usize) {
+        let is_bg_fetch = !self.is_sprite_fetch();
+
+        if self.exram_mode == EXRAM_MODE_EXT_ATTRIBUTES && self.is_rendering && is_bg_fetch {
+            let bank = (self.ext_attribute & 0x3f) | (self.chr_bank_upper_bits << 6);
+
+            return (bank as usize, EXT_ATTRIBUTE_CHR_BANK_SIZE);
+        }
+
+        let uses_bg_banks = match self.in_frame && self.is_8x16_sprites {
+            true => is_bg_fetch,
+            false => self.is_bg_chr_last_written,
+        };
+
+        let bank_size = CHR_SIZE >> self.chr_mode;
+
+        // the background banks only cover 4K, and repeat for the upper half
+        let bank = match (uses_bg_banks, self.chr_mode) {
+            (false, _) => {
+                let banks_per_register = 8 >> self.chr_mode;
+                let slot = addr as usize / bank_size;
+
+                self.chr_banks[(slot + 1) * banks_per_register - 1]
+            }
+            (true, 0) | (true, 1) => self.bg_chr_banks[3],
+            (true, 2) => self.bg_chr_banks[((addr >> 10) & 0x02 | 0x01) as usize],
+            (true, _) => self.bg_chr_banks[((addr >> 10) & 0x03) as usize],
+        };
+
+        (bank as usize, bank_size)
+    }
+
+    fn is_sprite_fetch(&self) -> bool {
+        let sprite_fetches = BG_PATTERN_FETCHES..BG_PATTERN_FETCHES + SPRITE_PATTERN_FETCHES;
+
+        self.in_frame && sprite_fetches.contains(&self.pattern_fetches)
+    }
+
+    fn nametable_source(&self, addr: u16) -> u8 {
+        let table = (addr - 0x2000) / NAMETABLE_SIZE;
+
+        (self.nametable_mapping >> (table * 2)) & 0x03
+    }
+
+    fn watch_ppu_read(&mut self, addr: u16) {
+        self.ppu_idle_cycles = 0;
+
+        let is_repeat = self.last_nametable_addr == Some(addr);
+
+        match is_repeat {
+            true => self.nametable_repeats += 1,
+            false => self.nametable_repeats = 0,
+        }
+
+        self.last_nametable_addr = match addr {
+            0x2000...0x2fff => Some(addr),
+            _ => None,
+        };
+
+        if self.nametable_repeats == 2 {
+            self.detect_scanline();
+        }
+    }
+
+    fn detect_scanline(&mut self) {
+        match self.in_frame {
+            true => {
+                self.scanline = self.scanline.wrapping_add(1);
+
+                if self.scanline == self.irq_scanline {
+                    self.irq_pending = true;
+                }
+            }
+            false => {
+                self.in_frame = true;
+                self.scanline = 0;
+            }
+        }
+
+        self.pattern_fetches = 0;
+    }
+}
+
+impl Mapper for MMC5Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x5010 => Some(self.audio.read_pcm_status()),
+            0x5015 => Some(self.audio.read_status()),
+            0x5204 => Some(self.read_status()),
+            0x5205 => Some(self.product() as u8),
+            0x5206 => Some((self.product() >> 8) as u8),
+            // exram is only readable in modes 2 and 3
+            0x5c00...0x5fff if self.exram_mode > EXRAM_MODE_EXT_ATTRIBUTES => {
+                Some(self.exram[addr as usize % EXRAM_SIZE])
+            }
+            0x6000...0x7fff => Some(self.prg_ram.read(self.prg_ram_bank(), PRG_BANK_SIZE, addr)),
+            0x8000...0xffff => {
+                let (bank, is_rom) = self.prg_bank_at(addr);
+
+                let val = match is_rom {
+                    true => self.prg_rom.read(bank, PRG_BANK_SIZE, addr),
+                    false => self.prg_ram.read(bank & 0x07, PRG_BANK_SIZE, addr),
+                };
+
+                self.audio.capture_prg_read(addr, val);
+
+                Some(val)
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x5000...0x5bff => self.write_register(addr, val),
+            0x5c00...0x5fff => {
+                // in the nametable modes, the cpu can only write while the ppu
+                // is rendering, and writes 0 otherwise
+                let val = match (self.exram_mode, self.in_frame) {
+                    (EXRAM_MODE_READ_ONLY, _) => return,
+                    (EXRAM_MODE_NAMETABLE, false) | (EXRAM_MODE_EXT_ATTRIBUTES, false) => 0,
+                    _ => val,
+                };
+
+                self.exram[addr as usize % EXRAM_SIZE] = val;
+            }
+            0x6000...0x7fff => {
+                if self.is_prg_ram_writable() {
+                    let bank = self.prg_ram_bank();
+                    self.prg_ram.write(bank, PRG_BANK_SIZE, addr, val);
+                }
+            }
+            0x8000...0xdfff => {
+                let (bank, is_rom) = self.prg_bank_at(addr);
+
+                if !is_rom && self.is_prg_ram_writable() {
+                    self.prg_ram.write(bank & 0x07, PRG_BANK_SIZE, addr, val);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.watch_ppu_read(addr);
+
+        let (bank, bank_size) = self.chr_bank_at(addr);
+        let val = self.chr.read(bank, bank_size, addr);
+
+        self.pattern_fetches = self.pattern_fetches.saturating_add(1);
+
+        val
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let (bank, bank_size) = self.chr_bank_at(addr);
+
+        self.chr.write(bank, bank_size, addr, val);
+    }
+
+    // Nametables filled from exram or fill mode are handled by nametable_read;
+    // for the rest, bit 0 of their mapping picks the vram page
+    fn mirroring(&self) -> Mirroring {
+        let mut pages = [0; 4];
+
+        for (table, page) in pages.iter_mut().enumerate() {
+            *page = (self.nametable_mapping >> (table * 2)) & 0x01;
+        }
+
+        Mirroring::Mapped(pages)
+    }
+
+    fn nametable_read(&mut self, addr: u16) -> Option<u8> {
+        self.watch_ppu_read(addr);
+
+        let offset = (addr % NAMETABLE_SIZE) as usize;
+        let is_attribute = offset >= ATTRIBUTE_TABLE_OFFSET;
+
+        // each tile gets its own chr bank and palette from exram
+        if self.exram_mode == EXRAM_MODE_EXT_ATTRIBUTES && self.is_rendering {
+            match is_attribute {
+                true => return Some((self.ext_attribute >> 6) * 0x55),
+                false => self.ext_attribute = self.exram[offset],
+            }
+        }
+
+        match (self.nametable_source(addr), is_attribute) {
+            (NAMETABLE_EXRAM, _) => match self.exram_mode {
+                EXRAM_MODE_NAMETABLE | EXRAM_MODE_EXT_ATTRIBUTES => Some(self.exram[offset]),
+                _ => Some(0),
+            },
+            (NAMETABLE_FILL, false) => Some(self.fill_tile),
+            (NAMETABLE_FILL, true) => Some(self.fill_attribute * 0x55),
+            _ => None,
+        }
+    }
+
+    fn nametable_write(&mut self, addr: u16, val: u8) -> bool {
+        match self.nametable_source(addr) {
+            NAMETABLE_EXRAM => {
+                if self.exram_mode <= EXRAM_MODE_EXT_ATTRIBUTES {
+                    self.exram[(addr % NAMETABLE_SIZE) as usize] = val;
+                }
+
+                true
+            }
+            NAMETABLE_FILL => true,
+            _ => false,
+        }
+    }
+
+    fn ppu_register_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x2000 => self.is_8x16_sprites = val & PPUCTRL_SPRITE_SIZE != 0,
+            0x2001 => self.is_rendering = val & PPUMASK_RENDERING != 0,
+            _ => {}
+        }
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_pending && self.irq_enabled) || self.audio.irq()
+    }
+
+    fn cpu_clock(&mut self) {
+        self.ppu_idle_cycles = self.ppu_idle_cycles.saturating_add(1);
+
+        if self.ppu_idle_cycles >= PPU_IDLE_CYCLES_FILTER {
+            self.in_frame = false;
+            self.last_nametable_addr = None;
+        }
+
+        self.audio.clock();
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+    use crate::ppu::{DefaultPpu, Ppu, PPUMASK};
+    use crate::util::rc_ref;
+
+    const CHR_BANK_SIZE: usize = 0x0400;
+
+    fn mmc5() -> MMC5Mapper {
+        let prg_rom = numbered_banks(16, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(256, CHR_BANK_SIZE);
+
+        MMC5Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            prg_ram_size: PRG_BANK_SIZE * 2,
+            ..Default::default()
+        })
+    }
+
+    fn prg_banks(mapper: &mut MMC5Mapper) -> Vec<u8> {
+        [0x8000, 0xa000, 0xc000, 0xe000]
+            .iter()
+            .map(|addr| mapper.cpu_read(*addr).unwrap())
+            .collect()
+    }
+
+    fn chr_banks(mapper: &mut MMC5Mapper) -> Vec<u8> {
+        (0..8).map(|i| mapper.ppu_read(i * 0x400)).collect()
+    }
+
+    // The three reads of the same nametable address the ppu makes at the start of a line
+    fn start_scanline(mapper: &mut MMC5Mapper) {
+        for _ in 0..3 {
+            mapper.nametable_read(0x2000);
+        }
+    }
+
+    #[test]
+    fn prg_modes() {
+        let mut mapper = mmc5();
+
+        // mode 3 at power on, with the last bank at $e000
+        assert_eq!(prg_banks(&mut mapper), vec![0, 0, 0, 15]);
+
+        for (register, bank) in [0x81u8, 0x82, 0x83, 0x84].iter().enumerate() {
+            mapper.cpu_write(0x5114 + register as u16, *bank);
+        }
+        assert_eq!(prg_banks(&mut mapper), vec![1, 2, 3, 4]);
+
+        // the bigger banks ignore their low bits
+        mapper.cpu_write(0x5100, 0x02);
+        assert_eq!(prg_banks(&mut mapper), vec![2, 3, 3, 4]);
+
+        mapper.cpu_write(0x5100, 0x01);
+        assert_eq!(prg_banks(&mut mapper), vec![2, 3, 4, 5]);
+
+        mapper.cpu_write(0x5100, 0x00);
+        assert_eq!(prg_banks(&mut mapper), vec![4, 5, 6, 7]);
+
+        // $5117 ignores its rom bit
+        mapper.cpu_write(0x5117, 0x04);
+        assert_eq!(prg_banks(&mut mapper), vec![4, 5, 6, 7]);
+
+        mapper.cpu_write(0x5100, 0x01);
+        assert_eq!(prg_banks(&mut mapper), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn prg_ram() {
+        let mut mapper = mmc5();
+
+        // writes are ignored until both protect registers are set up
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x00));
+
+        mapper.cpu_write(0x5102, 0x02);
+        mapper.cpu_write(0x5103, 0x01);
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+
+        mapper.cpu_write(0x5113, 0x01);
+        mapper.cpu_write(0x6000, 0x34);
+
+        // ram can be banked into $8000-$dfff too
+        mapper.cpu_write(0x5114, 0x00);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0x12));
+
+        mapper.cpu_write(0x5114, 0x01);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0x34));
+
+        mapper.cpu_write(0x8000, 0x56);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x56));
+    }
+
+    #[test]
+    fn chr_modes() {
+        let mut mapper = mmc5();
+
+        for register in 0..8 {
+            mapper.cpu_write(0x5120 + register, 0x10 + register as u8);
+        }
+
+        mapper.cpu_write(0x5101, 0x03);
+        assert_eq!(chr_banks(&mut mapper), vec![16, 17, 18, 19, 20, 21, 22, 23]);
+
+        mapper.cpu_write(0x5101, 0x02);
+        assert_eq!(chr_banks(&mut mapper), vec![34, 35, 38, 39, 42, 43, 46, 47]);
+
+        mapper.cpu_write(0x5101, 0x01);
+        assert_eq!(chr_banks(&mut mapper), vec![76, 77, 78, 79, 92, 93, 94, 95]);
+
+        mapper.cpu_write(0x5101, 0x00);
+        assert_eq!(
+            chr_banks(&mut mapper),
+            vec![184, 185, 186, 187, 188, 189, 190, 191]
+        );
+    }
+
+    #[test]
+    fn chr_bank_upper_bits() {
+        // 1M of chr, with each 1K bank filled with its upper bits
+        let chr_rom: Vec<u8> = (0..0x400)
+            .flat_map(|bank| vec![(bank >> 8) as u8; CHR_BANK_SIZE])
+            .collect();
+
+        let mut mapper = MMC5Mapper::new(&MapperOptions {
+            prg_rom: &[0; PRG_BANK_SIZE],
+            chr_rom: &chr_rom,
+            ..Default::default()
+        });
+
+        mapper.cpu_write(0x5101, 0x03);
+        mapper.cpu_write(0x5130, 0x02);
+        mapper.cpu_write(0x5120, 0x00);
+
+        // the upper bits are latched by the write, not the read
+        mapper.cpu_write(0x5130, 0x01);
+        assert_eq!(mapper.ppu_read(0x0000), 2);
+    }
+
+    #[test]
+    fn chr_sets() {
+        let mut mapper = mmc5();
+
+        mapper.cpu_write(0x5101, 0x03);
+
+        for register in 0..8 {
+            mapper.cpu_write(0x5120 + register, 0x10 + register as u8);
+        }
+
+        for register in 0..4 {
+            mapper.cpu_write(0x5128 + register, 0x20 + register as u8);
+        }
+
+        // with 8x8 sprites, the last set written is used for everything,
+        // with the background set repeating for both halves
+        assert_eq!(chr_banks(&mut mapper), vec![32, 33, 34, 35, 32, 33, 34, 35]);
+
+        mapper.cpu_write(0x5127, 0x17);
+        assert_eq!(mapper.ppu_read(0x0000), 16);
+
+        // with 8x16 sprites, the sprite fetches in the middle of each line use the sprite set
+        mapper.ppu_register_write(0x2000, PPUCTRL_SPRITE_SIZE);
+        start_scanline(&mut mapper);
+
+        for _ in 0..BG_PATTERN_FETCHES {
+            assert_eq!(mapper.ppu_read(0x1000), 32);
+        }
+
+        for _ in 0..SPRITE_PATTERN_FETCHES {
+            assert_eq!(mapper.ppu_read(0x1000), 20);
+        }
+
+        assert_eq!(mapper.ppu_read(0x1000), 32);
+    }
+
+    #[test]
+    fn nametables() {
+        let mut mapper = mmc5();
+
+        // vram page 0, vram page 1, exram, fill
+        mapper.cpu_write(0x5105, 0b11_10_01_00);
+        assert_eq!(mapper.mirroring(), Mirroring::Mapped([0, 1, 0, 1]));
+
+        assert_eq!(mapper.nametable_read(0x2000), None);
+        assert_eq!(mapper.nametable_read(0x2400), None);
+
+        assert_eq!(mapper.nametable_write(0x2812, 0x34), true);
+        assert_eq!(mapper.nametable_read(0x2812), Some(0x34));
+
+        mapper.cpu_write(0x5106, 0x56);
+        mapper.cpu_write(0x5107, 0x02);
+        assert_eq!(mapper.nametable_read(0x2c12), Some(0x56));
+        assert_eq!(mapper.nametable_read(0x2fc0), Some(0xaa));
+
+        // exram reads back 0 as a nametable once the cpu's using it as ram
+        mapper.cpu_write(0x5104, 0x02);
+        assert_eq!(mapper.nametable_read(0x2812), Some(0x00));
+        assert_eq!(mapper.cpu_read(0x5c12), Some(0x34));
+    }
+
+    #[test]
+    fn exram_cpu_access() {
+        let mut mapper = mmc5();
+
+        // outside of rendering, the nametable modes write 0 and aren't readable
+        mapper.cpu_write(0x5c00, 0x12);
+        assert_eq!(mapper.cpu_read(0x5c00), None);
+
+        start_scanline(&mut mapper);
+        mapper.cpu_write(0x5c01, 0x34);
+
+        mapper.cpu_write(0x5104, 0x02);
+        assert_eq!(mapper.cpu_read(0x5c00), Some(0x00));
+        assert_eq!(mapper.cpu_read(0x5c01), Some(0x34));
+
+        mapper.cpu_write(0x5c00, 0x56);
+        assert_eq!(mapper.cpu_read(0x5c00), Some(0x56));
+
+        mapper.cpu_write(0x5104, 0x03);
+        mapper.cpu_write(0x5c00, 0x78);
+        assert_eq!(mapper.cpu_read(0x5c00), Some(0x56));
+    }
+
+    #[test]
+    fn ext_attributes() {
+        let mut mapper = mmc5();
+
+        mapper.cpu_write(0x5104, 0x02);
+        mapper.cpu_write(0x5c21, 0xc5);
+        mapper.cpu_write(0x5104, 0x01);
+        mapper.ppu_register_write(0x2001, PPUMASK_RENDERING);
+
+        // the tile's exram byte picks the palette and a 4K chr bank for its pattern
+        mapper.nametable_read(0x2021);
+        assert_eq!(mapper.nametable_read(0x23c0), Some(0xff));
+        assert_eq!(mapper.ppu_read(0x0010), 20);
+        assert_eq!(mapper.ppu_read(0x1c18), 23);
+    }
+
+    #[test]
+    fn multiplier() {
+        let mut mapper = mmc5();
+
+        mapper.cpu_write(0x5205, 0xc8);
+        mapper.cpu_write(0x5206, 0x13);
+
+        assert_eq!(mapper.cpu_read(0x5205), Some(0xd8));
+        assert_eq!(mapper.cpu_read(0x5206), Some(0x0e));
+    }
+
+    #[test]
+    fn pcm_read_mode() {
+        let mut mapper = mmc5();
+
+        // rom banks 1 and 0 at $8000 and $a000
+        mapper.cpu_write(0x5114, 0x81);
+        mapper.cpu_write(0x5115, 0x80);
+        mapper.cpu_write(0x5010, 0x81);
+
+        mapper.cpu_read(0x8000);
+        assert!(mapper.audio_output() > 0.0);
+        assert_eq!(mapper.irq(), false);
+
+        // reading a zero raises the irq, and reading $5010 acknowledges it
+        mapper.cpu_read(0xa000);
+        assert_eq!(mapper.irq(), true);
+
+        assert_eq!(mapper.cpu_read(0x5010), Some(0x81));
+        assert_eq!(mapper.irq(), false);
+    }
+
+    #[test]
+    fn irq_from_rendering() {
+        let mapper = rc_ref(mmc5());
+        let mut ppu = DefaultPpu::new();
+        ppu.insert_cart(mapper.clone());
+
+        {
+            let mut mapper = mapper.borrow_mut();
+            mapper.cpu_write(0x5203, 10);
+            mapper.cpu_write(0x5204, IRQ_ENABLE);
+        }
+
+        ppu.write_register(PPUMASK, 0x18);
+        ppu.start();
+
+        let mut dots = 0;
+        while !mapper.borrow().irq() {
+            ppu.clock();
+            dots += 1;
+
+            if dots % 3 == 0 {
+                mapper.borrow_mut().cpu_clock();
+            }
+        }
+
+        // the irq fires as the ppu starts fetching scanline 10
+        let scanline = dots / 341 - 1;
+        let dot = dots % 341;
+
+        assert_eq!(scanline, 10);
+        assert!(dot < 8);
+
+        // the status read acknowledges it, and says the ppu's rendering
+        assert_eq!(mapper.borrow_mut().cpu_read(0x5204), Some(0xc0));
+        assert_eq!(mapper.borrow().irq(), false);
+
+        // which stops in vblank
+        while dots / 341 - 1 < 241 {
+            ppu.clock();
+            dots += 1;
+
+            if dots % 3 == 0 {
+                mapper.borrow_mut().cpu_clock();
+            }
+        }
+
+        assert_eq!(mapper.borrow_mut().cpu_read(0x5204), Some(0x00));
+    }
+}
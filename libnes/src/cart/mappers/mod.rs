@@ -1,26 +1,173 @@
-use std::rc::Rc;
 use std::cell::RefCell;
+use std::rc::Rc;
 
-use crate::nes::Nes;
+use crate::ppu::mem::Mirroring;
+use crate::util::rc_ref;
 
+mod audio;
+mod axrom;
+mod bnrom;
+mod camerica;
+mod cnrom;
+mod color_dreams;
+mod fme7;
+mod gxrom;
+mod memory;
+mod mmc1;
+mod mmc2;
+mod mmc3;
+mod mmc4;
+mod mmc5;
+mod n163;
 mod nrom;
+mod uxrom;
+mod vrc4;
+mod vrc6;
+mod vrc7;
+mod vrc_irq;
 
+pub use audio::*;
+pub use axrom::*;
+pub use bnrom::*;
+pub use camerica::*;
+pub use cnrom::*;
+pub use color_dreams::*;
+pub use fme7::*;
+pub use gxrom::*;
+pub use memory::*;
+pub use mmc1::*;
+pub use mmc2::*;
+pub use mmc3::*;
+pub use mmc4::*;
+pub use mmc5::*;
+pub use n163::*;
 pub use nrom::*;
+pub use uxrom::*;
+pub use vrc4::*;
+pub use vrc6::*;
+pub use vrc7::*;
+pub use vrc_irq::*;
+
+pub const PRG_RAM_UNIT_SIZE: usize = 8192;
+pub const CHR_RAM_SIZE: usize = 8192;
+
+// $7000, from the start of prg ram at $6000
+const TRAINER_OFFSET: usize = 0x1000;
 
+// A cartridge board: owns the cart's rom and ram, and decides what the cpu
+// and ppu see when they access the cart's address space
 pub trait Mapper {
-    fn map(&self, nes: Rc<RefCell<Nes>>, options: MapperOptions) -> Result<(), String>;
+    // $4020-$ffff; returns None for addresses the cart doesn't drive
+    fn cpu_read(&mut self, addr: u16) -> Option<u8>;
+    fn cpu_write(&mut self, addr: u16, val: u8);
+
+    // $0000-$1fff (the pattern tables)
+    fn ppu_read(&mut self, addr: u16) -> u8;
+    fn ppu_write(&mut self, addr: u16, val: u8);
+
+    fn mirroring(&self) -> Mirroring;
+
+    // $2000-$2fff; boards that don't drive the nametables themselves return None
+    // (or false for writes) and leave them to the console's vram
+    fn nametable_read(&mut self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    fn nametable_write(&mut self, _addr: u16, _val: u8) -> bool {
+        false
+    }
+
+    // Writes to the ppu's registers, for boards that snoop on them
+    fn ppu_register_write(&mut self, _addr: u16, _val: u8) {}
+
+    // State of the cart's /IRQ output
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // Called once per cpu cycle (i.e. on each M2 tick), for boards that keep time
+    fn cpu_clock(&mut self) {}
+
+    // The level of the cart's expansion audio, which the apu mixes in with its
+    // own channels; sampled once per cpu cycle, after cpu_clock
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
 }
 
+#[derive(Default)]
 pub struct MapperOptions<'a> {
     pub cart_data: &'a [u8],
     pub prg_rom: &'a [u8],
     pub chr_rom: &'a [u8],
+    pub prg_ram_size: usize,
+    pub mirroring: Mirroring,
+
+    // nes 2.0 carts use this to pick between boards sharing a mapper number
+    pub submapper: u8,
+
+    // Whether the board leaves its rom enabled during writes (see bus_conflict)
+    pub bus_conflicts: bool,
+
+    // 512 bytes the cart's prg ram holds at $7000 on power on
+    pub trainer: &'a [u8],
+}
+
+impl<'a> MapperOptions<'a> {
+    pub fn prg_ram(&self) -> BankedMemory {
+        let mut prg_ram = BankedMemory::ram(self.prg_ram_size);
+        prg_ram.load(TRAINER_OFFSET, self.trainer);
+
+        prg_ram
+    }
+
+    // Carts without chr rom have chr ram instead
+    pub fn chr(&self) -> BankedMemory {
+        match self.chr_rom.len() {
+            0 => BankedMemory::ram(CHR_RAM_SIZE),
+            _ => BankedMemory::rom(self.chr_rom),
+        }
+    }
+}
+
+// Boards with bus conflicts don't disable their rom when the cpu writes to it,
+// so the value that reaches the mapper is whatever both chips drove onto the bus
+pub fn bus_conflict(bus_conflicts: bool, rom_val: u8, val: u8) -> u8 {
+    match bus_conflicts {
+        true => rom_val & val,
+        false => val,
+    }
+}
+
+// Fills each bank with its bank number, so tests can tell which bank is mapped in
+#[cfg(test)]
+pub fn numbered_banks(num_banks: usize, bank_size: usize) -> Vec<u8> {
+    (0..num_banks)
+        .flat_map(|bank| vec![bank as u8; bank_size])
+        .collect()
 }
 
-pub fn get_mapper(id: u8) -> Result<Box<impl Mapper>, String> 
-{
+pub fn get_mapper(id: u8, options: &MapperOptions) -> Result<Rc<RefCell<Mapper>>, String> {
     match id {
-        0 => Ok(Box::from(NROMMapper::new())),
+        0 => Ok(rc_ref(NROMMapper::new(options))),
+        1 => Ok(rc_ref(MMC1Mapper::new(options))),
+        2 => Ok(rc_ref(UxROMMapper::new(options))),
+        3 => Ok(rc_ref(CNROMMapper::new(options))),
+        4 => Ok(rc_ref(MMC3Mapper::new(options))),
+        5 => Ok(rc_ref(MMC5Mapper::new(options))),
+        7 => Ok(rc_ref(AxROMMapper::new(options))),
+        9 => Ok(rc_ref(MMC2Mapper::new(options))),
+        10 => Ok(rc_ref(MMC4Mapper::new(options))),
+        11 => Ok(rc_ref(ColorDreamsMapper::new(options))),
+        19 => Ok(rc_ref(N163Mapper::new(options))),
+        21 | 22 | 23 | 25 => Ok(rc_ref(VRC4Mapper::new(id, options))),
+        24 | 26 => Ok(rc_ref(VRC6Mapper::new(id, options))),
+        34 if is_nina_001(options) => Ok(rc_ref(NINA001Mapper::new(options))),
+        34 => Ok(rc_ref(BNROMMapper::new(options))),
+        66 => Ok(rc_ref(GxROMMapper::new(options))),
+        69 => Ok(rc_ref(FME7Mapper::new(options))),
+        71 => Ok(rc_ref(CamericaMapper::new(options))),
+        85 => Ok(rc_ref(VRC7Mapper::new(options))),
         _ => Err(format!("Unsupported Mapper '{}'", id)),
     }
 }
@@ -0,0 +1,345 @@
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions, N163Audio};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+const CHIP_RAM_SIZE: usize = 0x80;
+
+const NAMETABLE_SIZE: u16 = 0x0400;
+
+// Bank values from here up select the console's vram instead of chr rom
+const VRAM_BANKS_START: u8 = 0xe0;
+
+const PRG_BANK_MASK: u8 = 0x3f;
+
+// $e000's bit for turning the expansion audio off
+const SOUND_DISABLE: u8 = 0x40;
+
+const WRITE_PROTECT_KEY_MASK: u8 = 0xf0;
+const WRITE_PROTECT_KEY: u8 = 0x40;
+
+const CHIP_RAM_AUTO_INCREMENT: u8 = 0x80;
+
+const IRQ_ENABLE: u16 = 0x8000;
+const IRQ_COUNTER_MAX: u16 = 0x7fff;
+
+// Pattern table banks that point at vram aren't supported, only nametable ones,
+// see https://wiki.nesdev.com/w/index.php/Namco_163
+pub struct N163Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+
+    // 128 bytes inside the chip, shared with its expansion audio
+    chip_ram: [u8; CHIP_RAM_SIZE],
+    chip_ram_addr: u8,
+
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    nametable_banks: [u8; 4],
+    write_protect: u8,
+
+    // bit 15 enables counting, and the low 15 bits are the counter
+    irq_counter: u16,
+    irq_pending: bool,
+
+    audio: N163Audio,
+}
+
+impl N163Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        N163Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            chip_ram: [0; CHIP_RAM_SIZE],
+            chip_ram_addr: 0,
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            nametable_banks: [VRAM_BANKS_START; 4],
+            write_protect: 0,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: N163Audio::new(),
+        }
+    }
+
+    fn read_chip_ram(&mut self) -> u8 {
+        let val = self.chip_ram[(self.chip_ram_addr & 0x7f) as usize];
+        self.increment_chip_ram_addr();
+
+        val
+    }
+
+    fn write_chip_ram(&mut self, val: u8) {
+        self.chip_ram[(self.chip_ram_addr & 0x7f) as usize] = val;
+        self.increment_chip_ram_addr();
+    }
+
+    fn increment_chip_ram_addr(&mut self) {
+        if self.chip_ram_addr & CHIP_RAM_AUTO_INCREMENT != 0 {
+            let addr = self.chip_ram_addr & 0x7f;
+            self.chip_ram_addr = CHIP_RAM_AUTO_INCREMENT | ((addr + 1) & 0x7f);
+        }
+    }
+
+    fn is_prg_ram_writable(&self, addr: u16) -> bool {
+        let chunk = (addr - 0x6000) / 0x0800;
+
+        self.write_protect & WRITE_PROTECT_KEY_MASK == WRITE_PROTECT_KEY
+            && self.write_protect & (1 << chunk) == 0
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        match addr {
+            0x8000...0xdfff => {
+                let register = self.prg_banks[((addr - 0x8000) as usize) / PRG_BANK_SIZE];
+
+                (register & PRG_BANK_MASK) as usize
+            }
+            _ => self.prg_rom.num_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        self.chr_banks[(addr as usize) / CHR_BANK_SIZE] as usize
+    }
+
+    fn is_sound_enabled(&self) -> bool {
+        self.prg_banks[0] & SOUND_DISABLE == 0
+    }
+
+    fn nametable_bank(&self, addr: u16) -> u8 {
+        self.nametable_banks[((addr - 0x2000) / NAMETABLE_SIZE) as usize]
+    }
+
+    // The counter counts up every cpu cycle, and stops once it fires
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter & IRQ_ENABLE == 0
+            || self.irq_counter & IRQ_COUNTER_MAX == IRQ_COUNTER_MAX
+        {
+            return;
+        }
+
+        self.irq_counter += 1;
+
+        if self.irq_counter & IRQ_COUNTER_MAX == IRQ_COUNTER_MAX {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for N163Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x4800...0x4fff => Some(self.read_chip_ram()),
+            0x5000...0x57ff => Some(self.irq_counter as u8),
+            0x5800...0x5fff => Some((self.irq_counter >> 8) as u8),
+            0x6000...0x7fff => Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr)),
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x4800...0x4fff => self.write_chip_ram(val),
+            0x5000...0x57ff => {
+                self.irq_counter = (self.irq_counter & 0xff00) | val as u16;
+                self.irq_pending = false;
+            }
+            0x5800...0x5fff => {
+                self.irq_counter = (self.irq_counter & 0x00ff) | ((val as u16) << 8);
+                self.irq_pending = false;
+            }
+            0x6000...0x7fff => {
+                if self.is_prg_ram_writable(addr) {
+                    self.prg_ram.write(0, PRG_RAM_SIZE, addr, val);
+                }
+            }
+            0x8000...0xbfff => self.chr_banks[((addr - 0x8000) >> 11) as usize] = val,
+            0xc000...0xdfff => self.nametable_banks[((addr - 0xc000) >> 11) as usize] = val,
+            0xe000...0xf7ff => self.prg_banks[((addr - 0xe000) >> 11) as usize] = val,
+            0xf800...0xffff => {
+                self.write_protect = val;
+                self.chip_ram_addr = val;
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank_at(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let bank = self.chr_bank_at(addr);
+
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    // Nametables banked to chr rom are handled by nametable_read
+    fn mirroring(&self) -> Mirroring {
+        let mut pages = [0; 4];
+
+        for (page, bank) in pages.iter_mut().zip(self.nametable_banks.iter()) {
+            *page = bank & 0x01;
+        }
+
+        Mirroring::Mapped(pages)
+    }
+
+    fn nametable_read(&mut self, addr: u16) -> Option<u8> {
+        match self.nametable_bank(addr) {
+            bank if bank < VRAM_BANKS_START => {
+                Some(self.chr.read(bank as usize, CHR_BANK_SIZE, addr))
+            }
+            _ => None,
+        }
+    }
+
+    fn nametable_write(&mut self, addr: u16, _val: u8) -> bool {
+        // chr rom nametables can't be written
+        self.nametable_bank(addr) < VRAM_BANKS_START
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.is_sound_enabled() {
+            self.audio.clock(&mut self.chip_ram);
+        }
+
+        self.clock_irq_counter();
+    }
+
+    fn audio_output(&self) -> f32 {
+        match self.is_sound_enabled() {
+            true => self.audio.output(&self.chip_ram),
+            false => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn n163() -> N163Mapper {
+        let prg_rom = numbered_banks(32, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(256, CHR_BANK_SIZE);
+
+        N163Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            prg_ram_size: PRG_RAM_SIZE,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = n163();
+
+        mapper.cpu_write(0xe000, 0x43);
+        mapper.cpu_write(0xe800, 0xc4);
+        mapper.cpu_write(0xf000, 0x05);
+
+        // the upper bits are for audio and chr, not the bank
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xa000), Some(4));
+        assert_eq!(mapper.cpu_read(0xc000), Some(5));
+        assert_eq!(mapper.cpu_read(0xe000), Some(31));
+    }
+
+    #[test]
+    fn prg_ram_write_protect() {
+        let mut mapper = n163();
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x00));
+
+        // $6800-$6fff stays protected
+        mapper.cpu_write(0xf800, 0x42);
+        mapper.cpu_write(0x6000, 0x12);
+        mapper.cpu_write(0x6800, 0x34);
+
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+        assert_eq!(mapper.cpu_read(0x6800), Some(0x00));
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut mapper = n163();
+
+        for i in 0..8 {
+            mapper.cpu_write(0x8000 + i * 0x800, 0x50 + i as u8);
+        }
+
+        let banks: Vec<u8> = (0..8).map(|i| mapper.ppu_read(i * 0x400)).collect();
+        assert_eq!(banks, vec![0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57]);
+    }
+
+    #[test]
+    fn nametables() {
+        let mut mapper = n163();
+
+        mapper.cpu_write(0xc000, 0xe0);
+        mapper.cpu_write(0xc800, 0xe1);
+        mapper.cpu_write(0xd000, 0xe1);
+        mapper.cpu_write(0xd800, 0x23);
+
+        assert_eq!(mapper.mirroring(), Mirroring::Mapped([0, 1, 1, 1]));
+
+        assert_eq!(mapper.nametable_read(0x2400), None);
+        assert_eq!(mapper.nametable_read(0x2c00), Some(0x23));
+
+        assert_eq!(mapper.nametable_write(0x2400, 0x00), false);
+        assert_eq!(mapper.nametable_write(0x2c00, 0x00), true);
+    }
+
+    #[test]
+    fn chip_ram() {
+        let mut mapper = n163();
+
+        // auto-incrementing wraps within the 128 bytes
+        mapper.cpu_write(0xf800, 0xfe);
+        mapper.cpu_write(0x4800, 0x12);
+        mapper.cpu_write(0x4800, 0x34);
+        mapper.cpu_write(0x4800, 0x56);
+
+        mapper.cpu_write(0xf800, 0x7e);
+        assert_eq!(mapper.cpu_read(0x4800), Some(0x12));
+        assert_eq!(mapper.cpu_read(0x4800), Some(0x12));
+
+        mapper.cpu_write(0xf800, 0x00);
+        assert_eq!(mapper.cpu_read(0x4800), Some(0x56));
+    }
+
+    #[test]
+    fn irq_counter() {
+        let mut mapper = n163();
+
+        mapper.cpu_write(0x5000, 0xfe);
+        mapper.cpu_write(0x5800, 0xff);
+
+        mapper.cpu_clock();
+        assert_eq!(mapper.irq(), true);
+
+        // it stops at $7fff
+        mapper.cpu_clock();
+        assert_eq!(mapper.cpu_read(0x5000), Some(0xff));
+        assert_eq!(mapper.cpu_read(0x5800), Some(0xff));
+
+        mapper.cpu_write(0x5800, 0x80);
+        assert_eq!(mapper.irq(), false);
+    }
+}
@@ -1,36 +1,121 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
 
-use crate::cart::mappers::{Mapper, MapperOptions};
-use crate::nes::Nes;
+const PRG_ROM_SIZE: usize = 0x8000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_SIZE: usize = 0x2000;
 
-pub struct NROMMapper {}
+// see https://wiki.nesdev.com/w/index.php/NROM
+pub struct NROMMapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+    mirroring: Mirroring,
+}
 
 impl NROMMapper {
-    pub fn new() -> Self {
-        NROMMapper {}
+    pub fn new(options: &MapperOptions) -> Self {
+        NROMMapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            mirroring: options.mirroring,
+        }
     }
 }
 
 impl Mapper for NROMMapper {
-    fn map(&self, nes: Rc<RefCell<Nes>>, options: MapperOptions) -> Result<(), String> {
-        let cpu = nes.borrow_mut().get_cpu();
-
-        cpu.borrow_mut()
-            .write_bytes_to(&0x8000u16.into(), &options.prg_rom);
-
-        // TODO: actually impl mirroring
-        // For now, just check if loading the prg rom would cause an overflow
-        // to determine if we load lower prg rom or "fake mirror":
-        if options.prg_rom.len() + 0xC000 <= 0x10000 {
-            cpu.borrow_mut()
-                .write_bytes_to(&0xC000u16.into(), &options.prg_rom);
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff => Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr)),
+            // 16K carts show up twice
+            0x8000...0xffff => Some(self.prg_rom.read(0, PRG_ROM_SIZE, addr)),
+            _ => None,
         }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x6000...0x7fff => self.prg_ram.write(0, PRG_RAM_SIZE, addr, val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(0, CHR_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(0, CHR_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn nrom(prg_rom: &[u8], chr_rom: &[u8]) -> NROMMapper {
+        NROMMapper::new(&MapperOptions {
+            prg_rom,
+            chr_rom,
+            prg_ram_size: PRG_RAM_SIZE,
+            mirroring: Mirroring::Vertical,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prg_rom_mirrors() {
+        let mut prg_rom = vec![0; 0x4000];
+        prg_rom[0x0000] = 0x01;
+        prg_rom[0x3fff] = 0x02;
+
+        let mut mapper = nrom(&prg_rom, &[0; CHR_SIZE]);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(0x01));
+        assert_eq!(mapper.cpu_read(0xbfff), Some(0x02));
+        assert_eq!(mapper.cpu_read(0xc000), Some(0x01));
+        assert_eq!(mapper.cpu_read(0xffff), Some(0x02));
+
+        // 32K carts fill the whole window
+        let mut prg_rom = vec![0; 0x8000];
+        prg_rom[0x4000] = 0x03;
+
+        let mut mapper = nrom(&prg_rom, &[0; CHR_SIZE]);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(0x00));
+        assert_eq!(mapper.cpu_read(0xc000), Some(0x03));
+    }
+
+    #[test]
+    fn prg_ram() {
+        let mut mapper = nrom(&[0; 0x4000], &[0; CHR_SIZE]);
+
+        mapper.cpu_write(0x6123, 0x45);
+        assert_eq!(mapper.cpu_read(0x6123), Some(0x45));
+
+        // rom isn't writable, and nothing answers below $6000
+        mapper.cpu_write(0x8000, 0x45);
+        assert_eq!(mapper.cpu_read(0x8000), Some(0x00));
+        assert_eq!(mapper.cpu_read(0x5000), None);
+    }
+
+    #[test]
+    fn chr() {
+        let mut chr_rom = vec![0; CHR_SIZE];
+        chr_rom[0x1234] = 0x56;
 
-        let ppu = nes.borrow_mut().get_ppu();
-        ppu.borrow_mut()
-            .write_bytes_to(&0x0000u16.into(), &options.chr_rom);
+        let mut mapper = nrom(&[0; 0x4000], &chr_rom);
+        mapper.ppu_write(0x1234, 0x00);
+        assert_eq!(mapper.ppu_read(0x1234), 0x56);
 
-        Ok(())
+        // no chr rom means chr ram
+        let mut mapper = nrom(&[0; 0x4000], &[]);
+        mapper.ppu_write(0x1234, 0x78);
+        assert_eq!(mapper.ppu_read(0x1234), 0x78);
     }
 }
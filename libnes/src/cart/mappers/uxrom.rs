@@ -0,0 +1,121 @@
+use crate::cart::mappers::{bus_conflict, BankedMemory, Mapper, MapperOptions};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_SIZE: usize = 0x2000;
+
+// see https://wiki.nesdev.com/w/index.php/UxROM
+pub struct UxROMMapper {
+    prg_rom: BankedMemory,
+    chr: BankedMemory,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+
+    prg_bank: u8,
+}
+
+impl UxROMMapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        UxROMMapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            chr: options.chr(),
+            mirroring: options.mirroring,
+            bus_conflicts: options.bus_conflicts,
+            prg_bank: 0,
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        match addr {
+            0x8000...0xbfff => self.prg_bank as usize,
+            // the last bank is fixed at $c000
+            _ => self.prg_rom.num_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+}
+
+impl Mapper for UxROMMapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x8000...0xffff => {
+                let rom_val = self
+                    .prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr);
+
+                self.prg_bank = bus_conflict(self.bus_conflicts, rom_val, val);
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(0, CHR_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        self.chr.write(0, CHR_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn uxrom(num_prg_banks: usize, bus_conflicts: bool) -> UxROMMapper {
+        let prg_rom = numbered_banks(num_prg_banks, PRG_BANK_SIZE);
+
+        UxROMMapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            bus_conflicts,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = uxrom(8, false);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(0xc000), Some(7));
+
+        mapper.cpu_write(0x8000, 0x05);
+        assert_eq!(mapper.cpu_read(0xbfff), Some(5));
+        assert_eq!(mapper.cpu_read(0xffff), Some(7));
+    }
+
+    #[test]
+    fn bus_conflicts() {
+        let mut mapper = uxrom(8, true);
+
+        // writing over bank 7 lets every bit through
+        mapper.cpu_write(0xc000, 0x05);
+        assert_eq!(mapper.cpu_read(0x8000), Some(5));
+
+        // while writing over bank 5 drops bit 1
+        mapper.cpu_write(0x8000, 0x06);
+        assert_eq!(mapper.cpu_read(0x8000), Some(4));
+    }
+
+    #[test]
+    fn chr_ram() {
+        let mut mapper = uxrom(2, false);
+
+        mapper.ppu_write(0x1234, 0x56);
+        assert_eq!(mapper.ppu_read(0x1234), 0x56);
+    }
+}
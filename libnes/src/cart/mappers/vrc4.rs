@@ -0,0 +1,314 @@
+use crate::cart::mappers::{BankedMemory, Mapper, MapperOptions, VrcIrq};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const PRG_SWAP_MODE: u8 = 0x02;
+
+// Which cpu address lines a board wires to the chip's two register select pins
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VrcPins {
+    pub a0: u16,
+    pub a1: u16,
+}
+
+impl VrcPins {
+    // Boards for one mapper number that wire them differently are told apart by
+    // the nes 2.0 submapper; without one, both wirings are decoded at once
+    pub fn for_mapper(mapper_id: u8, submapper: u8) -> Self {
+        let (a0, a1) = match (mapper_id, submapper) {
+            (21, 1) => (0x02, 0x04),
+            (21, 2) => (0x40, 0x80),
+            (21, _) => (0x42, 0x84),
+            (22, _) => (0x02, 0x01),
+            (23, 1) | (23, 3) => (0x01, 0x02),
+            (23, 2) => (0x04, 0x08),
+            (23, _) => (0x05, 0x0a),
+            (25, 1) | (25, 3) => (0x02, 0x01),
+            (25, 2) => (0x08, 0x04),
+            (_, _) => (0x0a, 0x05),
+        };
+
+        VrcPins { a0, a1 }
+    }
+
+    // Maps an address onto $x000-$x003
+    pub fn register(&self, addr: u16) -> u16 {
+        let a0 = (addr & self.a0 != 0) as u16;
+        let a1 = (addr & self.a1 != 0) as u16;
+
+        (addr & 0xf000) | (a1 << 1) | a0
+    }
+}
+
+// VRC2 is a cut-down VRC4, without the irq, prg swap mode or single-screen mirroring,
+// see https://wiki.nesdev.com/w/index.php/VRC2_and_VRC4
+pub struct VRC4Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+    pins: VrcPins,
+    is_vrc2: bool,
+
+    // vrc2a drops the low bit of its chr banks
+    chr_bank_shift: u8,
+
+    prg_banks: [u8; 2],
+    prg_mode: u8,
+    chr_banks: [u16; 8],
+    mirroring: u8,
+
+    irq: VrcIrq,
+}
+
+impl VRC4Mapper {
+    pub fn new(mapper_id: u8, options: &MapperOptions) -> Self {
+        let is_vrc2 = match (mapper_id, options.submapper) {
+            (22, _) | (23, 3) | (25, 3) => true,
+            _ => false,
+        };
+
+        let chr_bank_shift = match mapper_id {
+            22 => 1,
+            _ => 0,
+        };
+
+        VRC4Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            pins: VrcPins::for_mapper(mapper_id, options.submapper),
+            is_vrc2,
+            chr_bank_shift,
+            prg_banks: [0; 2],
+            prg_mode: 0,
+            chr_banks: [0; 8],
+            mirroring: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        match (self.pins.register(addr), self.is_vrc2) {
+            (0x8000...0x8003, _) => self.prg_banks[0] = val & 0x1f,
+            (0x9000...0x9003, true) => self.mirroring = val & 0x01,
+            (0x9000...0x9001, false) => self.mirroring = val & 0x03,
+            (0x9002, false) => self.prg_mode = val,
+            (0xa000...0xa003, _) => self.prg_banks[1] = val & 0x1f,
+            (register @ 0xb000...0xe003, _) => self.write_chr_bank(register, val),
+            (0xf000, false) => self.irq.write_latch_lo(val),
+            (0xf001, false) => self.irq.write_latch_hi(val),
+            (0xf002, false) => self.irq.write_control(val),
+            (0xf003, false) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    // Each 1K chr bank is split across a pair of registers: the low nibble at
+    // $x000/$x002 and the high bits at $x001/$x003
+    fn write_chr_bank(&mut self, register: u16, val: u8) {
+        let bank = (((register - 0xb000) >> 12) * 2 + ((register >> 1) & 0x01)) as usize;
+
+        let hi_mask = match self.is_vrc2 {
+            true => 0x0f,
+            false => 0x1f,
+        };
+
+        self.chr_banks[bank] = match register & 0x01 {
+            0 => (self.chr_banks[bank] & 0x1f0) | (val & 0x0f) as u16,
+            _ => (self.chr_banks[bank] & 0x0f) | (((val & hi_mask) as u16) << 4),
+        };
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        let second_last_bank = self.prg_rom.num_banks(PRG_BANK_SIZE) - 2;
+
+        let is_swapped = self.prg_mode & PRG_SWAP_MODE != 0;
+
+        match (addr, is_swapped) {
+            (0x8000...0x9fff, false) | (0xc000...0xdfff, true) => self.prg_banks[0] as usize,
+            (0x8000...0x9fff, true) | (0xc000...0xdfff, false) => second_last_bank,
+            (0xa000...0xbfff, _) => self.prg_banks[1] as usize,
+            _ => second_last_bank + 1,
+        }
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        (self.chr_banks[(addr as usize) / CHR_BANK_SIZE] >> self.chr_bank_shift) as usize
+    }
+}
+
+impl Mapper for VRC4Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff => Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr)),
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x6000...0x7fff => self.prg_ram.write(0, PRG_RAM_SIZE, addr, val),
+            0x8000...0xffff => self.write_register(addr, val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank_at(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let bank = self.chr_bank_at(addr);
+
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        vrc_mirroring(self.mirroring)
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.is_pending()
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+    }
+}
+
+// The mirroring control shared by the vrc boards
+pub fn vrc_mirroring(val: u8) -> Mirroring {
+    match val & 0x03 {
+        0 => Mirroring::Vertical,
+        1 => Mirroring::Horizontal,
+        2 => Mirroring::SingleScreenLower,
+        _ => Mirroring::SingleScreenUpper,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn vrc4(mapper_id: u8, submapper: u8) -> VRC4Mapper {
+        let prg_rom = numbered_banks(16, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(256, CHR_BANK_SIZE);
+
+        VRC4Mapper::new(
+            mapper_id,
+            &MapperOptions {
+                prg_rom: &prg_rom,
+                chr_rom: &chr_rom,
+                prg_ram_size: PRG_RAM_SIZE,
+                submapper,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn pins() {
+        // vrc4a, vrc4c, and both at once
+        assert_eq!(VrcPins::for_mapper(21, 1).register(0x9004), 0x9002);
+        assert_eq!(VrcPins::for_mapper(21, 2).register(0x9080), 0x9002);
+        assert_eq!(VrcPins::for_mapper(21, 0).register(0x9084), 0x9002);
+        assert_eq!(VrcPins::for_mapper(21, 0).register(0x9042), 0x9001);
+
+        // vrc2a has the pins swapped
+        assert_eq!(VrcPins::for_mapper(22, 0).register(0xb001), 0xb002);
+
+        // vrc4f/vrc2b and vrc4e
+        assert_eq!(VrcPins::for_mapper(23, 1).register(0xb003), 0xb003);
+        assert_eq!(VrcPins::for_mapper(23, 2).register(0xb00c), 0xb003);
+        assert_eq!(VrcPins::for_mapper(23, 2).register(0xb003), 0xb000);
+
+        // vrc4b/vrc2c and vrc4d
+        assert_eq!(VrcPins::for_mapper(25, 1).register(0xb001), 0xb002);
+        assert_eq!(VrcPins::for_mapper(25, 2).register(0xb004), 0xb002);
+        assert_eq!(VrcPins::for_mapper(25, 0).register(0xb004), 0xb002);
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = vrc4(21, 1);
+
+        mapper.cpu_write(0x8000, 0x03);
+        mapper.cpu_write(0xa000, 0x05);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xa000), Some(5));
+        assert_eq!(mapper.cpu_read(0xc000), Some(14));
+        assert_eq!(mapper.cpu_read(0xe000), Some(15));
+
+        // swap mode trades $8000 and $c000
+        mapper.cpu_write(0x9004, PRG_SWAP_MODE);
+        assert_eq!(mapper.cpu_read(0x8000), Some(14));
+        assert_eq!(mapper.cpu_read(0xc000), Some(3));
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut mapper = vrc4(25, 1);
+
+        // bank 2 is at $c000/$c002 on vrc4b
+        mapper.cpu_write(0xc000, 0x0b);
+        mapper.cpu_write(0xc002, 0x0a);
+
+        assert_eq!(mapper.ppu_read(0x0800), 0xab);
+
+        // vrc2a only uses the upper 7 bits of each bank
+        let mut mapper = vrc4(22, 0);
+
+        mapper.cpu_write(0xb000, 0x07);
+        mapper.cpu_write(0xb002, 0x01);
+        assert_eq!(mapper.ppu_read(0x0000), 0x0b);
+    }
+
+    #[test]
+    fn mirroring() {
+        let mut mapper = vrc4(23, 2);
+
+        mapper.cpu_write(0x9000, 0x01);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        mapper.cpu_write(0x9000, 0x03);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+
+        // vrc2 only has the one bit
+        let mut mapper = vrc4(23, 3);
+
+        mapper.cpu_write(0x9000, 0x03);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn irq() {
+        let mut mapper = vrc4(23, 2);
+
+        // vrc4e's irq registers are at $f000/$f004/$f008/$f00c
+        mapper.cpu_write(0xf000, 0x0e);
+        mapper.cpu_write(0xf004, 0x0f);
+        mapper.cpu_write(0xf008, 0x06);
+
+        mapper.cpu_clock();
+        assert_eq!(mapper.irq(), false);
+
+        mapper.cpu_clock();
+        assert_eq!(mapper.irq(), true);
+
+        mapper.cpu_write(0xf00c, 0x00);
+        assert_eq!(mapper.irq(), false);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::cart::mappers::{
+    vrc_mirroring, BankedMemory, Mapper, MapperOptions, Vrc6Audio, VrcIrq, VrcPins,
+};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const PPU_CONTROL_MIRRORING_SHIFT: u8 = 2;
+const PPU_CONTROL_PRG_RAM_ENABLE: u8 = 0x80;
+
+// Only the 1K chr banking mode is supported, which is the one every vrc6 game uses,
+// see https://wiki.nesdev.com/w/index.php/VRC6
+pub struct VRC6Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+    pins: VrcPins,
+
+    // (16K bank at $8000, 8K bank at $c000)
+    prg_banks: (u8, u8),
+    chr_banks: [u8; 8],
+    ppu_control: u8,
+
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl VRC6Mapper {
+    // vrc6b (mapper 26) swaps the register select pins of vrc6a (mapper 24)
+    pub fn new(mapper_id: u8, options: &MapperOptions) -> Self {
+        let pins = match mapper_id {
+            26 => VrcPins { a0: 0x02, a1: 0x01 },
+            _ => VrcPins { a0: 0x01, a1: 0x02 },
+        };
+
+        VRC6Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            pins,
+            prg_banks: (0, 0),
+            chr_banks: [0; 8],
+            ppu_control: 0,
+            irq: VrcIrq::new(),
+            audio: Vrc6Audio::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        match self.pins.register(addr) {
+            0x8000...0x8003 => self.prg_banks.0 = val & 0x0f,
+            0xb003 => self.ppu_control = val,
+            register @ 0x9000...0xb002 => self.audio.write_register(register, val),
+            0xc000...0xc003 => self.prg_banks.1 = val & 0x1f,
+            register @ 0xd000...0xe003 => {
+                let bank = ((register - 0xd000) >> 12) * 4 + (register & 0x03);
+
+                self.chr_banks[bank as usize] = val;
+            }
+            0xf000 => self.irq.write_latch(val),
+            0xf001 => self.irq.write_control(val),
+            0xf002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        let (bank_16k, bank_8k) = self.prg_banks;
+
+        match addr {
+            0x8000...0xbfff => (bank_16k as usize * 2) | ((addr >> 13) & 0x01) as usize,
+            0xc000...0xdfff => bank_8k as usize,
+            _ => self.prg_rom.num_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.ppu_control & PPU_CONTROL_PRG_RAM_ENABLE != 0
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        self.chr_banks[(addr as usize) / CHR_BANK_SIZE] as usize
+    }
+}
+
+impl Mapper for VRC6Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff if self.is_prg_ram_enabled() => {
+                Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr))
+            }
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x6000...0x7fff if self.is_prg_ram_enabled() => {
+                self.prg_ram.write(0, PRG_RAM_SIZE, addr, val)
+            }
+            0x8000...0xffff => self.write_register(addr, val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank_at(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let bank = self.chr_bank_at(addr);
+
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        vrc_mirroring(self.ppu_control >> PPU_CONTROL_MIRRORING_SHIFT)
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.is_pending()
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn vrc6(mapper_id: u8) -> VRC6Mapper {
+        let prg_rom = numbered_banks(32, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(256, CHR_BANK_SIZE);
+
+        VRC6Mapper::new(
+            mapper_id,
+            &MapperOptions {
+                prg_rom: &prg_rom,
+                chr_rom: &chr_rom,
+                prg_ram_size: PRG_RAM_SIZE,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = vrc6(24);
+
+        mapper.cpu_write(0x8000, 0x03);
+        mapper.cpu_write(0xc000, 0x11);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(6));
+        assert_eq!(mapper.cpu_read(0xa000), Some(7));
+        assert_eq!(mapper.cpu_read(0xc000), Some(17));
+        assert_eq!(mapper.cpu_read(0xe000), Some(31));
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut mapper = vrc6(26);
+
+        let registers = [
+            0xd000u16, 0xd002, 0xd001, 0xd003, 0xe000, 0xe002, 0xe001, 0xe003,
+        ];
+
+        for (i, addr) in registers.iter().enumerate() {
+            mapper.cpu_write(*addr, 0x20 + i as u8);
+        }
+
+        // vrc6b has the pins swapped, so $d002 is bank 1
+        let banks: Vec<u8> = (0..8).map(|i| mapper.ppu_read(i * 0x400)).collect();
+        assert_eq!(banks, vec![0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27]);
+    }
+
+    #[test]
+    fn ppu_control() {
+        let mut mapper = vrc6(24);
+
+        // prg ram is disabled at power on
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), None);
+
+        mapper.cpu_write(0xb003, 0x84);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+    }
+
+    #[test]
+    fn irq() {
+        let mut mapper = vrc6(24);
+
+        mapper.cpu_write(0xf000, 0xff);
+        mapper.cpu_write(0xf001, 0x06);
+
+        mapper.cpu_clock();
+        assert_eq!(mapper.irq(), true);
+
+        mapper.cpu_write(0xf002, 0x00);
+        assert_eq!(mapper.irq(), false);
+    }
+
+    #[test]
+    fn audio() {
+        let mut mapper = vrc6(26);
+
+        // a constant pulse at volume 5, through vrc6b's swapped pins
+        mapper.cpu_write(0x9000, 0x85);
+        mapper.cpu_write(0x9001, 0x80);
+        mapper.cpu_clock();
+
+        assert!(mapper.audio_output() > 0.0);
+    }
+}
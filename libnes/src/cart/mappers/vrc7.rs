@@ -0,0 +1,247 @@
+use crate::cart::mappers::{vrc_mirroring, BankedMemory, Mapper, MapperOptions, Vrc7Audio, VrcIrq};
+use crate::ppu::mem::Mirroring;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const CONTROL_AUDIO_RESET: u8 = 0x40;
+const CONTROL_PRG_RAM_ENABLE: u8 = 0x80;
+
+// A5 picks $9030 (data) over $9010 (register select)
+const AUDIO_DATA_PIN: u16 = 0x20;
+
+// see https://wiki.nesdev.com/w/index.php/VRC7
+pub struct VRC7Mapper {
+    prg_rom: BankedMemory,
+    prg_ram: BankedMemory,
+    chr: BankedMemory,
+
+    // vrc7a wires A4 to the chip's register select, vrc7b wires A3
+    select_pin: u16,
+
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    control: u8,
+
+    irq: VrcIrq,
+    audio: Vrc7Audio,
+}
+
+impl VRC7Mapper {
+    pub fn new(options: &MapperOptions) -> Self {
+        let select_pin = match options.submapper {
+            1 => 0x08,
+            2 => 0x10,
+            _ => 0x18,
+        };
+
+        VRC7Mapper {
+            prg_rom: BankedMemory::rom(options.prg_rom),
+            prg_ram: options.prg_ram(),
+            chr: options.chr(),
+            select_pin,
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+            audio: Vrc7Audio::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        let is_odd = addr & self.select_pin != 0;
+
+        match (addr & 0xf000, is_odd) {
+            (0x8000, false) => self.prg_banks[0] = val & 0x3f,
+            (0x8000, true) => self.prg_banks[1] = val & 0x3f,
+            (0x9000, false) => self.prg_banks[2] = val & 0x3f,
+            (0x9000, true) => match addr & AUDIO_DATA_PIN {
+                0 => self.audio.write_register_select(val),
+                _ => self.audio.write_register(val),
+            },
+            (register @ 0xa000...0xd000, _) => {
+                let bank = ((register - 0xa000) >> 11) as usize + is_odd as usize;
+
+                self.chr_banks[bank] = val;
+            }
+            (0xe000, false) => self.control = val,
+            (0xe000, true) => self.irq.write_latch(val),
+            (0xf000, false) => self.irq.write_control(val),
+            (0xf000, true) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_bank_at(&self, addr: u16) -> usize {
+        match addr {
+            0x8000...0xdfff => self.prg_banks[((addr - 0x8000) as usize) / PRG_BANK_SIZE] as usize,
+            _ => self.prg_rom.num_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.control & CONTROL_PRG_RAM_ENABLE != 0
+    }
+
+    fn chr_bank_at(&self, addr: u16) -> usize {
+        self.chr_banks[(addr as usize) / CHR_BANK_SIZE] as usize
+    }
+}
+
+impl Mapper for VRC7Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000...0x7fff if self.is_prg_ram_enabled() => {
+                Some(self.prg_ram.read(0, PRG_RAM_SIZE, addr))
+            }
+            0x8000...0xffff => Some(
+                self.prg_rom
+                    .read(self.prg_bank_at(addr), PRG_BANK_SIZE, addr),
+            ),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x6000...0x7fff if self.is_prg_ram_enabled() => {
+                self.prg_ram.write(0, PRG_RAM_SIZE, addr, val)
+            }
+            0x8000...0xffff => self.write_register(addr, val),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr.read(self.chr_bank_at(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, val: u8) {
+        let bank = self.chr_bank_at(addr);
+
+        self.chr.write(bank, CHR_BANK_SIZE, addr, val);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        vrc_mirroring(self.control)
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.is_pending()
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.clock();
+
+        // the audio reset holds the chip silent
+        if self.control & CONTROL_AUDIO_RESET == 0 {
+            self.audio.clock();
+        }
+    }
+
+    fn audio_output(&self) -> f32 {
+        match self.control & CONTROL_AUDIO_RESET {
+            0 => self.audio.output(),
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::numbered_banks;
+
+    fn vrc7(submapper: u8) -> VRC7Mapper {
+        let prg_rom = numbered_banks(32, PRG_BANK_SIZE);
+
+        let chr_rom = numbered_banks(256, CHR_BANK_SIZE);
+
+        VRC7Mapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &chr_rom,
+            prg_ram_size: PRG_RAM_SIZE,
+            submapper,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = vrc7(2);
+
+        mapper.cpu_write(0x8000, 0x03);
+        mapper.cpu_write(0x8010, 0x04);
+        mapper.cpu_write(0x9000, 0x05);
+
+        assert_eq!(mapper.cpu_read(0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(0xa000), Some(4));
+        assert_eq!(mapper.cpu_read(0xc000), Some(5));
+        assert_eq!(mapper.cpu_read(0xe000), Some(31));
+    }
+
+    #[test]
+    fn chr_banks() {
+        let mut mapper = vrc7(1);
+
+        for i in 0..8u16 {
+            let addr = 0xa000 + (i / 2) * 0x1000 + (i % 2) * 0x08;
+
+            mapper.cpu_write(addr, 0x30 + i as u8);
+        }
+
+        let banks: Vec<u8> = (0..8).map(|i| mapper.ppu_read(i * 0x400)).collect();
+        assert_eq!(banks, vec![0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37]);
+    }
+
+    #[test]
+    fn control() {
+        let mut mapper = vrc7(0);
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), None);
+
+        mapper.cpu_write(0xe000, 0x83);
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+
+        mapper.cpu_write(0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(0x6000), Some(0x12));
+    }
+
+    #[test]
+    fn irq() {
+        let mut mapper = vrc7(2);
+
+        mapper.cpu_write(0xe010, 0xff);
+        mapper.cpu_write(0xf000, 0x06);
+
+        mapper.cpu_clock();
+        assert_eq!(mapper.irq(), true);
+
+        mapper.cpu_write(0xf010, 0x00);
+        assert_eq!(mapper.irq(), false);
+    }
+
+    #[test]
+    fn audio() {
+        let mut mapper = vrc7(2);
+
+        // channel 0 keyed on with instrument 3 (the wurly piano)
+        let writes = [(0x10, 0x80), (0x30, 0x30), (0x20, 0x19)];
+        for (register, val) in writes.iter() {
+            mapper.cpu_write(0x9010, *register);
+            mapper.cpu_write(0x9030, *val);
+        }
+
+        let mut outputs = vec![];
+        for _ in 0..36 * 100 {
+            mapper.cpu_clock();
+            outputs.push(mapper.audio_output());
+        }
+        assert!(outputs.iter().any(|output| *output != 0.0));
+
+        mapper.cpu_write(0xe000, CONTROL_AUDIO_RESET);
+        assert_eq!(mapper.audio_output(), 0.0);
+    }
+}
@@ -0,0 +1,158 @@
+const IRQ_CONTROL_ENABLE_AFTER_ACK: u8 = 0x01;
+const IRQ_CONTROL_ENABLE: u8 = 0x02;
+const IRQ_CONTROL_CYCLE_MODE: u8 = 0x04;
+
+// The prescaler approximates scanlines from cpu cycles, 3 ppu dots at a time
+const PRESCALER_PERIOD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+// The irq counter shared by konami's vrc4, vrc6 and vrc7: it counts up from a
+// latch to $ff once per scanline (or cpu cycle), without watching the ppu at all,
+// see https://wiki.nesdev.com/w/index.php/VRC_IRQ
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+
+    is_enabled: bool,
+    is_enabled_after_ack: bool,
+    is_cycle_mode: bool,
+    is_pending: bool,
+}
+
+impl Default for VrcIrq {
+    fn default() -> Self {
+        VrcIrq {
+            latch: 0,
+            counter: 0,
+            prescaler: PRESCALER_PERIOD,
+            is_enabled: false,
+            is_enabled_after_ack: false,
+            is_cycle_mode: false,
+            is_pending: false,
+        }
+    }
+}
+
+impl VrcIrq {
+    pub fn new() -> Self {
+        VrcIrq::default()
+    }
+
+    pub fn write_latch(&mut self, val: u8) {
+        self.latch = val;
+    }
+
+    // vrc4 splits the latch across two registers
+    pub fn write_latch_lo(&mut self, val: u8) {
+        self.latch = (self.latch & 0xf0) | (val & 0x0f);
+    }
+
+    pub fn write_latch_hi(&mut self, val: u8) {
+        self.latch = (self.latch & 0x0f) | (val << 4);
+    }
+
+    pub fn write_control(&mut self, val: u8) {
+        self.is_enabled_after_ack = val & IRQ_CONTROL_ENABLE_AFTER_ACK != 0;
+        self.is_enabled = val & IRQ_CONTROL_ENABLE != 0;
+        self.is_cycle_mode = val & IRQ_CONTROL_CYCLE_MODE != 0;
+        self.is_pending = false;
+
+        if self.is_enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.is_pending = false;
+        self.is_enabled = self.is_enabled_after_ack;
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.is_pending
+    }
+
+    // Called once per cpu cycle
+    pub fn clock(&mut self) {
+        if !self.is_enabled {
+            return;
+        }
+
+        match self.is_cycle_mode {
+            true => self.clock_counter(),
+            false => {
+                self.prescaler -= PRESCALER_STEP;
+
+                if self.prescaler <= 0 {
+                    self.prescaler += PRESCALER_PERIOD;
+                    self.clock_counter();
+                }
+            }
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        match self.counter {
+            0xff => {
+                self.counter = self.latch;
+                self.is_pending = true;
+            }
+            _ => self.counter += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cycle_mode() {
+        let mut irq = VrcIrq::new();
+
+        irq.write_latch(0xfd);
+        irq.write_control(IRQ_CONTROL_ENABLE | IRQ_CONTROL_CYCLE_MODE);
+
+        // counts up from the latch, and fires on the way past $ff
+        irq.clock();
+        irq.clock();
+        assert_eq!(irq.is_pending(), false);
+
+        irq.clock();
+        assert_eq!(irq.is_pending(), true);
+
+        // acking without enable-after-ack turns it off
+        irq.acknowledge();
+        for _ in 0..10 {
+            irq.clock();
+        }
+        assert_eq!(irq.is_pending(), false);
+    }
+
+    #[test]
+    fn scanline_mode() {
+        let mut irq = VrcIrq::new();
+
+        irq.write_latch_lo(0x0e);
+        irq.write_latch_hi(0x0f);
+        irq.write_control(IRQ_CONTROL_ENABLE | IRQ_CONTROL_ENABLE_AFTER_ACK);
+
+        // two scanlines' worth of cpu cycles
+        let mut cycles = 0;
+        while !irq.is_pending() {
+            irq.clock();
+            cycles += 1;
+        }
+
+        assert_eq!(cycles, 228);
+
+        // it stays enabled after the ack, and reloads from the latch
+        irq.acknowledge();
+
+        for _ in 0..228 {
+            irq.clock();
+        }
+        assert_eq!(irq.is_pending(), true);
+    }
+}
@@ -5,7 +5,7 @@ use byteorder::{ByteOrder, LittleEndian};
 
 use crate::bits::lsb;
 use crate::bits::msb;
-use crate::cpu::instr::CpuInstruction;
+use crate::cpu::instr::{CpuInstruction, CyclePenalties};
 use crate::ev::{Observable, Observer};
 
 use super::mem::{Address, CpuMemoryMap, DefaultCpuMemoryMap};
@@ -16,13 +16,15 @@ pub const RESET_INTERRUPT_ADDR_START: u16 = 0xfffc;
 pub const IRQ_INTERRUPT_ADDR_START: u16 = 0xfffe;
 pub const BRK_INTERRUPT_ADDR_START: u16 = 0xffe6;
 
+const INTERRUPT_CYCLES: u16 = 7;
+
 pub trait Cpu {
     fn start(&mut self);
     fn stop(&mut self);
     fn reset(&mut self);
     fn clock(&mut self);
 
-    fn step(&mut self);
+    fn step(&mut self) -> u16;
     fn run(&mut self);
     fn is_running(&self) -> bool;
 
@@ -45,6 +47,17 @@ pub trait Cpu {
 
     fn get_registers(&self) -> &Registers;
     fn get_registers_mut(&mut self) -> &mut Registers;
+
+    fn get_cycles(&self) -> u64;
+    fn get_penalties_mut(&mut self) -> &mut CyclePenalties;
+
+    // Suspends the cpu for the given number of cycles after the current instruction (e.g. for dma)
+    fn stall(&mut self, cycles: u16);
+
+    // NMI is edge-triggered: it fires once each time the line goes from released to asserted
+    fn set_nmi_line(&mut self, asserted: bool);
+    // IRQ is level-triggered: it fires at every instruction boundary while asserted and not masked
+    fn set_irq_line(&mut self, asserted: bool);
 }
 
 impl Debug for Cpu {
@@ -62,6 +75,12 @@ impl Debug for Cpu {
 pub struct DefaultCpu {
     pub memory: Box<CpuMemoryMap>,
     pub registers: Registers,
+    cycles: u64,
+    pending_cycles: u16,
+    penalties: CyclePenalties,
+    nmi_line: bool,
+    nmi_pending: bool,
+    irq_line: bool,
     is_stopped: bool,
     debug: bool,
     has_started_up: bool,
@@ -91,10 +110,17 @@ impl Cpu for DefaultCpu {
         );
 
         self.registers.pc = LittleEndian::read_u16(&[lower, upper]);
+        self.nmi_pending = false;
     }
 
     fn clock(&mut self) {
-        self.step()
+        // Instructions run all at once on their first cycle; the rest of the
+        // clocks just burn off the cycles they took
+        if self.pending_cycles == 0 {
+            self.pending_cycles = self.step();
+        }
+
+        self.pending_cycles -= 1;
     }
 
     fn next_u8(&mut self) -> u8 {
@@ -142,8 +168,8 @@ impl Cpu for DefaultCpu {
     }
 
     fn push(&mut self, val: u8) {
-        self.registers.sp -= 1;
-        self.write_bytes_to(&self.registers.sp.into(), &[val]);
+        self.write_bytes_to(&stack_addr(self.registers.sp), &[val]);
+        self.registers.sp = self.registers.sp.wrapping_sub(1);
     }
 
     fn push_u16(&mut self, val: u16) {
@@ -152,16 +178,14 @@ impl Cpu for DefaultCpu {
 
     fn push_bytes(&mut self, bytes: &[u8]) {
         for byte in bytes.iter() {
-            self.registers.sp -= 1;
-            self.write_bytes_to(&self.registers.sp.into(), &[byte.clone()]);
+            self.push(byte.clone());
         }
     }
 
     fn pop(&mut self) -> u8 {
-        let value = self.read_u8_at(&self.registers.sp.into());
-        self.registers.sp += 1;
+        self.registers.sp = self.registers.sp.wrapping_add(1);
 
-        value
+        self.read_u8_at(&stack_addr(self.registers.sp))
     }
 
     fn pop_u16(&mut self) -> u16 {
@@ -179,6 +203,31 @@ impl Cpu for DefaultCpu {
         &mut self.registers
     }
 
+    fn get_cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    fn get_penalties_mut(&mut self) -> &mut CyclePenalties {
+        &mut self.penalties
+    }
+
+    fn stall(&mut self, cycles: u16) {
+        self.pending_cycles += cycles;
+        self.cycles += cycles as u64;
+    }
+
+    fn set_nmi_line(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+
+        self.nmi_line = asserted;
+    }
+
+    fn set_irq_line(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
     fn run(&mut self) {
         if !self.has_started_up {
             self.start();
@@ -197,28 +246,42 @@ impl Cpu for DefaultCpu {
         }
     }
 
-    fn step(&mut self) {
+    fn step(&mut self) -> u16 {
         let debug = self.debug;
 
         if debug {
             println!("cpu pre: {:?}", self as &mut Cpu);
         }
 
+        // Interrupts are polled at instruction boundaries, NMI taking priority over IRQ
+        if self.nmi_pending {
+            self.nmi_pending = false;
+
+            return self.interrupt("nmi", NMI_INTERRUPT_ADDR_START);
+        }
+
+        if self.irq_line && !self.registers.p.interrupt_disable {
+            return self.interrupt("irq", IRQ_INTERRUPT_ADDR_START);
+        }
+
         let instruction = self.next_instr();
 
         if debug {
             println!("instr: {:?}", instruction);
         }
 
-        instruction.run();
+        let cycles = instruction.run() as u16;
+        self.cycles += cycles as u64;
 
         if debug {
-            println!("cpu post: {:?}", self as &mut Cpu);
+            println!("cpu post: {:?} (cycles: {})", self as &mut Cpu, cycles);
         }
 
         if debug {
             println!("");
         }
+
+        cycles
     }
 
     fn load_mem(&mut self, mem: Box<CpuMemoryMap>) {
@@ -239,6 +302,12 @@ impl DefaultCpu {
         DefaultCpu {
             memory: Box::new(DefaultCpuMemoryMap::new()),
             registers: Registers::new(),
+            cycles: 0,
+            pending_cycles: 0,
+            penalties: CyclePenalties::default(),
+            nmi_line: false,
+            nmi_pending: false,
+            irq_line: false,
             is_stopped: false,
             debug: debug,
             has_started_up: false,
@@ -254,4 +323,29 @@ impl DefaultCpu {
 
         CpuInstruction::from(opcode, self)
     }
+
+    fn interrupt(&mut self, name: &str, vector_addr: u16) -> u16 {
+        if self.debug {
+            println!("interrupt: {}", name);
+        }
+
+        // Hardware interrupts push P with the break flag cleared
+        let mut p = self.registers.p.clone();
+        p.break_command = false;
+
+        let pc = self.registers.pc;
+        self.push_u16(pc);
+        self.push(p.into());
+
+        self.registers.p.interrupt_disable = true;
+        self.registers.pc = self.read_u16_at(&vector_addr.into());
+
+        self.cycles += INTERRUPT_CYCLES as u64;
+
+        INTERRUPT_CYCLES
+    }
+}
+
+fn stack_addr(sp: u8) -> Address {
+    (0x0100 | sp as u16).into()
 }
@@ -1,3 +1,4 @@
+use crate::bits::msb;
 use crate::cpu::instr::addressing::AddressingMode;
 use crate::cpu::mem::Address;
 use crate::cpu::registers::ProcStatusFlags;
@@ -51,13 +52,19 @@ pub fn get_operand(cpu: &mut Cpu, addr_mode: &AddressingMode) -> GetOperandResul
         AddressingMode::Absolute => GetOperandResult::Address(cpu.next_u16().into()),
         AddressingMode::AbsoluteX => {
             let base_addr: Address = cpu.next_u16().into();
+            let addr = &base_addr + cpu.get_registers_mut().x;
 
-            (&base_addr + cpu.get_registers_mut().x).into()
+            cpu.get_penalties_mut().page_crossed = crosses_page(&base_addr, &addr);
+
+            addr.into()
         }
         AddressingMode::AbsoluteY => {
             let base_addr: Address = cpu.next_u16().into();
+            let addr = &base_addr + cpu.get_registers_mut().y;
 
-            (&base_addr + cpu.get_registers_mut().y).into()
+            cpu.get_penalties_mut().page_crossed = crosses_page(&base_addr, &addr);
+
+            addr.into()
         }
         AddressingMode::Indirect => {
             let addr = cpu.next_u16();
@@ -96,12 +103,19 @@ pub fn get_operand(cpu: &mut Cpu, addr_mode: &AddressingMode) -> GetOperandResul
             // A <- *($4038)
             let indir_addr = (cpu.get_registers_mut().y as u16 + operand_addr).into();
 
+            cpu.get_penalties_mut().page_crossed =
+                crosses_page(&operand_addr.into(), &indir_addr);
+
             // A <- $23
             GetOperandResult::Value(cpu.read_u8_at(&indir_addr) as i8)
         }
     }
 }
 
+pub fn crosses_page(from: &Address, to: &Address) -> bool {
+    msb(from.get_addr()) != msb(to.get_addr())
+}
+
 pub fn flags_from_compare(cpu_status: ProcStatusFlags, left: i8, right: i8) -> ProcStatusFlags {
     ProcStatusFlags {
         carry: left >= right,
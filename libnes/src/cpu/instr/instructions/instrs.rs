@@ -87,10 +87,8 @@ pub fn bcc(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let value = operand.resolve_value(cpu);
 
-    let registers = cpu.get_registers_mut();
-
-    if !registers.p.carry {
-        registers.pc = apply_branch_offset(registers.pc, value);
+    if !cpu.get_registers().p.carry {
+        branch(cpu, value);
     }
 }
 
@@ -98,10 +96,8 @@ pub fn bcs(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let value = operand.resolve_value(cpu);
 
-    let registers = cpu.get_registers_mut();
-
-    if registers.p.carry {
-        registers.pc = apply_branch_offset(registers.pc, value);
+    if cpu.get_registers().p.carry {
+        branch(cpu, value);
     }
 }
 
@@ -109,10 +105,8 @@ pub fn beq(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let value = operand.resolve_value(cpu);
 
-    let registers = cpu.get_registers_mut();
-
-    if registers.p.zero {
-        registers.pc = apply_branch_offset(registers.pc, value);
+    if cpu.get_registers().p.zero {
+        branch(cpu, value);
     }
 }
 
@@ -133,10 +127,8 @@ pub fn bmi(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let offset = operand.resolve_value(cpu);
 
-    let registers = cpu.get_registers_mut();
-
-    if registers.p.negative {
-        registers.pc = apply_branch_offset(registers.pc, offset);
+    if cpu.get_registers().p.negative {
+        branch(cpu, offset);
     }
 }
 
@@ -144,10 +136,8 @@ pub fn bne(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let value = operand.resolve_value(cpu) as i8;
 
-    let registers = cpu.get_registers_mut();
-
-    if !registers.p.zero {
-        registers.pc = apply_branch_offset(registers.pc, value);
+    if !cpu.get_registers().p.zero {
+        branch(cpu, value);
     }
 }
 
@@ -155,10 +145,8 @@ pub fn bpl(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let offset = operand.resolve_value(cpu);
 
-    let registers = cpu.get_registers_mut();
-
-    if !registers.p.zero {
-        registers.pc = apply_branch_offset(registers.pc, offset);
+    if !cpu.get_registers().p.zero {
+        branch(cpu, offset);
     }
 }
 
@@ -183,10 +171,8 @@ pub fn bvc(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let offset = operand.resolve_value(cpu);
 
-    let registers = cpu.get_registers_mut();
-
-    if !registers.p.negative {
-        registers.pc = apply_branch_offset(registers.pc, offset);
+    if !cpu.get_registers().p.negative {
+        branch(cpu, offset);
     }
 }
 
@@ -194,10 +180,8 @@ pub fn bvs(cpu: &mut impl Cpu, addr_mode: AddressingMode) {
     let operand = get_operand(cpu, &addr_mode);
     let offset = operand.resolve_value(cpu);
 
-    let registers = cpu.get_registers_mut();
-
-    if registers.p.overflow {
-        registers.pc = apply_branch_offset(registers.pc, offset);
+    if cpu.get_registers().p.overflow {
+        branch(cpu, offset);
     }
 }
 
@@ -678,6 +662,17 @@ fn apply_branch_offset(pc: u16, offset: i8) -> u16 {
     (pc as i32 + offset as i32) as u16
 }
 
+fn branch(cpu: &mut impl Cpu, offset: i8) {
+    let old_pc = cpu.get_registers().pc;
+    let new_pc = apply_branch_offset(old_pc, offset);
+
+    cpu.get_registers_mut().pc = new_pc;
+
+    let penalties = cpu.get_penalties_mut();
+    penalties.branch_taken = true;
+    penalties.branch_page_crossed = msb(old_pc) != msb(new_pc);
+}
+
 fn set_zn_flags_from_result(cpu: &mut impl Cpu, result: u8) {
     let registers = cpu.get_registers_mut();
 
@@ -17,7 +17,7 @@ impl From<u16> for Address {
             0x0000...0x07ff => Address::Address(addr),
             0x0800...0x1fff => Address::Mirror {
                 mirror_lo: 0x0000,
-                mirror_hi: 0x7fff,
+                mirror_hi: 0x07ff,
                 addr: addr,
             },
             0x2000...0x2007 => Address::Address(addr),
@@ -86,8 +86,8 @@ impl Address {
                 mirror_hi,
                 addr,
             } => {
-                let mirror_size = mirror_hi - mirror_lo;
-                let effective_addr = mirror_lo + (addr % mirror_size);
+                let mirror_size = mirror_hi - mirror_lo + 1;
+                let effective_addr = mirror_lo + ((addr - mirror_lo) % mirror_size);
 
                 effective_addr
             }
@@ -112,4 +112,13 @@ impl Address {
 
 #[cfg(test)]
 mod test {
+    use super::*;
+
+    #[test]
+    fn mirrors() {
+        assert_eq!(Address::from(0x0812u16).get_addr(), 0x0012);
+        assert_eq!(Address::from(0x1fffu16).get_addr(), 0x07ff);
+        assert_eq!(Address::from(0x2008u16).get_addr(), 0x2000);
+        assert_eq!(Address::from(0x3456u16).get_addr(), 0x2006);
+    }
 }
@@ -33,6 +33,7 @@ impl DefaultCpuMemoryMap {
 impl CpuMemoryMap for DefaultCpuMemoryMap {
     fn get(&self, addr: &Address) -> u8 {
         let effective_addr = addr.get_addr();
+
         let byte = self.memory[effective_addr as usize];
 
         // Notify subscribers
@@ -4,8 +4,9 @@ use std::path::Path;
 use std::sync::mpsc::channel;
 use std::time::Duration;
 
+use crate::bits::to_bytes;
 use crate::cpu::helpers::*;
-use crate::cpu::{Cpu, DefaultCpu};
+use crate::cpu::{Cpu, DefaultCpu, IRQ_INTERRUPT_ADDR_START, NMI_INTERRUPT_ADDR_START};
 
 #[test]
 fn basic_program() {
@@ -123,6 +124,201 @@ fn ror_mem() {
     assert_eq!(cpu.read_u8_at(&0x8000u16.into()), 0x40);
 }
 
+#[test]
+fn cycles_page_cross() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str(&mut cpu, "a2 01 bd 00 06 bd ff 06 9d 00 06");
+
+    cpu.start();
+
+    assert_eq!(cpu.step(), 2);
+    assert_eq!(cpu.step(), 4);
+    assert_eq!(cpu.step(), 5);
+
+    // stores always take the extra cycle, crossed or not
+    assert_eq!(cpu.step(), 5);
+
+    assert_eq!(cpu.get_cycles(), 16);
+}
+
+#[test]
+fn cycles_branch() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str(&mut cpu, "a9 01 f0 02 d0 00 d0 fe");
+
+    cpu.start();
+
+    assert_eq!(cpu.step(), 2);
+
+    // not taken
+    assert_eq!(cpu.step(), 2);
+
+    // taken, same page
+    assert_eq!(cpu.step(), 3);
+    assert_eq!(cpu.step(), 3);
+}
+
+#[test]
+fn cycles_branch_page_cross() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str_with_options(
+        &mut cpu,
+        "a9 00 f0 04",
+        LoadProgramOptions {
+            load_addr: 0x06fa,
+            start_addr: 0x06fa,
+            debug: false,
+        },
+    );
+
+    cpu.start();
+
+    assert_eq!(cpu.step(), 2);
+    assert_eq!(cpu.step(), 4);
+    assert_eq!(cpu.registers.pc, 0x0702);
+}
+
+#[test]
+fn clock_spreads_instruction_cycles() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str(&mut cpu, "a9 01 8d 00 02 a9 05");
+
+    cpu.start();
+
+    // lda (2 cycles) runs on the first clock
+    cpu.clock();
+    assert_eq!(cpu.registers.pc, 0x0602);
+
+    cpu.clock();
+    assert_eq!(cpu.registers.pc, 0x0602);
+
+    // sta (4 cycles) runs on the third
+    cpu.clock();
+    assert_eq!(cpu.registers.pc, 0x0605);
+
+    for _ in 0..3 {
+        cpu.clock();
+    }
+    assert_eq!(cpu.registers.pc, 0x0605);
+
+    cpu.clock();
+    assert_eq!(cpu.registers.pc, 0x0607);
+    assert_eq!(cpu.get_cycles(), 8);
+}
+
+#[test]
+fn nmi() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str(&mut cpu, "ea ea ea");
+    cpu.write_bytes_to(&NMI_INTERRUPT_ADDR_START.into(), &[0x00, 0x07]);
+
+    cpu.start();
+    cpu.step();
+
+    cpu.set_nmi_line(true);
+
+    assert_eq!(cpu.step(), 7);
+    assert_eq!(cpu.registers.pc, 0x0700);
+    assert_eq!(cpu.registers.sp, 0xfa);
+    assert_eq!(cpu.registers.p.interrupt_disable, true);
+
+    // pc hi, pc lo, then p with the break flag cleared
+    assert_eq!(cpu.read_u8_at(&0x01fdu16.into()), 0x06);
+    assert_eq!(cpu.read_u8_at(&0x01fcu16.into()), 0x01);
+    assert_eq!(cpu.read_u8_at(&0x01fbu16.into()), 0b00100000);
+}
+
+#[test]
+fn nmi_is_edge_triggered() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str(&mut cpu, "ea ea ea");
+    cpu.write_bytes_to(&NMI_INTERRUPT_ADDR_START.into(), &[0x00, 0x07]);
+    cpu.write_bytes_to(&0x0700u16.into(), &to_bytes("ea ea ea"));
+
+    cpu.start();
+
+    cpu.set_nmi_line(true);
+    assert_eq!(cpu.step(), 7);
+
+    // holding the line doesn't fire again
+    cpu.set_nmi_line(true);
+    assert_eq!(cpu.step(), 2);
+    assert_eq!(cpu.registers.pc, 0x0701);
+
+    // ...but releasing and reasserting does, even with interrupts disabled
+    cpu.set_nmi_line(false);
+    cpu.set_nmi_line(true);
+    assert_eq!(cpu.step(), 7);
+    assert_eq!(cpu.registers.pc, 0x0700);
+    assert_eq!(cpu.registers.sp, 0xf7);
+}
+
+#[test]
+fn irq_respects_interrupt_disable() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str(&mut cpu, "78 ea 58 ea");
+    cpu.write_bytes_to(&IRQ_INTERRUPT_ADDR_START.into(), &[0x00, 0x07]);
+
+    cpu.start();
+
+    // sei
+    cpu.step();
+
+    cpu.set_irq_line(true);
+
+    // masked, so the nop runs
+    cpu.step();
+    assert_eq!(cpu.registers.pc, 0x0602);
+
+    // cli
+    cpu.step();
+
+    assert_eq!(cpu.step(), 7);
+    assert_eq!(cpu.registers.pc, 0x0700);
+    assert_eq!(cpu.read_u8_at(&0x01fdu16.into()), 0x06);
+    assert_eq!(cpu.read_u8_at(&0x01fcu16.into()), 0x03);
+    assert_eq!(cpu.read_u8_at(&0x01fbu16.into()), 0b00100000);
+}
+
+#[test]
+fn irq_is_level_triggered() {
+    let mut cpu = DefaultCpu::new(true);
+
+    load_program_str(&mut cpu, "ea ea");
+    cpu.write_bytes_to(&IRQ_INTERRUPT_ADDR_START.into(), &[0x00, 0x07]);
+
+    // handler just returns without acknowledging the irq
+    cpu.write_bytes_to(&0x0700u16.into(), &to_bytes("40"));
+
+    cpu.start();
+    cpu.set_irq_line(true);
+
+    assert_eq!(cpu.step(), 7);
+
+    // rti
+    cpu.step();
+    assert_eq!(cpu.registers.pc, 0x0600);
+    assert_eq!(cpu.registers.sp, 0xfd);
+    assert_eq!(cpu.registers.p.interrupt_disable, false);
+
+    // still asserted, so it fires again
+    assert_eq!(cpu.step(), 7);
+    assert_eq!(cpu.registers.pc, 0x0700);
+
+    cpu.step();
+    cpu.set_irq_line(false);
+
+    assert_eq!(cpu.step(), 2);
+    assert_eq!(cpu.registers.pc, 0x0601);
+}
+
 #[test]
 #[ignore]
 fn snake() {
@@ -3,7 +3,9 @@ extern crate byteorder;
 #[macro_use]
 extern crate bitflags;
 
+pub mod apu;
 mod bits;
+pub mod bus;
 pub mod cart;
 pub mod cpu;
 pub mod nes;
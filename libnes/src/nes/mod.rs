@@ -1,9 +1,17 @@
-use crate::cpu::mem::CpuMemoryAccessEvent;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::apu::{Apu, DefaultApu};
+use crate::bus::DefaultBus;
+use crate::cart::mappers::Mapper;
 use crate::cpu::Cpu;
 use crate::ppu::Ppu;
+use crate::util::rc_ref;
+
+const PPU_DOTS_PER_CPU_CYCLE: u8 = 3;
+
+const OAM_DMA_CYCLES: u16 = 513;
+const DMC_FETCH_CYCLES: u16 = 4;
 
 pub trait Nes {
     fn start(&mut self) -> ();
@@ -12,11 +20,16 @@ pub trait Nes {
 
     fn get_cpu(&mut self) -> Rc<RefCell<Cpu>>;
     fn get_ppu(&mut self) -> Rc<RefCell<Ppu>>;
+    fn get_apu(&mut self) -> Rc<RefCell<Apu>>;
+
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>);
 }
 
 pub struct DefaultNes {
     cpu: Rc<RefCell<Cpu>>,
     ppu: Rc<RefCell<Ppu>>,
+    apu: Rc<RefCell<Apu>>,
+    cart: Option<Rc<RefCell<Mapper>>>,
 }
 
 impl Nes for DefaultNes {
@@ -28,11 +41,12 @@ impl Nes for DefaultNes {
     fn reset(&mut self) {}
 
     fn tick(&mut self) {
-        for _ in 0..(1_789_773 / 60) {
-            self.cpu.borrow_mut().clock();
-        }
+        // Runs until the ppu finishes the current frame
+        let frame = self.ppu.borrow().get_frame_count();
 
-        self.ppu.borrow_mut().clock();
+        while self.ppu.borrow().get_frame_count() == frame {
+            self.clock();
+        }
     }
 
     fn get_cpu(&mut self) -> Rc<RefCell<Cpu>> {
@@ -42,23 +56,265 @@ impl Nes for DefaultNes {
     fn get_ppu(&mut self) -> Rc<RefCell<Ppu>> {
         self.ppu.clone()
     }
+
+    fn get_apu(&mut self) -> Rc<RefCell<Apu>> {
+        self.apu.clone()
+    }
+
+    // Inserting a cart powers the system back up, with a fresh bus
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>) {
+        let mut bus = DefaultBus::new(self.ppu.clone(), self.apu.clone());
+        bus.insert_cart(cart.clone());
+
+        self.cpu.borrow_mut().load_mem(Box::new(bus));
+        self.ppu.borrow_mut().insert_cart(cart.clone());
+        self.apu.borrow_mut().insert_cart(cart.clone());
+
+        self.cart = Some(cart);
+    }
 }
 
 impl DefaultNes {
-    pub fn new(cpu: Rc<RefCell<Cpu>>, ppu: Rc<RefCell<Ppu>>) -> Self {
-        // Wire Cpu up to Ppu
-        {
-            let cpu = cpu.clone();
-            let ppu = ppu.clone();
-
-            cpu.borrow_mut()
-                .subscribe_mem(Box::from(move |event: &CpuMemoryAccessEvent| {
-                    ppu.borrow_mut().on_cpu_memory_access(event);
-                }));
+    // Runs a single cpu cycle and the ppu dots that go with it
+    fn clock(&mut self) {
+        self.cpu.borrow_mut().clock();
+
+        if let Some(cart) = &self.cart {
+            cart.borrow_mut().cpu_clock();
+        }
+
+        self.apu.borrow_mut().clock();
+
+        let oam_dma = self.ppu.borrow_mut().take_oam_dma();
+        if let Some(page) = oam_dma {
+            self.run_oam_dma(page);
         }
 
-        let nes = DefaultNes { cpu, ppu };
+        let dmc_fetch = self.apu.borrow().pending_dmc_fetch();
+        if let Some(addr) = dmc_fetch {
+            self.run_dmc_fetch(addr);
+        }
+
+        for _ in 0..PPU_DOTS_PER_CPU_CYCLE {
+            self.ppu.borrow_mut().clock();
+        }
+
+        let nmi = self.ppu.borrow().is_nmi_asserted();
+        self.cpu.borrow_mut().set_nmi_line(nmi);
+
+        let cart_irq = match &self.cart {
+            Some(cart) => cart.borrow().irq(),
+            None => false,
+        };
+
+        // /IRQ is wired-or between the apu and the cart
+        let irq = cart_irq || self.apu.borrow().is_irq_asserted();
+        self.cpu.borrow_mut().set_irq_line(irq);
+    }
+
+    // Copies a page of cpu memory into oam, stalling the cpu while it does
+    fn run_oam_dma(&mut self, page: u8) {
+        let mut cpu = self.cpu.borrow_mut();
+
+        let start_addr = (page as u16) << 8;
+        let bytes: Vec<u8> = (0..256)
+            .map(|i| cpu.read_u8_at(&(start_addr + i).into()))
+            .collect();
+
+        self.ppu.borrow_mut().write_oam(&bytes);
+
+        // dma takes an extra cycle to line up when it starts on an odd cycle
+        let alignment_cycles = (cpu.get_cycles() % 2) as u16;
+        cpu.stall(OAM_DMA_CYCLES + alignment_cycles);
+    }
+
+    // The dmc reads its samples over the cpu bus, so the cpu stalls while it does
+    fn run_dmc_fetch(&mut self, addr: u16) {
+        let mut cpu = self.cpu.borrow_mut();
+
+        let val = cpu.read_u8_at(&addr.into());
+        self.apu.borrow_mut().load_dmc_sample(val);
+
+        cpu.stall(DMC_FETCH_CYCLES);
+    }
+
+    pub fn new(cpu: Rc<RefCell<Cpu>>, ppu: Rc<RefCell<Ppu>>) -> Self {
+        let apu: Rc<RefCell<Apu>> = rc_ref(DefaultApu::new());
+
+        // The cpu reaches the rest of the system through the bus
+        let bus = DefaultBus::new(ppu.clone(), apu.clone());
+        cpu.borrow_mut().load_mem(Box::new(bus));
+
+        let nes = DefaultNes {
+            cpu,
+            ppu,
+            apu,
+            cart: None,
+        };
 
         nes
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{DefaultNes, Nes};
+    use crate::bits::to_bytes;
+    use crate::cart::mappers::{MapperOptions, NROMMapper};
+    use crate::cpu::{Cpu, DefaultCpu};
+    use crate::ppu::mem::Mirroring;
+    use crate::ppu::{DefaultPpu, Ppu, OAMADDR, OAMDATA};
+    use crate::util::rc_ref;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Sets up an nes with an nrom cart that runs prog from $8000,
+    // and nmi_handler from $9000
+    fn nes_with_program(
+        prog: &str,
+        nmi_handler: &str,
+    ) -> (DefaultNes, Rc<RefCell<DefaultCpu>>, Rc<RefCell<DefaultPpu>>) {
+        nes_with_program_and_rom(prog, nmi_handler, vec![0; 0x8000])
+    }
+
+    fn nes_with_program_and_rom(
+        prog: &str,
+        nmi_handler: &str,
+        mut prg_rom: Vec<u8>,
+    ) -> (DefaultNes, Rc<RefCell<DefaultCpu>>, Rc<RefCell<DefaultPpu>>) {
+        let prog = to_bytes(prog);
+        prg_rom[..prog.len()].copy_from_slice(&prog);
+
+        let nmi_handler = to_bytes(nmi_handler);
+        prg_rom[0x1000..0x1000 + nmi_handler.len()].copy_from_slice(&nmi_handler);
+
+        // nmi and reset vectors
+        prg_rom[0x7ffa..0x7ffe].copy_from_slice(&[0x00, 0x90, 0x00, 0x80]);
+
+        let cart = NROMMapper::new(&MapperOptions {
+            prg_rom: &prg_rom,
+            chr_rom: &[],
+            prg_ram_size: 0x2000,
+            mirroring: Mirroring::Horizontal,
+            ..Default::default()
+        });
+
+        let cpu = rc_ref(DefaultCpu::new(false));
+        let ppu = rc_ref(DefaultPpu::new());
+
+        let mut nes = DefaultNes::new(cpu.clone(), ppu.clone());
+        nes.insert_cart(rc_ref(cart));
+
+        (nes, cpu, ppu)
+    }
+
+    #[test]
+    fn nmi_on_vblank() {
+        // enable nmi on vblank, then spin, while the nmi handler bumps a counter
+        let (mut nes, cpu, _) = nes_with_program("a9 80 8d 00 20 4c 05 80", "e6 10 40");
+
+        nes.start();
+
+        // the first tick only runs the pre-render line
+        for _ in 0..4 {
+            nes.tick();
+        }
+
+        assert_eq!(cpu.borrow().read_u8_at(&0x0010u16.into()), 3);
+    }
+
+    #[test]
+    fn oam_dma() {
+        // dma page 2 into oam, starting from oamaddr
+        let (mut nes, cpu, ppu) = nes_with_program("a9 04 8d 03 20 a9 02 8d 14 40", "40");
+
+        let page: Vec<u8> = (0..=255).collect();
+        cpu.borrow_mut().write_bytes_to(&0x0200u16.into(), &page);
+
+        nes.start();
+
+        for _ in 0..12 {
+            nes.clock();
+        }
+
+        let mut ppu = ppu.borrow_mut();
+        let mut read_oam = |addr: u8| {
+            ppu.write_register(OAMADDR, addr);
+            ppu.read_register(OAMDATA)
+        };
+
+        assert_eq!(read_oam(0x04), 0x00);
+        assert_eq!(read_oam(0x05), 0x01);
+        assert_eq!(read_oam(0xff), 0xfb);
+        assert_eq!(read_oam(0x00), 0xfc);
+
+        // unimplemented attribute bits are dropped
+        assert_eq!(read_oam(0x0a), 0x02);
+    }
+
+    #[test]
+    fn oam_dma_stalls_cpu() {
+        // clocks until the instruction after the dma has run
+        let clocks_to_next_instr = |prog: &str| {
+            let (mut nes, cpu, _) = nes_with_program(prog, "40");
+
+            cpu.borrow_mut().write_bytes_to(&0x0011u16.into(), &[0x02]);
+
+            nes.start();
+
+            let mut clocks = 0;
+            while cpu.borrow().read_u8_at(&0x0010u16.into()) == 0 {
+                nes.clock();
+                clocks += 1;
+            }
+
+            clocks
+        };
+
+        // lda #$02 (2 cycles), sta $4014 (4 cycles), then dma starts on an even cycle
+        assert_eq!(
+            clocks_to_next_instr("a9 02 8d 14 40 e6 10"),
+            2 + 4 + 513 + 1
+        );
+
+        // lda $11 (3 cycles), sta $4014 (4 cycles), then dma starts on an odd cycle
+        assert_eq!(
+            clocks_to_next_instr("a5 11 8d 14 40 e6 10"),
+            3 + 4 + 514 + 1
+        );
+    }
+
+    #[test]
+    fn dmc_fetch_stalls_cpu() {
+        // clocks until the instruction after the write to SND_CHN has run
+        let clocks_to_next_instr = |prog: &str| {
+            // the dmc's sample lives at $c000
+            let mut prg_rom = vec![0; 0x8000];
+            prg_rom[0x4000] = 0xff;
+
+            let (mut nes, cpu, _) = nes_with_program_and_rom(prog, "40", prg_rom);
+
+            nes.start();
+
+            let mut clocks = 0;
+            while cpu.borrow().read_u8_at(&0x0010u16.into()) == 0 {
+                nes.clock();
+                clocks += 1;
+            }
+
+            (nes, clocks)
+        };
+
+        // sei, dmc irq on, one byte from $c000, then lda #$10, sta $4015 to start it
+        let setup = "78 a9 8f 8d 10 40 a9 00 8d 12 40 8d 13 40";
+
+        let (mut nes, clocks) = clocks_to_next_instr(&format!("{} a9 10 8d 15 40 e6 10", setup));
+        let (_, clocks_without_dmc) =
+            clocks_to_next_instr(&format!("{} a9 00 8d 15 40 e6 10", setup));
+
+        assert_eq!(clocks, clocks_without_dmc + 4);
+
+        // the one byte sample is done, so the dmc raises its irq
+        assert_eq!(nes.get_apu().borrow().is_irq_asserted(), true);
+    }
+}
@@ -0,0 +1,56 @@
+// The ppu's i/o data bus holds the last value driven onto it, with each bit
+// decaying to 0 if it isn't refreshed for a while,
+// see https://wiki.nesdev.com/w/index.php/Open_bus_behavior#PPU_open_bus
+
+// Roughly 600ms
+pub const DECAY_FRAMES: u64 = 36;
+
+#[derive(Default)]
+pub struct IoLatch {
+    value: u8,
+    refreshed_at: [u64; 8],
+}
+
+impl IoLatch {
+    // Drives the bits of val selected by mask onto the bus
+    pub fn drive(&mut self, val: u8, mask: u8, frame: u64) {
+        self.value = (self.value & !mask) | (val & mask);
+
+        for bit in 0..8 {
+            if mask & (1 << bit) != 0 {
+                self.refreshed_at[bit] = frame;
+            }
+        }
+    }
+
+    pub fn read(&mut self, frame: u64) -> u8 {
+        for bit in 0..8 {
+            if frame.saturating_sub(self.refreshed_at[bit]) >= DECAY_FRAMES {
+                self.value &= !(1 << bit);
+            }
+        }
+
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decays() {
+        let mut latch = IoLatch::default();
+
+        latch.drive(0xff, 0xff, 0);
+        latch.drive(0x00, 0x0f, 10);
+        assert_eq!(latch.read(10), 0xf0);
+
+        latch.drive(0xff, 0x03, 20);
+        assert_eq!(latch.read(DECAY_FRAMES - 1), 0xf3);
+
+        // the upper bits were last refreshed at frame 0
+        assert_eq!(latch.read(DECAY_FRAMES), 0x03);
+        assert_eq!(latch.read(DECAY_FRAMES + 20), 0x00);
+    }
+}
@@ -1,6 +1,51 @@
+use crate::cart::mappers::Mapper;
+use std::cell::RefCell;
 use std::ops::Add;
+use std::rc::Rc;
 
 const PPU_MEMORY_MAP_SIZE: u32 = 0x10000u32;
+const NAMETABLE_SIZE: u16 = 0x400;
+
+// The console has 2K of vram for two nametables; four-screen carts add another 2K
+const VRAM_SIZE: usize = NAMETABLE_SIZE as usize * 4;
+
+// How the four nametables at $2000/$2400/$2800/$2c00 map onto vram
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mirroring {
+    // $2000 = $2400, $2800 = $2c00
+    Horizontal,
+    // $2000 = $2800, $2400 = $2c00
+    Vertical,
+    SingleScreenLower,
+    SingleScreenUpper,
+    FourScreen,
+    // Each nametable picks its own 1K page of vram, for boards that switch them individually
+    Mapped([u8; 4]),
+}
+
+impl Default for Mirroring {
+    fn default() -> Self {
+        Mirroring::Horizontal
+    }
+}
+
+impl Mirroring {
+    // Offset into vram of a nametable address
+    pub fn vram_offset(&self, addr: u16) -> usize {
+        let table = (addr - 0x2000) / NAMETABLE_SIZE;
+
+        let vram_table = match self {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical => table & 0x01,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            Mirroring::FourScreen => table,
+            Mirroring::Mapped(pages) => pages[table as usize] as u16,
+        };
+
+        (vram_table * NAMETABLE_SIZE + (addr % NAMETABLE_SIZE)) as usize
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum Address {
@@ -18,7 +63,7 @@ impl From<u16> for Address {
             0x0000...0x2fff => Address::Address(addr),
             0x3000...0x3eff => Address::Mirror {
                 mirror_lo: 0x2000,
-                mirror_hi: 0x2eff,
+                mirror_hi: 0x2fff,
                 addr
             },
             0x3f00...0x3f1f => Address::Address(addr),
@@ -91,7 +136,7 @@ impl Address {
                 mirror_hi,
                 addr,
             } => {
-                let mirror_size = mirror_hi - mirror_lo;
+                let mirror_size = mirror_hi - mirror_lo + 1;
                 let effective_addr = mirror_lo + (addr % mirror_size);
 
                 effective_addr
@@ -115,33 +160,197 @@ impl Address {
     }
 }
 
+// The backdrop entries of the sprite palettes ($3f10/$3f14/$3f18/$3f1c)
+// mirror those of the background palettes
+fn palette_mirror(addr: u16) -> u16 {
+    match addr {
+        0x3f10 | 0x3f14 | 0x3f18 | 0x3f1c => addr - 0x10,
+        _ => addr,
+    }
+}
+
 pub trait PpuMemoryMap {
     fn get(&self, addr: &Address) -> u8;
     fn set(&mut self, addr: &Address, val: u8) -> ();
+
+    fn get_mirroring(&self) -> Mirroring;
+    fn set_mirroring(&mut self, mirroring: Mirroring);
+
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>);
 }
 
 pub struct DefaultPpuMemoryMap {
     memory: Box<[u8; PPU_MEMORY_MAP_SIZE as usize]>,
+    vram: [u8; VRAM_SIZE],
+    mirroring: Mirroring,
+
+    // Once a cart is inserted it owns the pattern tables and picks the mirroring
+    cart: Option<Rc<RefCell<Mapper>>>,
 }
 
 impl PpuMemoryMap for DefaultPpuMemoryMap {
     fn get(&self, addr: &Address) -> u8 {
-        let effective_addr = addr.get_addr();
+        let effective_addr = palette_mirror(addr.get_addr());
+
+        match (effective_addr, &self.cart) {
+            (0x0000...0x1fff, Some(cart)) => cart.borrow_mut().ppu_read(effective_addr),
+            (0x2000...0x2fff, Some(cart)) => {
+                let val = cart.borrow_mut().nametable_read(effective_addr);
 
-        self.memory[effective_addr as usize]
+                val.unwrap_or_else(|| self.vram[self.get_mirroring().vram_offset(effective_addr)])
+            }
+            (0x2000...0x2fff, None) => self.vram[self.get_mirroring().vram_offset(effective_addr)],
+            _ => self.memory[effective_addr as usize],
+        }
     }
 
     fn set(&mut self, addr: &Address, val: u8) -> () {
-        let effective_addr = addr.get_addr();
+        let effective_addr = palette_mirror(addr.get_addr());
 
-        self.memory[effective_addr as usize] = val;
+        match (effective_addr, &self.cart) {
+            (0x0000...0x1fff, Some(cart)) => cart.borrow_mut().ppu_write(effective_addr, val),
+            (0x2000...0x2fff, _) => {
+                let is_handled = match &self.cart {
+                    Some(cart) => cart.borrow_mut().nametable_write(effective_addr, val),
+                    None => false,
+                };
+
+                if !is_handled {
+                    let offset = self.get_mirroring().vram_offset(effective_addr);
+                    self.vram[offset] = val;
+                }
+            }
+            _ => self.memory[effective_addr as usize] = val,
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        match &self.cart {
+            Some(cart) => cart.borrow().mirroring(),
+            None => self.mirroring,
+        }
+    }
+
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>) {
+        self.cart = Some(cart);
     }
 }
 
 impl DefaultPpuMemoryMap {
     pub fn new() -> Self {
         DefaultPpuMemoryMap {
-            memory: Box::from([0u8; PPU_MEMORY_MAP_SIZE as usize])
+            memory: Box::from([0u8; PPU_MEMORY_MAP_SIZE as usize]),
+            vram: [0u8; VRAM_SIZE],
+            mirroring: Mirroring::Horizontal,
+            cart: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cart::mappers::{MapperOptions, NROMMapper};
+    use crate::util::rc_ref;
+
+    #[test]
+    fn mirrors() {
+        let mut mem = DefaultPpuMemoryMap::new();
+
+        mem.set(&0x2123u16.into(), 0x01);
+        assert_eq!(mem.get(&0x3123u16.into()), 0x01);
+
+        mem.set(&0x3f05u16.into(), 0x02);
+        assert_eq!(mem.get(&0x3f25u16.into()), 0x02);
+        assert_eq!(mem.get(&0x3fe5u16.into()), 0x02);
+    }
+
+    #[test]
+    fn nametable_mirroring() {
+        let tables_with = |mirroring: Mirroring| {
+            let mut mem = DefaultPpuMemoryMap::new();
+            mem.set_mirroring(mirroring);
+
+            for (i, addr) in [0x2000u16, 0x2400, 0x2800, 0x2c00].iter().enumerate() {
+                mem.set(&(addr + 0x123).into(), i as u8 + 1);
+            }
+
+            [0x2000u16, 0x2400, 0x2800, 0x2c00]
+                .iter()
+                .map(|addr| mem.get(&(addr + 0x123).into()))
+                .collect::<Vec<u8>>()
+        };
+
+        assert_eq!(tables_with(Mirroring::Horizontal), vec![2, 2, 4, 4]);
+        assert_eq!(tables_with(Mirroring::Vertical), vec![3, 4, 3, 4]);
+        assert_eq!(tables_with(Mirroring::SingleScreenLower), vec![4, 4, 4, 4]);
+        assert_eq!(tables_with(Mirroring::SingleScreenUpper), vec![4, 4, 4, 4]);
+        assert_eq!(tables_with(Mirroring::FourScreen), vec![1, 2, 3, 4]);
+        assert_eq!(tables_with(Mirroring::Mapped([0, 1, 1, 0])), vec![4, 3, 3, 4]);
+    }
+
+    #[test]
+    fn switching_mirroring() {
+        let mut mem = DefaultPpuMemoryMap::new();
+
+        mem.set_mirroring(Mirroring::SingleScreenLower);
+        mem.set(&0x2000u16.into(), 0x01);
+
+        mem.set_mirroring(Mirroring::SingleScreenUpper);
+        mem.set(&0x2000u16.into(), 0x02);
+
+        // the two screens are separate vram, and stay around when switching between them
+        assert_eq!(mem.get(&0x2c00u16.into()), 0x02);
+
+        mem.set_mirroring(Mirroring::SingleScreenLower);
+        assert_eq!(mem.get(&0x2c00u16.into()), 0x01);
+
+        mem.set_mirroring(Mirroring::Vertical);
+        assert_eq!(mem.get(&0x2800u16.into()), 0x01);
+        assert_eq!(mem.get(&0x2c00u16.into()), 0x02);
+    }
+
+    #[test]
+    fn palette_backdrop_mirrors() {
+        let mut mem = DefaultPpuMemoryMap::new();
+
+        for (i, addr) in [0x3f10u16, 0x3f14, 0x3f18, 0x3f1c].iter().enumerate() {
+            mem.set(&(*addr).into(), i as u8 + 1);
+            assert_eq!(mem.get(&(addr - 0x10).into()), i as u8 + 1);
         }
+
+        mem.set(&0x3f08u16.into(), 0x05);
+        assert_eq!(mem.get(&0x3f38u16.into()), 0x05);
+
+        // the other sprite palette entries are separate
+        mem.set(&0x3f11u16.into(), 0x06);
+        assert_eq!(mem.get(&0x3f01u16.into()), 0x00);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn cart() {
+        let mut mem = DefaultPpuMemoryMap::new();
+
+        let mut chr_rom = vec![0; 0x2000];
+        chr_rom[0x1234] = 0x56;
+
+        mem.insert_cart(rc_ref(NROMMapper::new(&MapperOptions {
+            prg_rom: &[0; 0x4000],
+            chr_rom: &chr_rom,
+            prg_ram_size: 0,
+            mirroring: Mirroring::Vertical,
+            ..Default::default()
+        })));
+
+        assert_eq!(mem.get(&0x1234u16.into()), 0x56);
+
+        // the cart's mirroring wins
+        mem.set_mirroring(Mirroring::Horizontal);
+        mem.set(&0x2000u16.into(), 0x01);
+        assert_eq!(mem.get(&0x2800u16.into()), 0x01);
+    }
+}
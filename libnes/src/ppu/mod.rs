@@ -1,21 +1,33 @@
 pub mod attr_table;
+pub mod io_latch;
 pub mod mem;
 pub mod nametable;
+pub mod palette;
 pub mod pattern_table;
 pub mod registers;
+pub mod render;
+pub mod scroll;
+pub mod sprites;
 pub mod tiles;
 
-use crate::bits::u16_from_u8s;
-use crate::cpu::mem::CpuMemoryAccessEvent;
+use crate::cart::mappers::Mapper;
 use crate::util::rc_ref;
 use std::cell::RefCell;
 use std::clone::Clone;
 use std::rc::Rc;
 
 use attr_table::*;
-use mem::{Address, DefaultPpuMemoryMap, PpuMemoryMap};
+use io_latch::IoLatch;
+use mem::{Address, DefaultPpuMemoryMap, Mirroring, PpuMemoryMap};
 use nametable::*;
+use palette::{Rgb, SystemPalette};
 use registers::*;
+use render::*;
+use scroll::ScrollRegisters;
+use sprites::SpritePipeline;
+
+#[cfg(test)]
+mod tests;
 
 pub const PATTERN_TABLE_ONE_START_ADDR: u16 = 0x0000;
 pub const PATTERN_TABLE_TWO_START_ADDR: u16 = 0x1000;
@@ -35,79 +47,158 @@ pub trait Ppu {
     fn start(&mut self);
     fn clock(&mut self);
 
+    fn get_framebuffer(&self) -> &[Rgb];
+    fn set_palette(&mut self, palette: SystemPalette);
+    fn get_frame_count(&self) -> u64;
+
+    // State of the ppu's /NMI output (asserted while in vblank with nmi generation enabled)
+    fn is_nmi_asserted(&self) -> bool;
+
     fn get_pattern_tables(&self) -> [Rc<RefCell<PatternTable>>; 2];
     fn get_active_pattern_table(&self) -> Rc<RefCell<PatternTable>>;
 
     fn get_nametable(&self, table_index: u8) -> NameTable;
+    fn set_mirroring(&mut self, mirroring: Mirroring);
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>);
     fn get_active_nametable(&self) -> NameTable;
 
     fn write_bytes_to(&mut self, start_addr: &Address, bytes: &[u8]);
     fn read_bytes(&self, start_addr: &Address, num_bytes: u16) -> Vec<u8>;
 
-    fn on_cpu_memory_access(&mut self, event: &CpuMemoryAccessEvent);
+    // Reads/writes one of the registers at PPUCTRL..=PPUDATA
+    fn read_register(&mut self, addr: u16) -> u8;
+    fn write_register(&mut self, addr: u16, val: u8);
+
+    // Records the cpu page written to OAMDMA
+    fn request_oam_dma(&mut self, page: u8);
+    // Takes the cpu page written to OAMDMA since the last call, if any
+    fn take_oam_dma(&mut self) -> Option<u8>;
+    // Writes bytes to oam the same way OAMDATA does
+    fn write_oam(&mut self, bytes: &[u8]);
 }
 
 pub struct DefaultPpu {
-    vram_addr: u16,
-    oamaddr: u16,
+    oamaddr: u8,
+    pending_oam_dma: Option<u8>,
     ppu_ctrl: PpuCtrlRegister,
-    pending_ppuaddr_hi: Option<u8>,
+    ppu_mask: PpuMaskRegister,
+    ppu_status: PpuStatusRegister,
+    scroll: ScrollRegisters,
+    read_buffer: u8,
+    io_latch: IoLatch,
     mem: Box<PpuMemoryMap>,
+
+    scanline: u16,
+    dot: u16,
+    frame: u64,
+    bg: BackgroundPipeline,
+    sprites: SpritePipeline,
+    palette: SystemPalette,
+    framebuffer: Box<[Rgb; SCREEN_WIDTH * SCREEN_HEIGHT]>,
 }
 
 impl Ppu for DefaultPpu {
     fn start(&mut self) {
-        // TODO: impl
+        self.scanline = PRE_RENDER_SCANLINE;
+        self.dot = 0;
+        self.frame = 0;
     }
 
     fn clock(&mut self) {
-        // TODO: impl
+        self.clock_pipeline();
+        self.clock_sprites();
+
+        match (self.scanline, self.dot) {
+            (VBLANK_SCANLINE, 1) => self.ppu_status.vblank = true,
+            (PRE_RENDER_SCANLINE, 1) => {
+                self.ppu_status.vblank = false;
+                self.ppu_status.sprite_zero_hit = false;
+                self.ppu_status.sprite_overflow = false;
+            }
+            _ => {}
+        }
+
+        self.advance_dot();
+    }
+
+    fn get_framebuffer(&self) -> &[Rgb] {
+        &self.framebuffer[..]
     }
 
-    fn on_cpu_memory_access(&mut self, event: &CpuMemoryAccessEvent) {
-        match event {
-            CpuMemoryAccessEvent::Get(addr, _) => {
-                let raw_addr: u16 = addr.into();
+    fn set_palette(&mut self, palette: SystemPalette) {
+        self.palette = palette;
+    }
+
+    fn get_frame_count(&self) -> u64 {
+        self.frame
+    }
 
-                match raw_addr {
-                    PPUSTATUS => {}
-                    OAMDATA => {}
-                    PPUDATA => {},
-                    _ => {}
-                }
+    fn is_nmi_asserted(&self) -> bool {
+        self.ppu_status.vblank && self.ppu_ctrl.gen_nmi
+    }
+
+    fn read_register(&mut self, addr: u16) -> u8 {
+        match addr {
+            PPUSTATUS => {
+                let status = (&self.ppu_status).into();
+
+                self.ppu_status.vblank = false;
+                self.scroll.reset_latch();
+
+                // status only drives the top 3 bits
+                self.drive_io_latch(status, 0xe0)
             }
-            CpuMemoryAccessEvent::Set(addr, val) => {
-                let raw_addr: u16 = addr.into();
-
-                match raw_addr {
-                    PPUCTRL => {
-                        self.ppu_ctrl = (*val).into();
-                    }
-                    PPUMASK => {}
-                    PPUADDR => match self.pending_ppuaddr_hi {
-                        Some(addr_hi) => {
-                            self.vram_addr = u16_from_u8s(*val, addr_hi);
-
-                            self.pending_ppuaddr_hi = None;
-                        }
-                        None => self.pending_ppuaddr_hi = Some(*val),
-                    },
-                    PPUDATA => {
-                        // write to vram addr
-                        self.mem.set(&self.vram_addr.into(), *val);
-
-                        // increment by ppuctrl vram incr val
-                        self.vram_addr += self.ppu_ctrl.vram_addr_incr;
-                    }
-                    OAMADDR => {
-                        self.oamaddr = raw_addr;
-                    }
-                    OAMDATA => {}
-                    OAMDMA => {}
-                    PPUSCROLL => {}
-                    _ => {}
-                }
+            OAMDATA => {
+                let val = self.sprites.oam[self.oamaddr as usize];
+
+                self.drive_io_latch(val, 0xff)
             }
+            PPUDATA => self.read_ppudata(),
+            // write-only registers read back whatever is on the bus
+            _ => self.io_latch.read(self.frame),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, val: u8) {
+        self.io_latch.drive(val, 0xff, self.frame);
+
+        match addr {
+            PPUCTRL => {
+                self.ppu_ctrl = val.into();
+                self.scroll.write_ctrl(val);
+            }
+            PPUMASK => {
+                self.ppu_mask = val.into();
+            }
+            PPUADDR => self.scroll.write_addr(val),
+            PPUDATA => {
+                // write to vram addr
+                self.mem.set(&self.scroll.addr().into(), val);
+
+                // increment by ppuctrl vram incr val
+                self.scroll.increment_addr(self.ppu_ctrl.vram_addr_incr);
+            }
+            OAMADDR => {
+                self.oamaddr = val;
+            }
+            OAMDATA => self.write_oam(&[val]),
+            PPUSCROLL => self.scroll.write_scroll(val),
+            _ => {}
+        }
+    }
+
+    fn request_oam_dma(&mut self, page: u8) {
+        self.pending_oam_dma = Some(page);
+    }
+
+    fn take_oam_dma(&mut self) -> Option<u8> {
+        self.pending_oam_dma.take()
+    }
+
+    fn write_oam(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.sprites.write_oam(self.oamaddr, *byte);
+            self.oamaddr = self.oamaddr.wrapping_add(1);
         }
     }
 
@@ -142,6 +233,14 @@ impl Ppu for DefaultPpu {
         self.get_nametable(self.ppu_ctrl.nametable_index)
     }
 
+    fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mem.set_mirroring(mirroring);
+    }
+
+    fn insert_cart(&mut self, cart: Rc<RefCell<Mapper>>) {
+        self.mem.insert_cart(cart);
+    }
+
     fn write_bytes_to(&mut self, start_addr: &Address, bytes: &[u8]) {
         let raw_start_addr: u16 = start_addr.into();
 
@@ -170,13 +269,55 @@ impl DefaultPpu {
     pub fn new() -> Self {
         DefaultPpu {
             mem: Box::from(DefaultPpuMemoryMap::new()),
-            pending_ppuaddr_hi: None,
-            vram_addr: 0x0000,
-            oamaddr: 0x0000,
+            oamaddr: 0x00,
+            pending_oam_dma: None,
             ppu_ctrl: PpuCtrlRegister::default(),
+            ppu_mask: PpuMaskRegister::default(),
+            ppu_status: PpuStatusRegister::default(),
+            scroll: ScrollRegisters::default(),
+            read_buffer: 0x00,
+            io_latch: IoLatch::default(),
+            scanline: PRE_RENDER_SCANLINE,
+            dot: 0,
+            frame: 0,
+            bg: BackgroundPipeline::default(),
+            sprites: SpritePipeline::default(),
+            palette: SystemPalette::default(),
+            framebuffer: Box::from([Rgb::default(); SCREEN_WIDTH * SCREEN_HEIGHT]),
         }
     }
 
+    // Drives the masked bits of val onto the io bus, and returns what the cpu sees
+    fn drive_io_latch(&mut self, val: u8, mask: u8) -> u8 {
+        self.io_latch.drive(val, mask, self.frame);
+        self.io_latch.read(self.frame)
+    }
+
+    // Reads go through a one byte buffer, except for palette ram which is
+    // returned immediately (while the buffer picks up the nametable byte underneath it)
+    fn read_ppudata(&mut self) -> u8 {
+        let addr = self.scroll.addr();
+
+        let val = match addr >= PALETTE_RAM_START_ADDR {
+            true => {
+                self.read_buffer = self.mem.get(&(addr - 0x1000).into());
+
+                let color = self.mem.get(&addr.into()) & 0x3f;
+                self.drive_io_latch(color, 0x3f)
+            }
+            false => {
+                let val = self.read_buffer;
+                self.read_buffer = self.mem.get(&addr.into());
+
+                self.drive_io_latch(val, 0xff)
+            }
+        };
+
+        self.scroll.increment_addr(self.ppu_ctrl.vram_addr_incr);
+
+        val
+    }
+
     pub fn read_pattern_table_at(&self, start_addr: u16) -> Rc<RefCell<PatternTable>> {
         let mut table = PatternTable::new();
         let mut tile_index = 0u8;
@@ -0,0 +1,157 @@
+pub const NUM_COLORS: usize = 64;
+
+// .pal files hold either one set of colors or one per combination of the three emphasis bits
+pub const PAL_FILE_SIZE: usize = NUM_COLORS * 3;
+pub const EMPHASIS_PAL_FILE_SIZE: usize = PAL_FILE_SIZE * 8;
+
+// Emphasizing a channel darkens the other two by roughly this much
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+// 2C02 NTSC palette in .pal layout (64 rgb triples),
+// see https://wiki.nesdev.com/w/index.php/PPU_palettes
+#[rustfmt::skip]
+const NTSC_PALETTE: [u8; NUM_COLORS * 3] = [
+    84, 84, 84,    0, 30, 116,    8, 16, 144,    48, 0, 136,    68, 0, 100,    92, 0, 48,     84, 4, 0,      60, 24, 0,
+    32, 42, 0,     8, 58, 0,      0, 64, 0,      0, 60, 0,      0, 50, 60,     0, 0, 0,       0, 0, 0,       0, 0, 0,
+    152, 150, 152, 8, 76, 196,    48, 50, 236,   92, 30, 228,   136, 20, 176,  160, 20, 100,  152, 34, 32,   120, 60, 0,
+    84, 90, 0,     40, 114, 0,    8, 124, 0,     0, 118, 40,    0, 102, 120,   0, 0, 0,       0, 0, 0,       0, 0, 0,
+    236, 238, 236, 76, 154, 236,  120, 124, 236, 176, 98, 236,  228, 84, 236,  236, 88, 180,  236, 106, 100, 212, 136, 32,
+    160, 170, 0,   116, 196, 0,   76, 208, 32,   56, 204, 108,  56, 180, 204,  60, 60, 60,    0, 0, 0,       0, 0, 0,
+    236, 238, 236, 168, 204, 236, 188, 188, 236, 212, 178, 236, 236, 174, 236, 236, 174, 212, 236, 180, 176, 228, 196, 144,
+    204, 210, 120, 180, 222, 120, 168, 226, 144, 152, 226, 180, 160, 214, 228, 160, 162, 160, 0, 0, 0,       0, 0, 0,
+];
+
+// Maps the ppu's 6-bit color values (and optionally emphasis bits) to rgb
+pub struct SystemPalette {
+    colors: Vec<Rgb>,
+}
+
+impl Default for SystemPalette {
+    fn default() -> Self {
+        SystemPalette::from_rgb_bytes(&NTSC_PALETTE)
+    }
+}
+
+impl SystemPalette {
+    // Parses a 192 or 1536 byte .pal file
+    pub fn from_pal(bytes: &[u8]) -> Result<Self, String> {
+        match bytes.len() {
+            PAL_FILE_SIZE | EMPHASIS_PAL_FILE_SIZE => Ok(SystemPalette::from_rgb_bytes(bytes)),
+            len => Err(format!(
+                "Expected a palette of {} or {} bytes, but got {}",
+                PAL_FILE_SIZE, EMPHASIS_PAL_FILE_SIZE, len
+            )),
+        }
+    }
+
+    fn from_rgb_bytes(bytes: &[u8]) -> Self {
+        let colors = bytes
+            .chunks(3)
+            .map(|rgb| Rgb::new(rgb[0], rgb[1], rgb[2]))
+            .collect();
+
+        SystemPalette { colors }
+    }
+
+    pub fn has_emphasis(&self) -> bool {
+        self.colors.len() > NUM_COLORS
+    }
+
+    // Emphasis is the 3-bit (blue, green, red) emphasis value from ppumask;
+    // palettes without emphasis colors approximate it
+    pub fn get_rgb(&self, color: u8, emphasis: u8) -> Rgb {
+        let color = (color & 0x3f) as usize;
+        let emphasis = emphasis & 0x07;
+
+        match self.has_emphasis() {
+            true => self.colors[emphasis as usize * NUM_COLORS + color],
+            false => emphasize(self.colors[color], emphasis),
+        }
+    }
+}
+
+fn emphasize(rgb: Rgb, emphasis: u8) -> Rgb {
+    if emphasis == 0 {
+        return rgb;
+    }
+
+    // a channel is darkened when any of the others are emphasized
+    let attenuate = |channel: u8, bit: u8| match emphasis & !bit {
+        0 => channel,
+        _ => (channel as f32 * EMPHASIS_ATTENUATION) as u8,
+    };
+
+    Rgb::new(
+        attenuate(rgb.r, 0x01),
+        attenuate(rgb.g, 0x02),
+        attenuate(rgb.b, 0x04),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn get_rgb() {
+        let palette = SystemPalette::default();
+
+        assert_eq!(palette.get_rgb(0x00, 0), Rgb::new(84, 84, 84));
+        assert_eq!(palette.get_rgb(0x16, 0), Rgb::new(152, 34, 32));
+        assert_eq!(palette.get_rgb(0x30, 0), Rgb::new(236, 238, 236));
+
+        // only the low 6 bits select a color
+        assert_eq!(palette.get_rgb(0x56, 0), palette.get_rgb(0x16, 0));
+    }
+
+    #[test]
+    fn from_pal() {
+        let bytes: Vec<u8> = (0..PAL_FILE_SIZE).map(|i| i as u8).collect();
+        let palette = SystemPalette::from_pal(&bytes).unwrap();
+
+        assert_eq!(palette.has_emphasis(), false);
+        assert_eq!(palette.get_rgb(0x01, 0), Rgb::new(3, 4, 5));
+    }
+
+    #[test]
+    fn approximates_emphasis() {
+        let palette = SystemPalette::default();
+
+        // 0x30 is (236, 238, 236)
+        assert_eq!(palette.get_rgb(0x30, 0x01), Rgb::new(236, 194, 192));
+        assert_eq!(palette.get_rgb(0x30, 0x02), Rgb::new(192, 238, 192));
+        assert_eq!(palette.get_rgb(0x30, 0x07), Rgb::new(192, 194, 192));
+    }
+
+    #[test]
+    fn from_emphasis_pal() {
+        let bytes: Vec<u8> = (0..EMPHASIS_PAL_FILE_SIZE)
+            .map(|i| (i / PAL_FILE_SIZE) as u8)
+            .collect();
+        let palette = SystemPalette::from_pal(&bytes).unwrap();
+
+        assert_eq!(palette.has_emphasis(), true);
+        assert_eq!(palette.get_rgb(0x3f, 0), Rgb::new(0, 0, 0));
+        assert_eq!(palette.get_rgb(0x00, 0x05), Rgb::new(5, 5, 5));
+        assert_eq!(palette.get_rgb(0x3f, 0x07), Rgb::new(7, 7, 7));
+    }
+
+    #[test]
+    fn from_pal_bad_size() {
+        assert!(SystemPalette::from_pal(&[0; 191]).is_err());
+        assert!(SystemPalette::from_pal(&[0; 1535]).is_err());
+    }
+}
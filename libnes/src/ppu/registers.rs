@@ -1,8 +1,7 @@
 use crate::bits::get_bit_val;
 use crate::bits::get_bit_val_u8;
+use crate::bits::set_bit_val;
 
-// TODO: actually write what the default state of the reg should be
-#[derive(Default)]
 pub struct PpuCtrlRegister {
     pub nametable_index: u8,
     pub vram_addr_incr: u16,
@@ -13,6 +12,13 @@ pub struct PpuCtrlRegister {
     pub gen_nmi: bool,
 }
 
+// ppuctrl is cleared at power on
+impl Default for PpuCtrlRegister {
+    fn default() -> Self {
+        PpuCtrlRegister::from(0x00)
+    }
+}
+
 impl From<u8> for PpuCtrlRegister {
     fn from(byte: u8) -> Self {
         PpuCtrlRegister {
@@ -20,7 +26,7 @@ impl From<u8> for PpuCtrlRegister {
                 let lo = get_bit_val_u8(byte, 0);
                 let hi = get_bit_val_u8(byte, 1);
 
-                lo & (hi << 1)
+                lo | (hi << 1)
             },
             vram_addr_incr: match get_bit_val_u8(byte, 2) {
                 0 => 1,
@@ -41,3 +47,68 @@ impl From<u8> for PpuCtrlRegister {
         }
     }
 }
+
+#[derive(Default)]
+pub struct PpuMaskRegister {
+    pub greyscale: bool,
+    pub show_bg_left: bool,
+    pub show_sprites_left: bool,
+    pub show_bg: bool,
+    pub show_sprites: bool,
+    pub emphasize_red: bool,
+    pub emphasize_green: bool,
+    pub emphasize_blue: bool,
+}
+
+impl From<u8> for PpuMaskRegister {
+    fn from(byte: u8) -> Self {
+        PpuMaskRegister {
+            greyscale: get_bit_val(byte, 0),
+            show_bg_left: get_bit_val(byte, 1),
+            show_sprites_left: get_bit_val(byte, 2),
+            show_bg: get_bit_val(byte, 3),
+            show_sprites: get_bit_val(byte, 4),
+            emphasize_red: get_bit_val(byte, 5),
+            emphasize_green: get_bit_val(byte, 6),
+            emphasize_blue: get_bit_val(byte, 7),
+        }
+    }
+}
+
+impl PpuMaskRegister {
+    pub fn is_rendering_enabled(&self) -> bool {
+        self.show_bg || self.show_sprites
+    }
+
+    pub fn shows_bg_at(&self, x: usize) -> bool {
+        self.show_bg && (x >= 8 || self.show_bg_left)
+    }
+
+    pub fn shows_sprites_at(&self, x: usize) -> bool {
+        self.show_sprites && (x >= 8 || self.show_sprites_left)
+    }
+
+    // Emphasis bits as (blue, green, red)
+    pub fn emphasis(&self) -> u8 {
+        let emphasis = set_bit_val(0, 0, self.emphasize_red);
+        let emphasis = set_bit_val(emphasis, 1, self.emphasize_green);
+
+        set_bit_val(emphasis, 2, self.emphasize_blue)
+    }
+}
+
+#[derive(Default)]
+pub struct PpuStatusRegister {
+    pub sprite_overflow: bool,
+    pub sprite_zero_hit: bool,
+    pub vblank: bool,
+}
+
+impl Into<u8> for &PpuStatusRegister {
+    fn into(self) -> u8 {
+        let status = set_bit_val(0, 5, self.sprite_overflow);
+        let status = set_bit_val(status, 6, self.sprite_zero_hit);
+
+        set_bit_val(status, 7, self.vblank)
+    }
+}
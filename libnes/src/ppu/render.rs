@@ -0,0 +1,236 @@
+use super::sprites::SpritePixel;
+use super::DefaultPpu;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+pub const DOTS_PER_SCANLINE: u16 = 341;
+pub const POST_RENDER_SCANLINE: u16 = 240;
+pub const VBLANK_SCANLINE: u16 = 241;
+pub const PRE_RENDER_SCANLINE: u16 = 261;
+
+pub const PALETTE_RAM_START_ADDR: u16 = 0x3f00;
+
+// see https://wiki.nesdev.com/w/index.php/PPU_rendering
+#[derive(Default)]
+pub struct BackgroundPipeline {
+    nametable_latch: u8,
+    attribute_latch: u8,
+    pattern_lo_latch: u8,
+    pattern_hi_latch: u8,
+
+    pattern_lo_shift: u16,
+    pattern_hi_shift: u16,
+    attribute_lo_shift: u16,
+    attribute_hi_shift: u16,
+}
+
+impl BackgroundPipeline {
+    pub fn shift(&mut self) {
+        self.pattern_lo_shift <<= 1;
+        self.pattern_hi_shift <<= 1;
+        self.attribute_lo_shift <<= 1;
+        self.attribute_hi_shift <<= 1;
+    }
+
+    // Moves the latched tile into the low byte of the shift registers
+    pub fn reload(&mut self) {
+        self.pattern_lo_shift = (self.pattern_lo_shift & 0xff00) | self.pattern_lo_latch as u16;
+        self.pattern_hi_shift = (self.pattern_hi_shift & 0xff00) | self.pattern_hi_latch as u16;
+
+        let (attribute_lo, attribute_hi) = match self.attribute_latch {
+            0 => (0x00, 0x00),
+            1 => (0xff, 0x00),
+            2 => (0x00, 0xff),
+            _ => (0xff, 0xff),
+        };
+
+        self.attribute_lo_shift = (self.attribute_lo_shift & 0xff00) | attribute_lo;
+        self.attribute_hi_shift = (self.attribute_hi_shift & 0xff00) | attribute_hi;
+    }
+
+    // Returns the (palette num, pixel value) at the head of the shift registers
+    pub fn pixel(&self, fine_x: u8) -> (u8, u8) {
+        let bit = 15 - fine_x as u16;
+        let get = |shift: u16| ((shift >> bit) & 1) as u8;
+
+        let pixel = get(self.pattern_lo_shift) | (get(self.pattern_hi_shift) << 1);
+        let palette_num = get(self.attribute_lo_shift) | (get(self.attribute_hi_shift) << 1);
+
+        (palette_num, pixel)
+    }
+}
+
+impl DefaultPpu {
+    pub(super) fn clock_pipeline(&mut self) {
+        let scanline = self.scanline;
+        let dot = self.dot;
+
+        let is_visible_line = scanline < POST_RENDER_SCANLINE;
+        let is_pre_render_line = scanline == PRE_RENDER_SCANLINE;
+
+        // with rendering off the ppu stops fetching, and leaves v alone
+        let is_rendering = self.ppu_mask.is_rendering_enabled();
+
+        if is_rendering && (is_visible_line || is_pre_render_line) {
+            if (dot >= 2 && dot <= 257) || (dot >= 322 && dot <= 337) {
+                self.bg.shift();
+            }
+
+            let is_fetch_dot = (dot >= 1 && dot <= 256) || (dot >= 321 && dot <= 336);
+
+            if is_fetch_dot {
+                match (dot - 1) % 8 {
+                    0 => {
+                        if dot >= 9 {
+                            self.bg.reload();
+                        }
+
+                        self.fetch_nametable_byte();
+                    }
+                    2 => self.fetch_attribute_byte(),
+                    4 => self.fetch_pattern_byte(false),
+                    6 => self.fetch_pattern_byte(true),
+                    _ => {}
+                }
+            }
+
+            if dot == 257 || dot == 337 {
+                self.bg.reload();
+            }
+
+            // the two unused nametable fetches at the end of the line, which
+            // some mappers (e.g. mmc5) count scanlines with
+            if dot == 337 || dot == 339 {
+                self.mem.get(&self.nametable_addr().into());
+            }
+
+            // v follows the fetches: the next tile after each pattern fetch,
+            // the next row at the end of the line
+            if is_fetch_dot && dot % 8 == 0 {
+                self.scroll.increment_coarse_x();
+            }
+
+            if dot == 256 {
+                self.scroll.increment_y();
+            }
+
+            if dot == 257 {
+                self.scroll.copy_horizontal();
+            }
+
+            if is_pre_render_line && dot >= 280 && dot <= 304 {
+                self.scroll.copy_vertical();
+            }
+        }
+
+        if is_visible_line && dot >= 1 && dot <= SCREEN_WIDTH as u16 {
+            self.output_pixel();
+        }
+    }
+
+    pub(super) fn advance_dot(&mut self) {
+        self.dot += 1;
+
+        // odd frames skip the last dot of the pre-render line while rendering
+        let is_odd_frame = self.frame % 2 == 1;
+        let skips_dot = is_odd_frame && self.ppu_mask.is_rendering_enabled();
+
+        let last_dot = match self.scanline == PRE_RENDER_SCANLINE && skips_dot {
+            true => DOTS_PER_SCANLINE - 2,
+            false => DOTS_PER_SCANLINE - 1,
+        };
+
+        if self.dot > last_dot {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline > PRE_RENDER_SCANLINE {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+    }
+
+    fn output_pixel(&mut self) {
+        let x = (self.dot - 1) as usize;
+        let y = self.scanline as usize;
+
+        let (bg_palette_num, bg_pixel) = match self.ppu_mask.shows_bg_at(x) {
+            true => self.bg.pixel(self.scroll.fine_x),
+            false => (0, 0),
+        };
+
+        let sprite = match self.ppu_mask.shows_sprites_at(x) {
+            true => self.sprites.pixel(x as u8),
+            false => None,
+        };
+
+        // sprite 0 can hit anywhere both layers are showing except the last column
+        if let Some(ref sprite) = sprite {
+            if sprite.is_sprite_zero && bg_pixel != 0 && x != 255 {
+                self.ppu_status.sprite_zero_hit = true;
+            }
+        }
+
+        // pixel value 0 is transparent and falls through to the universal background color
+        let palette_index = match (bg_pixel, sprite) {
+            (0, None) => 0,
+            (0, Some(sprite)) => sprite_palette_index(&sprite),
+            (_, Some(ref sprite)) if !sprite.behind_bg => sprite_palette_index(sprite),
+            (_, _) => (bg_palette_num << 2) | bg_pixel,
+        };
+
+        let color = self
+            .mem
+            .get(&(PALETTE_RAM_START_ADDR + palette_index as u16).into());
+
+        // greyscale drops the hue, leaving the column of greys
+        let color = match self.ppu_mask.greyscale {
+            true => color & 0x30,
+            false => color,
+        };
+
+        self.framebuffer[y * SCREEN_WIDTH + x] =
+            self.palette.get_rgb(color, self.ppu_mask.emphasis());
+    }
+
+    // The nametable byte of the tile v points at
+    pub(super) fn nametable_addr(&self) -> u16 {
+        0x2000 | (self.scroll.v & 0x0fff)
+    }
+
+    fn fetch_nametable_byte(&mut self) {
+        let addr = self.nametable_addr();
+
+        self.bg.nametable_latch = self.mem.get(&addr.into());
+    }
+
+    fn fetch_attribute_byte(&mut self) {
+        let (coarse_x, coarse_y) = (self.scroll.coarse_x(), self.scroll.coarse_y());
+
+        let addr = 0x23c0
+            | (self.scroll.nametable_select() << 10)
+            | ((coarse_y / 4) << 3)
+            | (coarse_x / 4);
+
+        let shift = ((coarse_y & 0x02) << 1) | (coarse_x & 0x02);
+
+        self.bg.attribute_latch = (self.mem.get(&addr.into()) >> shift) & 0x03;
+    }
+
+    fn fetch_pattern_byte(&mut self, hi_plane: bool) {
+        let table_addr = self.ppu_ctrl.bg_pattern_table_index as u16 * 0x1000;
+        let tile_addr = table_addr + (self.bg.nametable_latch as u16 * 16) + self.scroll.fine_y();
+
+        match hi_plane {
+            true => self.bg.pattern_hi_latch = self.mem.get(&(tile_addr + 8).into()),
+            false => self.bg.pattern_lo_latch = self.mem.get(&tile_addr.into()),
+        }
+    }
+}
+
+// Sprites use the upper four palettes
+fn sprite_palette_index(sprite: &SpritePixel) -> u8 {
+    0x10 | (sprite.palette_num << 2) | sprite.pixel
+}
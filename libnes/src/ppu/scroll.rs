@@ -0,0 +1,231 @@
+// The ppu's internal scroll registers shared by PPUSCROLL and PPUADDR,
+// see https://wiki.nesdev.com/w/index.php/PPU_scrolling
+//
+// v and t are laid out as yyy NN YYYYY XXXXX
+// (fine y, nametable select, coarse y, coarse x)
+#[derive(Default)]
+pub struct ScrollRegisters {
+    // current vram address
+    pub v: u16,
+    // temporary vram address (the top left onscreen tile)
+    pub t: u16,
+    pub fine_x: u8,
+    // first/second write toggle
+    pub w: bool,
+}
+
+const COARSE_X_MASK: u16 = 0x001f;
+const COARSE_Y_MASK: u16 = 0x03e0;
+const NAMETABLE_X_MASK: u16 = 0x0400;
+const NAMETABLE_Y_MASK: u16 = 0x0800;
+const NAMETABLE_MASK: u16 = NAMETABLE_X_MASK | NAMETABLE_Y_MASK;
+const FINE_Y_MASK: u16 = 0x7000;
+
+const HORIZONTAL_MASK: u16 = COARSE_X_MASK | NAMETABLE_X_MASK;
+const VERTICAL_MASK: u16 = COARSE_Y_MASK | NAMETABLE_Y_MASK | FINE_Y_MASK;
+
+impl ScrollRegisters {
+    pub fn write_ctrl(&mut self, val: u8) {
+        self.t = (self.t & !NAMETABLE_MASK) | (((val & 0x03) as u16) << 10);
+    }
+
+    pub fn write_scroll(&mut self, val: u8) {
+        match self.w {
+            false => {
+                self.t = (self.t & !COARSE_X_MASK) | (val >> 3) as u16;
+                self.fine_x = val & 0x07;
+            }
+            true => {
+                self.t = (self.t & !(COARSE_Y_MASK | FINE_Y_MASK))
+                    | (((val & 0x07) as u16) << 12)
+                    | (((val >> 3) as u16) << 5);
+            }
+        }
+
+        self.w = !self.w;
+    }
+
+    pub fn write_addr(&mut self, val: u8) {
+        match self.w {
+            false => {
+                // the top bit of t is cleared along with the high byte
+                self.t = (self.t & 0x00ff) | (((val & 0x3f) as u16) << 8);
+            }
+            true => {
+                self.t = (self.t & 0xff00) | val as u16;
+                self.v = self.t;
+            }
+        }
+
+        self.w = !self.w;
+    }
+
+    pub fn reset_latch(&mut self) {
+        self.w = false;
+    }
+
+    // Address of the current vram access
+    pub fn addr(&self) -> u16 {
+        self.v & 0x3fff
+    }
+
+    pub fn increment_addr(&mut self, incr: u16) {
+        self.v = (self.v + incr) & 0x7fff;
+    }
+
+    pub fn coarse_x(&self) -> u16 {
+        self.v & COARSE_X_MASK
+    }
+
+    pub fn coarse_y(&self) -> u16 {
+        (self.v & COARSE_Y_MASK) >> 5
+    }
+
+    pub fn fine_y(&self) -> u16 {
+        (self.v & FINE_Y_MASK) >> 12
+    }
+
+    pub fn nametable_select(&self) -> u16 {
+        (self.v & NAMETABLE_MASK) >> 10
+    }
+
+    // Moves v to the next tile, wrapping into the horizontally adjacent nametable
+    pub fn increment_coarse_x(&mut self) {
+        match self.coarse_x() {
+            31 => self.v = (self.v & !COARSE_X_MASK) ^ NAMETABLE_X_MASK,
+            _ => self.v += 1,
+        }
+    }
+
+    // Moves v to the next pixel row, wrapping into the vertically adjacent nametable
+    pub fn increment_y(&mut self) {
+        if self.fine_y() < 7 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !FINE_Y_MASK;
+
+        let coarse_y = match self.coarse_y() {
+            29 => {
+                self.v ^= NAMETABLE_Y_MASK;
+                0
+            }
+            // rows 30 and 31 are attribute data and wrap without switching nametables
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+
+        self.v = (self.v & !COARSE_Y_MASK) | (coarse_y << 5);
+    }
+
+    pub fn copy_horizontal(&mut self) {
+        self.v = (self.v & !HORIZONTAL_MASK) | (self.t & HORIZONTAL_MASK);
+    }
+
+    pub fn copy_vertical(&mut self) {
+        self.v = (self.v & !VERTICAL_MASK) | (self.t & VERTICAL_MASK);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scroll_writes() {
+        let mut scroll = ScrollRegisters::default();
+
+        scroll.write_ctrl(0b0000_0011);
+        assert_eq!(scroll.t, 0b0000_1100_0000_0000);
+
+        scroll.write_scroll(0b0111_1101);
+        assert_eq!(scroll.t, 0b0000_1100_0000_1111);
+        assert_eq!(scroll.fine_x, 0b101);
+        assert_eq!(scroll.w, true);
+
+        scroll.write_scroll(0b0101_1110);
+        assert_eq!(scroll.t, 0b0110_1101_0110_1111);
+        assert_eq!(scroll.w, false);
+
+        // v is untouched until the second ppuaddr write
+        assert_eq!(scroll.v, 0);
+    }
+
+    #[test]
+    fn addr_writes() {
+        let mut scroll = ScrollRegisters::default();
+        scroll.t = 0x7fff;
+
+        scroll.write_addr(0xff);
+        assert_eq!(scroll.t, 0x3fff);
+        assert_eq!(scroll.v, 0);
+
+        scroll.write_addr(0x21);
+        assert_eq!(scroll.t, 0x3f21);
+        assert_eq!(scroll.v, 0x3f21);
+    }
+
+    #[test]
+    fn reset_latch() {
+        let mut scroll = ScrollRegisters::default();
+
+        scroll.write_addr(0x21);
+        scroll.reset_latch();
+        scroll.write_addr(0x23);
+        scroll.write_addr(0x45);
+
+        assert_eq!(scroll.v, 0x2345);
+    }
+
+    #[test]
+    fn increment_coarse_x() {
+        let mut scroll = ScrollRegisters::default();
+        scroll.v = 30;
+
+        scroll.increment_coarse_x();
+        assert_eq!(scroll.v, 31);
+
+        scroll.increment_coarse_x();
+        assert_eq!(scroll.v, NAMETABLE_X_MASK);
+
+        scroll.v = NAMETABLE_X_MASK | 31;
+        scroll.increment_coarse_x();
+        assert_eq!(scroll.v, 0);
+    }
+
+    #[test]
+    fn increment_y() {
+        let mut scroll = ScrollRegisters::default();
+
+        scroll.v = 0x6000;
+        scroll.increment_y();
+        assert_eq!(scroll.v, 0x7000);
+
+        // fine y overflows into coarse y
+        scroll.increment_y();
+        assert_eq!(scroll.v, 1 << 5);
+
+        // row 29 wraps into the next nametable
+        scroll.v = FINE_Y_MASK | (29 << 5);
+        scroll.increment_y();
+        assert_eq!(scroll.v, NAMETABLE_Y_MASK);
+
+        // row 31 wraps in place
+        scroll.v = FINE_Y_MASK | (31 << 5);
+        scroll.increment_y();
+        assert_eq!(scroll.v, 0);
+    }
+
+    #[test]
+    fn copies() {
+        let mut scroll = ScrollRegisters::default();
+        scroll.t = 0x7fff;
+
+        scroll.copy_horizontal();
+        assert_eq!(scroll.v, HORIZONTAL_MASK);
+
+        scroll.copy_vertical();
+        assert_eq!(scroll.v, 0x7fff);
+    }
+}
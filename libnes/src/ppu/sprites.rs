@@ -0,0 +1,246 @@
+use super::render::{POST_RENDER_SCANLINE, PRE_RENDER_SCANLINE};
+use super::DefaultPpu;
+
+pub const OAM_SIZE: usize = 256;
+pub const SECONDARY_OAM_SIZE: usize = 32;
+pub const MAX_SPRITES_PER_SCANLINE: usize = 8;
+
+const NUM_SPRITES: usize = OAM_SIZE / 4;
+
+const ATTRIBUTE_PALETTE_MASK: u8 = 0x03;
+const ATTRIBUTE_BEHIND_BG: u8 = 0x20;
+const ATTRIBUTE_FLIP_HORIZONTAL: u8 = 0x40;
+const ATTRIBUTE_FLIP_VERTICAL: u8 = 0x80;
+
+// Unimplemented attribute bits always read back as 0
+const ATTRIBUTE_UNUSED_MASK: u8 = 0x1c;
+
+// A sprite loaded for output on the current scanline
+#[derive(Clone, Copy, Default)]
+pub struct SpriteUnit {
+    pattern_lo: u8,
+    pattern_hi: u8,
+    attributes: u8,
+    x: u8,
+}
+
+// An opaque sprite pixel
+pub struct SpritePixel {
+    pub palette_num: u8,
+    pub pixel: u8,
+    pub behind_bg: bool,
+    pub is_sprite_zero: bool,
+}
+
+// see https://wiki.nesdev.com/w/index.php/PPU_sprite_evaluation
+pub struct SpritePipeline {
+    pub oam: [u8; OAM_SIZE],
+    secondary_oam: [u8; SECONDARY_OAM_SIZE],
+
+    // results of evaluating the next scanline
+    num_found: usize,
+    found_sprite_zero: bool,
+
+    // sprites being output on the current scanline
+    units: [SpriteUnit; MAX_SPRITES_PER_SCANLINE],
+    num_units: usize,
+    has_sprite_zero: bool,
+}
+
+impl Default for SpritePipeline {
+    fn default() -> Self {
+        SpritePipeline {
+            oam: [0; OAM_SIZE],
+            secondary_oam: [0xff; SECONDARY_OAM_SIZE],
+            num_found: 0,
+            found_sprite_zero: false,
+            units: [SpriteUnit::default(); MAX_SPRITES_PER_SCANLINE],
+            num_units: 0,
+            has_sprite_zero: false,
+        }
+    }
+}
+
+impl SpritePipeline {
+    pub fn write_oam(&mut self, addr: u8, val: u8) {
+        let val = match addr % 4 {
+            2 => val & !ATTRIBUTE_UNUSED_MASK,
+            _ => val,
+        };
+
+        self.oam[addr as usize] = val;
+    }
+
+    pub fn clear_secondary_oam(&mut self) {
+        self.secondary_oam = [0xff; SECONDARY_OAM_SIZE];
+        self.num_found = 0;
+        self.found_sprite_zero = false;
+    }
+
+    // Copies the sprites in range of the scanline into secondary oam,
+    // returning whether the sprite overflow flag should be set
+    pub fn evaluate(&mut self, scanline: u16, sprite_height: u16) -> bool {
+        let in_range = |y: u8| scanline >= y as u16 && scanline - (y as u16) < sprite_height;
+
+        let mut n = 0;
+        while n < NUM_SPRITES && self.num_found < MAX_SPRITES_PER_SCANLINE {
+            let sprite = &self.oam[n * 4..n * 4 + 4];
+            let slot = self.num_found * 4;
+
+            self.secondary_oam[slot] = sprite[0];
+
+            if in_range(sprite[0]) {
+                self.secondary_oam[slot..slot + 4].copy_from_slice(sprite);
+                self.num_found += 1;
+
+                if n == 0 {
+                    self.found_sprite_zero = true;
+                }
+            }
+
+            n += 1;
+        }
+
+        // once secondary oam is full the ppu keeps looking for a ninth sprite,
+        // but (in hardware) also increments the byte offset on every miss,
+        // so it checks tile/attribute/x bytes as if they were y coords
+        let mut m = 0;
+        while n < NUM_SPRITES {
+            if in_range(self.oam[n * 4 + m]) {
+                return true;
+            }
+
+            n += 1;
+            m = (m + 1) % 4;
+        }
+
+        false
+    }
+
+    // Returns the first opaque sprite pixel at the given x coord
+    pub fn pixel(&self, x: u8) -> Option<SpritePixel> {
+        for (i, unit) in self.units[..self.num_units].iter().enumerate() {
+            if x < unit.x || x - unit.x >= 8 {
+                continue;
+            }
+
+            let bit = 7 - (x - unit.x);
+            let pixel = ((unit.pattern_lo >> bit) & 1) | (((unit.pattern_hi >> bit) & 1) << 1);
+
+            if pixel == 0 {
+                continue;
+            }
+
+            return Some(SpritePixel {
+                palette_num: unit.attributes & ATTRIBUTE_PALETTE_MASK,
+                pixel,
+                behind_bg: unit.attributes & ATTRIBUTE_BEHIND_BG != 0,
+                is_sprite_zero: i == 0 && self.has_sprite_zero,
+            });
+        }
+
+        None
+    }
+}
+
+impl DefaultPpu {
+    pub(super) fn clock_sprites(&mut self) {
+        let scanline = self.scanline;
+        let dot = self.dot;
+
+        let is_visible_line = scanline < POST_RENDER_SCANLINE;
+        let is_pre_render_line = scanline == PRE_RENDER_SCANLINE;
+
+        if !self.ppu_mask.is_rendering_enabled() || (!is_visible_line && !is_pre_render_line) {
+            return;
+        }
+
+        match dot {
+            1 => self.sprites.clear_secondary_oam(),
+            // sprites are evaluated over dots 65-256, but only the result matters
+            65 if is_visible_line => {
+                if self.sprites.evaluate(scanline, self.sprite_height()) {
+                    self.ppu_status.sprite_overflow = true;
+                }
+            }
+            257...320 => {
+                self.oamaddr = 0;
+
+                // each sprite's pattern fetches follow two unused nametable fetches
+                match (dot - 257) % 8 {
+                    0 | 2 => {
+                        self.mem.get(&self.nametable_addr().into());
+                    }
+                    7 => self.fetch_sprite(((dot - 257) / 8) as usize),
+                    _ => {}
+                }
+
+                if dot == 320 {
+                    self.sprites.num_units = self.sprites.num_found;
+                    self.sprites.has_sprite_zero = self.sprites.found_sprite_zero;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn sprite_height(&self) -> u16 {
+        match self.ppu_ctrl.sprite_size_type {
+            true => 16,
+            false => 8,
+        }
+    }
+
+    fn fetch_sprite(&mut self, slot: usize) {
+        // empty slots still fetch tile $ff, which mappers watching the ppu's
+        // address bus (e.g. mmc3's scanline counter) rely on
+        if slot >= self.sprites.num_found {
+            let tile_addr = self.sprite_tile_addr(0xff, 0);
+
+            self.mem.get(&tile_addr.into());
+            self.mem.get(&(tile_addr + 8).into());
+
+            self.sprites.units[slot] = SpriteUnit::default();
+            return;
+        }
+
+        let sprite = &self.sprites.secondary_oam[slot * 4..slot * 4 + 4];
+        let (y, tile, attributes, x) = (sprite[0], sprite[1], sprite[2], sprite[3]);
+
+        let mut row = self.scanline - y as u16;
+        if attributes & ATTRIBUTE_FLIP_VERTICAL != 0 {
+            row = self.sprite_height() - 1 - row;
+        }
+
+        let tile_addr = self.sprite_tile_addr(tile, row);
+
+        let mut pattern_lo = self.mem.get(&tile_addr.into());
+        let mut pattern_hi = self.mem.get(&(tile_addr + 8).into());
+
+        if attributes & ATTRIBUTE_FLIP_HORIZONTAL != 0 {
+            pattern_lo = pattern_lo.reverse_bits();
+            pattern_hi = pattern_hi.reverse_bits();
+        }
+
+        self.sprites.units[slot] = SpriteUnit {
+            pattern_lo,
+            pattern_hi,
+            attributes,
+            x,
+        };
+    }
+
+    fn sprite_tile_addr(&self, tile: u8, row: u16) -> u16 {
+        // 8x16 sprites take their pattern table from bit 0 of the tile index
+        // and use the next tile for their bottom half
+        match self.sprite_height() {
+            16 => {
+                let table_addr = (tile & 0x01) as u16 * 0x1000;
+                let tile = (tile & 0xfe) as u16 + (row / 8);
+
+                table_addr + tile * 16 + (row % 8)
+            }
+            _ => self.ppu_ctrl.sprite_pattern_table_addr + tile as u16 * 16 + row,
+        }
+    }
+}
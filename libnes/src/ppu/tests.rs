@@ -0,0 +1,752 @@
+use crate::ppu::io_latch::DECAY_FRAMES;
+use crate::ppu::palette::{Rgb, SystemPalette};
+use crate::ppu::render::*;
+use crate::ppu::{
+    DefaultPpu, Ppu, OAMADDR, OAMDATA, PPUADDR, PPUCTRL, PPUDATA, PPUMASK, PPUSCROLL, PPUSTATUS,
+};
+
+#[test]
+fn frame_timing() {
+    let mut ppu = DefaultPpu::new();
+    write_register(&mut ppu, PPUMASK, 0x1e);
+    ppu.start();
+
+    // pre-render line of frame 0 plus the 262 lines of frame 1
+    let dots_to_frame_one = DOTS_PER_SCANLINE as u32;
+    let dots_per_frame = DOTS_PER_SCANLINE as u32 * 262;
+
+    for _ in 0..dots_to_frame_one {
+        ppu.clock();
+    }
+    assert_eq!(ppu.get_frame_count(), 1);
+
+    // odd frames are one dot shorter
+    for _ in 0..dots_per_frame - 1 {
+        ppu.clock();
+    }
+    assert_eq!(ppu.get_frame_count(), 2);
+
+    for _ in 0..dots_per_frame - 1 {
+        ppu.clock();
+    }
+    assert_eq!(ppu.get_frame_count(), 2);
+
+    ppu.clock();
+    assert_eq!(ppu.get_frame_count(), 3);
+}
+
+#[test]
+fn frame_timing_without_rendering() {
+    let mut ppu = DefaultPpu::new();
+    ppu.start();
+
+    let dots_to_frame_one = DOTS_PER_SCANLINE as u32;
+    let dots_per_frame = DOTS_PER_SCANLINE as u32 * 262;
+
+    for _ in 0..dots_to_frame_one {
+        ppu.clock();
+    }
+
+    // odd frames only skip a dot while rendering
+    for _ in 0..dots_per_frame - 1 {
+        ppu.clock();
+    }
+    assert_eq!(ppu.get_frame_count(), 1);
+
+    ppu.clock();
+    assert_eq!(ppu.get_frame_count(), 2);
+}
+
+#[test]
+fn renders_background_tiles() {
+    let mut ppu = DefaultPpu::new();
+
+    // tile 1: left half uses color 1, right half uses color 3
+    ppu.write_bytes_to(&0x0010u16.into(), &[0xff; 8]);
+    ppu.write_bytes_to(&0x0018u16.into(), &[0x0f; 8]);
+
+    // tiles (0, 0) and (2, 2) use tile 1
+    ppu.write_bytes_to(&0x2000u16.into(), &[0x01]);
+    ppu.write_bytes_to(&0x2042u16.into(), &[0x01]);
+
+    // top-left quadrant of the first attribute entry uses palette 0, bottom-right uses palette 1
+    ppu.write_bytes_to(&0x23c0u16.into(), &[0b0100_0000]);
+
+    ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f, 0x16, 0x00, 0x27]);
+    ppu.write_bytes_to(&0x3f04u16.into(), &[0x0f, 0x01, 0x00, 0x21]);
+
+    write_register(&mut ppu, PPUMASK, 0x1e);
+    run_frame(&mut ppu);
+
+    let framebuffer = ppu.get_framebuffer();
+    let pixel = |x: usize, y: usize| framebuffer[y * SCREEN_WIDTH + x];
+
+    for y in 0..8 {
+        assert_eq!(pixel(0, y), rgb(0x16));
+        assert_eq!(pixel(3, y), rgb(0x16));
+        assert_eq!(pixel(4, y), rgb(0x27));
+        assert_eq!(pixel(7, y), rgb(0x27));
+        assert_eq!(pixel(8, y), rgb(0x0f));
+    }
+
+    for y in 16..24 {
+        assert_eq!(pixel(15, y), rgb(0x0f));
+        assert_eq!(pixel(16, y), rgb(0x01));
+        assert_eq!(pixel(20, y), rgb(0x21));
+        assert_eq!(pixel(24, y), rgb(0x0f));
+    }
+}
+
+#[test]
+fn renders_scrolled_background() {
+    let mut ppu = DefaultPpu::new();
+
+    ppu.write_bytes_to(&0x0010u16.into(), &[0xff; 8]);
+    ppu.write_bytes_to(&0x2042u16.into(), &[0x01]);
+    ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f, 0x16]);
+
+    // tile (2, 2) scrolled up and left to (3, 6)
+    write_register(&mut ppu, PPUSCROLL, 13);
+    write_register(&mut ppu, PPUSCROLL, 10);
+
+    write_register(&mut ppu, PPUMASK, 0x1e);
+    run_frame(&mut ppu);
+
+    let framebuffer = ppu.get_framebuffer();
+    let pixel = |x: usize, y: usize| framebuffer[y * SCREEN_WIDTH + x];
+
+    assert_eq!(pixel(3, 5), rgb(0x0f));
+
+    for y in 6..14 {
+        assert_eq!(pixel(2, y), rgb(0x0f));
+        assert_eq!(pixel(3, y), rgb(0x16));
+        assert_eq!(pixel(10, y), rgb(0x16));
+        assert_eq!(pixel(11, y), rgb(0x0f));
+    }
+
+    assert_eq!(pixel(3, 14), rgb(0x0f));
+}
+
+#[test]
+fn renders_sprites() {
+    let mut ppu = DefaultPpu::new();
+
+    // tile 2: the left half of the top row uses color 1
+    ppu.write_bytes_to(&0x0020u16.into(), &[0xf0]);
+
+    ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f]);
+    ppu.write_bytes_to(&0x3f15u16.into(), &[0x16]);
+
+    write_oam(
+        &mut ppu,
+        &[
+            20, 0x02, 0x01, 10, //
+            40, 0x02, 0xc1, 30, // flipped both ways
+        ],
+    );
+
+    write_register(&mut ppu, PPUMASK, 0x1e);
+    run_frame(&mut ppu);
+
+    let framebuffer = ppu.get_framebuffer();
+    let pixel = |x: usize, y: usize| framebuffer[y * SCREEN_WIDTH + x];
+
+    // sprites are drawn a line below their y coord
+    assert_eq!(pixel(10, 20), rgb(0x0f));
+    assert_eq!(pixel(9, 21), rgb(0x0f));
+    assert_eq!(pixel(10, 21), rgb(0x16));
+    assert_eq!(pixel(13, 21), rgb(0x16));
+    assert_eq!(pixel(14, 21), rgb(0x0f));
+    assert_eq!(pixel(10, 22), rgb(0x0f));
+
+    assert_eq!(pixel(34, 41), rgb(0x0f));
+    assert_eq!(pixel(33, 48), rgb(0x0f));
+    assert_eq!(pixel(34, 48), rgb(0x16));
+    assert_eq!(pixel(37, 48), rgb(0x16));
+}
+
+#[test]
+fn renders_tall_sprites() {
+    let mut ppu = DefaultPpu::new();
+
+    // tiles 4 and 5 of the right pattern table: top and bottom rows of the sprite
+    ppu.write_bytes_to(&0x1040u16.into(), &[0xff]);
+    ppu.write_bytes_to(&0x1057u16.into(), &[0xff]);
+
+    ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f]);
+    ppu.write_bytes_to(&0x3f11u16.into(), &[0x16]);
+
+    write_register(&mut ppu, PPUCTRL, 0x20);
+    write_oam(&mut ppu, &[50, 0x05, 0x00, 10, 80, 0x05, 0x80, 10]);
+
+    write_register(&mut ppu, PPUMASK, 0x1e);
+    run_frame(&mut ppu);
+
+    let framebuffer = ppu.get_framebuffer();
+    let pixel = |x: usize, y: usize| framebuffer[y * SCREEN_WIDTH + x];
+
+    assert_eq!(pixel(10, 51), rgb(0x16));
+    assert_eq!(pixel(10, 52), rgb(0x0f));
+    assert_eq!(pixel(10, 58), rgb(0x0f));
+    assert_eq!(pixel(10, 59), rgb(0x0f));
+    assert_eq!(pixel(10, 66), rgb(0x16));
+
+    // vertical flipping swaps the halves
+    assert_eq!(pixel(10, 81), rgb(0x16));
+    assert_eq!(pixel(10, 96), rgb(0x16));
+}
+
+#[test]
+fn sprite_priority() {
+    let mut ppu = DefaultPpu::new();
+
+    // opaque background tiles at (0, 0) and (1, 0)
+    ppu.write_bytes_to(&0x0010u16.into(), &[0xff; 8]);
+    ppu.write_bytes_to(&0x2000u16.into(), &[0x01, 0x01]);
+
+    // opaque sprite tile
+    ppu.write_bytes_to(&0x0020u16.into(), &[0xff; 8]);
+
+    ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f, 0x21]);
+    ppu.write_bytes_to(&0x3f11u16.into(), &[0x2a]);
+    ppu.write_bytes_to(&0x3f15u16.into(), &[0x16]);
+
+    write_oam(
+        &mut ppu,
+        &[
+            // behind the background, and hides the overlapping sprite after it
+            1, 0x02, 0x20, 0, //
+            1, 0x02, 0x01, 0, //
+            // in front of the background
+            1, 0x02, 0x01, 8, //
+            // behind a transparent background pixel
+            1, 0x02, 0x20, 16,
+        ],
+    );
+
+    write_register(&mut ppu, PPUMASK, 0x1e);
+    run_frame(&mut ppu);
+
+    let framebuffer = ppu.get_framebuffer();
+    let pixel = |x: usize, y: usize| framebuffer[y * SCREEN_WIDTH + x];
+
+    assert_eq!(pixel(0, 4), rgb(0x21));
+    assert_eq!(pixel(8, 4), rgb(0x16));
+    assert_eq!(pixel(16, 4), rgb(0x2a));
+}
+
+#[test]
+fn sprite_overflow() {
+    let overflows = |oam: &[u8]| {
+        let mut ppu = DefaultPpu::new();
+        write_register(&mut ppu, PPUMASK, 0x1e);
+        ppu.start();
+
+        write_oam(&mut ppu, oam);
+        run_until(&mut ppu, 101, 0);
+
+        read_register(&mut ppu, PPUSTATUS) & 0x20 != 0
+    };
+
+    let sprite = [100, 0x00, 0x00, 0x00];
+
+    assert_eq!(overflows(&sprite.repeat(8)), false);
+    assert_eq!(overflows(&sprite.repeat(9)), true);
+
+    // after finding eight sprites the ppu misreads the next sprite's tile index as its y coord
+    let mut oam = sprite.repeat(8);
+    oam.extend(&[0xff, 0x00, 0x00, 0x00, 0xff, 100, 0x00, 0x00]);
+
+    assert_eq!(overflows(&oam), true);
+}
+
+#[test]
+fn oam_access() {
+    let mut ppu = DefaultPpu::new();
+
+    write_register(&mut ppu, OAMADDR, 0x02);
+    write_register(&mut ppu, OAMDATA, 0xff);
+    write_register(&mut ppu, OAMDATA, 0x12);
+
+    // reads don't increment oamaddr
+    assert_eq!(read_register(&mut ppu, OAMDATA), 0x00);
+    assert_eq!(read_register(&mut ppu, OAMDATA), 0x00);
+
+    write_register(&mut ppu, OAMADDR, 0x02);
+    assert_eq!(read_register(&mut ppu, OAMDATA), 0xe3);
+
+    write_register(&mut ppu, OAMADDR, 0x03);
+    assert_eq!(read_register(&mut ppu, OAMDATA), 0x12);
+}
+
+#[test]
+fn rendering_disabled() {
+    let mut ppu = DefaultPpu::new();
+
+    ppu.write_bytes_to(&0x0010u16.into(), &[0xff; 8]);
+    ppu.write_bytes_to(&0x2000u16.into(), &[0x01; 960]);
+    ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f, 0x16]);
+
+    write_oam(&mut ppu, &[20, 0x01, 0x00, 20]);
+
+    run_frame(&mut ppu);
+
+    // only the backdrop color is drawn
+    assert!(ppu
+        .get_framebuffer()
+        .iter()
+        .all(|pixel| *pixel == rgb(0x0f)));
+}
+
+#[test]
+fn rendering_disabled_leaves_v() {
+    let writes_to = |mask: u8| {
+        let mut ppu = DefaultPpu::new();
+        write_register(&mut ppu, PPUMASK, mask);
+
+        ppu.start();
+        run_until(&mut ppu, 10, 0);
+
+        write_register(&mut ppu, PPUADDR, 0x21);
+        write_register(&mut ppu, PPUADDR, 0x08);
+
+        run_until(&mut ppu, 20, 0);
+
+        write_register(&mut ppu, PPUDATA, 0xaa);
+
+        ppu.read_bytes(&0x2108u16.into(), 1)[0] == 0xaa
+    };
+
+    assert_eq!(writes_to(0x00), true);
+    assert_eq!(writes_to(0x1e), false);
+}
+
+#[test]
+fn left_clipping() {
+    let pixels_with = |mask: u8| {
+        let mut ppu = DefaultPpu::new();
+
+        // background tile at (0, 0) and a sprite over the left half of tile (1, 0)
+        ppu.write_bytes_to(&0x0010u16.into(), &[0xff; 8]);
+        ppu.write_bytes_to(&0x2000u16.into(), &[0x01, 0x01]);
+
+        ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f, 0x21]);
+        ppu.write_bytes_to(&0x3f11u16.into(), &[0x16]);
+
+        write_oam(&mut ppu, &[1, 0x01, 0x00, 4]);
+
+        write_register(&mut ppu, PPUMASK, mask);
+        run_frame(&mut ppu);
+
+        let framebuffer = ppu.get_framebuffer();
+        let pixel = |x: usize| framebuffer[4 * SCREEN_WIDTH + x];
+
+        (pixel(0), pixel(4), pixel(8))
+    };
+
+    assert_eq!(pixels_with(0x1e), (rgb(0x21), rgb(0x16), rgb(0x16)));
+    assert_eq!(pixels_with(0x1c), (rgb(0x0f), rgb(0x16), rgb(0x16)));
+    assert_eq!(pixels_with(0x1a), (rgb(0x21), rgb(0x21), rgb(0x16)));
+    assert_eq!(pixels_with(0x18), (rgb(0x0f), rgb(0x0f), rgb(0x16)));
+
+    // each layer can be turned off entirely
+    assert_eq!(pixels_with(0x16), (rgb(0x0f), rgb(0x16), rgb(0x16)));
+    assert_eq!(pixels_with(0x0e), (rgb(0x21), rgb(0x21), rgb(0x21)));
+}
+
+#[test]
+fn greyscale_and_emphasis() {
+    let pixel_with = |mask: u8| {
+        let mut ppu = DefaultPpu::new();
+
+        ppu.write_bytes_to(&0x0010u16.into(), &[0xff; 8]);
+        ppu.write_bytes_to(&0x2000u16.into(), &[0x01]);
+        ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f, 0x26]);
+
+        write_register(&mut ppu, PPUMASK, mask);
+        run_frame(&mut ppu);
+
+        ppu.get_framebuffer()[0]
+    };
+
+    let palette = SystemPalette::default();
+
+    assert_eq!(pixel_with(0x1e), palette.get_rgb(0x26, 0));
+    assert_eq!(pixel_with(0x1f), palette.get_rgb(0x20, 0));
+    assert_eq!(pixel_with(0x3e), palette.get_rgb(0x26, 0x01));
+    assert_eq!(pixel_with(0xdf), palette.get_rgb(0x20, 0x06));
+}
+
+// Sprite 0 hit tests, modeled on blargg's sprite_hit_tests
+
+#[test]
+fn sprite_zero_hit_basics() {
+    // opaque sprite over opaque background
+    let mut ppu = sprite_zero_hit_ppu(true);
+    write_oam(&mut ppu, &[20, SOLID_TILE, 0x00, 20]);
+    assert_eq!(sprite_zero_hits(&mut ppu), true);
+
+    // transparent background
+    let mut ppu = sprite_zero_hit_ppu(false);
+    write_oam(&mut ppu, &[20, SOLID_TILE, 0x00, 20]);
+    assert_eq!(sprite_zero_hits(&mut ppu), false);
+
+    // transparent sprite
+    let mut ppu = sprite_zero_hit_ppu(true);
+    write_oam(&mut ppu, &[20, 0x00, 0x00, 20]);
+    assert_eq!(sprite_zero_hits(&mut ppu), false);
+
+    // only sprite 0 can hit
+    let mut ppu = sprite_zero_hit_ppu(true);
+    write_oam(
+        &mut ppu,
+        &[0xff, 0x00, 0x00, 0x00, 20, SOLID_TILE, 0x00, 20],
+    );
+    assert_eq!(sprite_zero_hits(&mut ppu), false);
+
+    // priority doesn't matter
+    let mut ppu = sprite_zero_hit_ppu(true);
+    write_oam(&mut ppu, &[20, SOLID_TILE, 0x20, 20]);
+    assert_eq!(sprite_zero_hits(&mut ppu), true);
+
+    // offscreen
+    let mut ppu = sprite_zero_hit_ppu(true);
+    write_oam(&mut ppu, &[0xf0, SOLID_TILE, 0x00, 20]);
+    assert_eq!(sprite_zero_hits(&mut ppu), false);
+}
+
+#[test]
+fn sprite_zero_hit_flag_lifetime() {
+    let mut ppu = sprite_zero_hit_ppu(true);
+    write_oam(&mut ppu, &[20, SOLID_TILE, 0x00, 20]);
+
+    assert_eq!(sprite_zero_hits(&mut ppu), true);
+
+    // reading ppustatus doesn't clear the flag
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x40, 0x40);
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x40, 0x40);
+
+    // the pre-render line does
+    run_until(&mut ppu, PRE_RENDER_SCANLINE, 1);
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x40, 0x40);
+
+    ppu.clock();
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x40, 0x00);
+}
+
+#[test]
+fn sprite_zero_hit_timing() {
+    let mut ppu = sprite_zero_hit_ppu(true);
+    write_oam(&mut ppu, &[20, CORNER_TILE, 0x00, 30]);
+
+    ppu.start();
+
+    // the pixel at (30, 21) is output on dot 31
+    run_until(&mut ppu, 21, 31);
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x40, 0x00);
+
+    ppu.clock();
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x40, 0x40);
+}
+
+#[test]
+fn sprite_zero_hit_corners() {
+    // only the top left pixel of the sprite is opaque, and only the
+    // background pixel at (40, 40) is opaque
+    let hits_at = |x: u8, y: u8| {
+        let mut ppu = sprite_zero_hit_ppu(false);
+        ppu.write_bytes_to(&0x20a5u16.into(), &[CORNER_TILE]);
+
+        write_oam(&mut ppu, &[y, CORNER_TILE, 0x00, x]);
+
+        sprite_zero_hits(&mut ppu)
+    };
+
+    assert_eq!(hits_at(40, 39), true);
+    assert_eq!(hits_at(39, 39), false);
+    assert_eq!(hits_at(41, 39), false);
+    assert_eq!(hits_at(40, 38), false);
+    assert_eq!(hits_at(40, 40), false);
+}
+
+#[test]
+fn sprite_zero_hit_flip() {
+    // the sprite's opaque corner lands on the background's opaque corner at (47, 47)
+    let hits_with = |attributes: u8| {
+        let mut ppu = sprite_zero_hit_ppu(false);
+        ppu.write_bytes_to(&0x20a5u16.into(), &[OPPOSITE_CORNER_TILE]);
+
+        write_oam(&mut ppu, &[39, CORNER_TILE, attributes, 40]);
+
+        sprite_zero_hits(&mut ppu)
+    };
+
+    assert_eq!(hits_with(0x00), false);
+    assert_eq!(hits_with(0x40), false);
+    assert_eq!(hits_with(0x80), false);
+    assert_eq!(hits_with(0xc0), true);
+}
+
+#[test]
+fn sprite_zero_hit_left_clip() {
+    let hits_with = |mask: u8, x: u8| {
+        let mut ppu = sprite_zero_hit_ppu(true);
+        write_register(&mut ppu, PPUMASK, mask);
+
+        write_oam(&mut ppu, &[20, CORNER_TILE, 0x00, x]);
+
+        sprite_zero_hits(&mut ppu)
+    };
+
+    assert_eq!(hits_with(0x1e, 0), true);
+    assert_eq!(hits_with(0x18, 0), false);
+    assert_eq!(hits_with(0x1a, 0), false);
+    assert_eq!(hits_with(0x1c, 0), false);
+    assert_eq!(hits_with(0x18, 7), false);
+    assert_eq!(hits_with(0x18, 8), true);
+}
+
+#[test]
+fn sprite_zero_hit_right_edge() {
+    let hits_at = |x: u8| {
+        let mut ppu = sprite_zero_hit_ppu(true);
+        write_oam(&mut ppu, &[20, CORNER_TILE, 0x00, x]);
+
+        sprite_zero_hits(&mut ppu)
+    };
+
+    assert_eq!(hits_at(254), true);
+    assert_eq!(hits_at(255), false);
+}
+
+#[test]
+fn sprite_zero_hit_screen_bottom() {
+    let hits_at = |y: u8| {
+        let mut ppu = sprite_zero_hit_ppu(true);
+        write_oam(&mut ppu, &[y, CORNER_TILE, 0x00, 20]);
+
+        sprite_zero_hits(&mut ppu)
+    };
+
+    assert_eq!(hits_at(238), true);
+    assert_eq!(hits_at(239), false);
+}
+
+#[test]
+fn sprite_zero_hit_double_height() {
+    // the bottom row of an 8x16 sprite made of CORNER_TILE and OPPOSITE_CORNER_TILE
+    let hits_at = |y: u8| {
+        let mut ppu = sprite_zero_hit_ppu(false);
+        ppu.write_bytes_to(&0x20a5u16.into(), &[OPPOSITE_CORNER_TILE]);
+
+        write_register(&mut ppu, PPUCTRL, 0x20);
+        write_oam(&mut ppu, &[y, CORNER_TILE, 0x00, 40]);
+
+        sprite_zero_hits(&mut ppu)
+    };
+
+    assert_eq!(hits_at(39), false);
+    assert_eq!(hits_at(31), true);
+}
+
+#[test]
+fn vblank_set_and_cleared() {
+    let mut ppu = DefaultPpu::new();
+    ppu.start();
+
+    run_until(&mut ppu, VBLANK_SCANLINE, 1);
+    assert_eq!(ppu.ppu_status.vblank, false);
+
+    ppu.clock();
+    assert_eq!(ppu.ppu_status.vblank, true);
+
+    run_until(&mut ppu, PRE_RENDER_SCANLINE, 1);
+    assert_eq!(ppu.ppu_status.vblank, true);
+
+    ppu.clock();
+    assert_eq!(ppu.ppu_status.vblank, false);
+}
+
+#[test]
+fn ppustatus_read_clears_vblank_and_latch() {
+    let mut ppu = DefaultPpu::new();
+    ppu.start();
+
+    run_until(&mut ppu, VBLANK_SCANLINE, 2);
+
+    // first half of a ppuaddr write, abandoned by the status read
+    write_register(&mut ppu, PPUADDR, 0x3f);
+
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x80, 0x80);
+    assert_eq!(read_register(&mut ppu, PPUSTATUS) & 0x80, 0x00);
+
+    write_register(&mut ppu, PPUADDR, 0x21);
+    write_register(&mut ppu, PPUADDR, 0x08);
+    write_register(&mut ppu, PPUDATA, 0xaa);
+
+    assert_eq!(ppu.read_bytes(&0x2108u16.into(), 1), vec![0xaa]);
+}
+
+#[test]
+fn ppudata_reads_are_buffered() {
+    let mut ppu = DefaultPpu::new();
+    ppu.write_bytes_to(&0x2345u16.into(), &[0x11, 0x22]);
+    ppu.write_bytes_to(&0x2365u16.into(), &[0x33]);
+
+    write_register(&mut ppu, PPUADDR, 0x23);
+    write_register(&mut ppu, PPUADDR, 0x45);
+
+    // the first read returns the stale buffer
+    assert_eq!(read_register(&mut ppu, PPUDATA), 0x00);
+    assert_eq!(read_register(&mut ppu, PPUDATA), 0x11);
+    assert_eq!(read_register(&mut ppu, PPUDATA), 0x22);
+
+    // reads increment by 32 too
+    write_register(&mut ppu, PPUCTRL, 0x04);
+    write_register(&mut ppu, PPUADDR, 0x23);
+    write_register(&mut ppu, PPUADDR, 0x45);
+
+    read_register(&mut ppu, PPUDATA);
+    read_register(&mut ppu, PPUDATA);
+    assert_eq!(read_register(&mut ppu, PPUDATA), 0x33);
+}
+
+#[test]
+fn palette_reads_bypass_buffer() {
+    let mut ppu = DefaultPpu::new();
+    ppu.write_bytes_to(&0x3f01u16.into(), &[0x16]);
+    ppu.write_bytes_to(&0x2f01u16.into(), &[0x44]);
+
+    write_register(&mut ppu, PPUADDR, 0x3f);
+    write_register(&mut ppu, PPUADDR, 0x01);
+
+    // the top two bits come from the io bus, which still has the last write on it
+    assert_eq!(read_register(&mut ppu, PPUDATA), 0x16);
+
+    // the buffer now holds the nametable byte under the palette entry
+    write_register(&mut ppu, PPUADDR, 0x00);
+    write_register(&mut ppu, PPUADDR, 0x00);
+
+    assert_eq!(read_register(&mut ppu, PPUDATA), 0x44);
+}
+
+#[test]
+fn open_bus() {
+    let mut ppu = DefaultPpu::new();
+
+    // write-only registers read back the last value written to any register
+    write_register(&mut ppu, PPUSCROLL, 0xa5);
+    assert_eq!(read_register(&mut ppu, PPUCTRL), 0xa5);
+    assert_eq!(read_register(&mut ppu, PPUMASK), 0xa5);
+    assert_eq!(read_register(&mut ppu, OAMADDR), 0xa5);
+    assert_eq!(read_register(&mut ppu, PPUSCROLL), 0xa5);
+    assert_eq!(read_register(&mut ppu, PPUADDR), 0xa5);
+
+    // ppustatus fills in its low bits from the bus and refreshes the top 3
+    assert_eq!(read_register(&mut ppu, PPUSTATUS), 0x05);
+    assert_eq!(read_register(&mut ppu, PPUCTRL), 0x05);
+
+    // palette reads fill in their top 2 bits from the bus
+    write_register(&mut ppu, PPUADDR, 0x3f);
+    write_register(&mut ppu, PPUADDR, 0x00);
+    ppu.write_bytes_to(&0x3f00u16.into(), &[0x0f]);
+
+    write_register(&mut ppu, OAMADDR, 0xc0);
+    assert_eq!(read_register(&mut ppu, PPUDATA), 0xcf);
+
+    // and the bus decays without being refreshed
+    ppu.frame += DECAY_FRAMES;
+    assert_eq!(read_register(&mut ppu, PPUCTRL), 0x00);
+}
+
+#[test]
+fn nmi_on_vblank() {
+    let mut ppu = DefaultPpu::new();
+    ppu.start();
+
+    run_until(&mut ppu, VBLANK_SCANLINE, 2);
+
+    // vblank alone doesn't assert nmi
+    assert_eq!(ppu.is_nmi_asserted(), false);
+
+    write_register(&mut ppu, PPUCTRL, 0x80);
+    assert_eq!(ppu.is_nmi_asserted(), true);
+
+    // acknowledging vblank releases the line
+    read_register(&mut ppu, PPUSTATUS);
+    assert_eq!(ppu.is_nmi_asserted(), false);
+
+    run_until(&mut ppu, VBLANK_SCANLINE, 2);
+    assert_eq!(ppu.is_nmi_asserted(), true);
+
+    run_until(&mut ppu, PRE_RENDER_SCANLINE, 2);
+    assert_eq!(ppu.is_nmi_asserted(), false);
+}
+
+fn write_register(ppu: &mut DefaultPpu, addr: u16, val: u8) {
+    ppu.write_register(addr, val);
+}
+
+fn read_register(ppu: &mut DefaultPpu, addr: u16) -> u8 {
+    ppu.read_register(addr)
+}
+
+const SOLID_TILE: u8 = 0x01;
+const CORNER_TILE: u8 = 0x02;
+const OPPOSITE_CORNER_TILE: u8 = 0x03;
+
+// Sets up tiles for sprite 0 hit tests, optionally filling the background with SOLID_TILE
+fn sprite_zero_hit_ppu(solid_bg: bool) -> DefaultPpu {
+    let mut ppu = DefaultPpu::new();
+
+    ppu.write_bytes_to(&0x0010u16.into(), &[0xff; 8]);
+    ppu.write_bytes_to(&0x0020u16.into(), &[0x80]);
+    ppu.write_bytes_to(&0x0037u16.into(), &[0x01]);
+
+    if solid_bg {
+        ppu.write_bytes_to(&0x2000u16.into(), &[SOLID_TILE; 960]);
+    }
+
+    write_register(&mut ppu, PPUMASK, 0x1e);
+
+    ppu
+}
+
+// Runs the first frame up to vblank and checks the sprite 0 hit flag
+fn sprite_zero_hits(ppu: &mut DefaultPpu) -> bool {
+    ppu.start();
+    run_until(ppu, VBLANK_SCANLINE, 0);
+
+    read_register(ppu, PPUSTATUS) & 0x40 != 0
+}
+
+fn rgb(color: u8) -> Rgb {
+    SystemPalette::default().get_rgb(color, 0)
+}
+
+// Fills oam with the given sprites, leaving the rest offscreen
+fn write_oam(ppu: &mut DefaultPpu, sprites: &[u8]) {
+    write_register(ppu, OAMADDR, 0x00);
+
+    for i in 0..256 {
+        write_register(ppu, OAMDATA, *sprites.get(i).unwrap_or(&0xff));
+    }
+}
+
+// Clocks the ppu until it's next about to run the given dot
+fn run_until(ppu: &mut DefaultPpu, scanline: u16, dot: u16) {
+    ppu.clock();
+
+    while ppu.scanline != scanline || ppu.dot != dot {
+        ppu.clock();
+    }
+}
+
+// Starts the ppu and runs it through the first full frame
+fn run_frame(ppu: &mut DefaultPpu) {
+    ppu.start();
+
+    while ppu.get_frame_count() < 2 {
+        ppu.clock();
+    }
+}
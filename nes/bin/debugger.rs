@@ -27,7 +27,9 @@ pub fn start_debugger<'a>(cpu_ref: Rc<RefCell<Cpu>>)
         match input.as_str() {
             ".exit" => break,
             "r" => cpu.run(),
-            "s" => cpu.step(),
+            "s" => {
+                cpu.step();
+            }
             "p" => println!("{:?}", cpu),
             "p!" => always_print_status = !always_print_status,
             "h" => print_debugger_mode_help(),
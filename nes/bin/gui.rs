@@ -1,4 +1,5 @@
-use libnes::ppu::nametable::NAMETABLE_DIMS;
+use libnes::ppu::palette::Rgb;
+use libnes::ppu::render::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -10,6 +11,8 @@ use piston::window::WindowSettings;
 
 use libnes::nes::Nes;
 
+const PIXEL_SIZE: f64 = 2.0;
+
 struct AppDebugState {
     nametable_tile_index: u16,
     print_debug_info: bool,
@@ -26,11 +29,10 @@ impl App {
     fn render(&mut self, args: &RenderArgs) {
         use graphics::*;
 
-        const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
         const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
 
         let mut nes = self.nes.borrow_mut();
-        let mut ppu = nes.get_ppu();
+        let ppu = nes.get_ppu();
 
         let nametable_draw_tile_index = self.debug.nametable_tile_index;
 
@@ -40,46 +42,21 @@ impl App {
         }
 
         self.gl.draw(args.viewport(), |c, gl| {
-            clear(GREEN, gl);
-
-            let nametable = ppu.borrow().get_active_nametable();
-            let pattern_table = ppu.borrow_mut().get_active_pattern_table();
-
-            let mut index = 0;
-
-            for row in 0..NAMETABLE_DIMS[0] {
-                for col in 0..NAMETABLE_DIMS[1] {
-                    let tile = nametable.get_tile_at_loc(row, col, &pattern_table);
-
-                    // TODO: actually use palette for stuff
-                    let colors = tile.pattern_table_tile.get_color_indices();
-
-                    const TILE_SIZE: f64 = 16.0;
-                    let tile_x_offset = col as f64 * TILE_SIZE;
-                    let tile_y_offset = row as f64 * TILE_SIZE;
+            clear(BLACK, gl);
 
-                    for (i, color) in colors.iter().enumerate() {
-                        let row = (i / 8) as f64;
-                        let col = (i % 8) as f64;
+            let ppu = ppu.borrow();
+            let framebuffer = ppu.get_framebuffer();
 
-                        let pixel_size = TILE_SIZE / 8.0;
+            for (i, color) in framebuffer.iter().enumerate() {
+                let row = (i / SCREEN_WIDTH) as f64;
+                let col = (i % SCREEN_WIDTH) as f64;
 
-                        rectangle(
-                            match color {
-                                0 => BLACK,
-                                _ => GREEN,
-                            },
-                            rectangle::square(0.0, 0.0, pixel_size),
-                            c.transform.trans(
-                                tile_x_offset + (col * pixel_size),
-                                tile_y_offset + (row * pixel_size),
-                            ),
-                            gl,
-                        );
-                    }
-
-                    index += 1;
-                }
+                rectangle(
+                    get_color(color),
+                    rectangle::square(0.0, 0.0, PIXEL_SIZE),
+                    c.transform.trans(col * PIXEL_SIZE, row * PIXEL_SIZE),
+                    gl,
+                );
             }
         });
     }
@@ -93,7 +70,12 @@ impl App {
 pub fn start_gui(nes: Rc<RefCell<Nes>>) {
     let opengl = OpenGL::V3_2;
 
-    let mut window: Window = WindowSettings::new("nes", [480, 480])
+    let window_size = [
+        (SCREEN_WIDTH as f64 * PIXEL_SIZE) as u32,
+        (SCREEN_HEIGHT as f64 * PIXEL_SIZE) as u32,
+    ];
+
+    let mut window: Window = WindowSettings::new("nes", window_size)
         .opengl(opengl)
         .exit_on_esc(true)
         .build()
@@ -112,7 +94,8 @@ pub fn start_gui(nes: Rc<RefCell<Nes>>) {
         },
     };
 
-    let mut events = Events::new(EventSettings::new());
+    // Nes::tick runs a whole frame
+    let mut events = Events::new(EventSettings::new().ups(60));
     while let Some(e) = events.next(&mut window) {
         if let Some(r) = e.render_args() {
             app.render(&r);
@@ -138,3 +121,12 @@ pub fn start_gui(nes: Rc<RefCell<Nes>>) {
         }
     }
 }
+
+fn get_color(color: &Rgb) -> [f32; 4] {
+    [
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0,
+        1.0,
+    ]
+}
@@ -20,6 +20,7 @@ use libnes::cart::{get_cart_loader, CartLoader, RomFormat};
 use libnes::cpu::helpers::load_program_str;
 use libnes::cpu::{Cpu, DefaultCpu};
 use libnes::nes::{DefaultNes, Nes};
+use libnes::ppu::palette::SystemPalette;
 use libnes::ppu::{DefaultPpu, Ppu};
 use libnes::util::rc_ref;
 
@@ -73,6 +74,7 @@ fn exec_command_run<'a>(options: &ArgMatches<'a>) {
     let rom_format_str = options.value_of("format").expect("format is required");
     let start_addr = options.value_of("startaddr");
     let gui = options.value_of("gui");
+    let palette_filename = options.value_of("palette");
 
     let rom_format = match rom_format_str {
         "ines" => RomFormat::iNes,
@@ -82,6 +84,15 @@ fn exec_command_run<'a>(options: &ArgMatches<'a>) {
     let cpu = rc_ref(DefaultCpu::new(debug));
     let ppu = rc_ref(DefaultPpu::new());
 
+    if let Some(palette_filename) = palette_filename {
+        let palette_data = fs::read(palette_filename)
+            .expect(&format!("Failed to read palette file {}", palette_filename));
+
+        let palette = SystemPalette::from_pal(&palette_data).expect("Failed to load palette");
+
+        ppu.borrow_mut().set_palette(palette);
+    }
+
     let nes = rc_ref(DefaultNes::new(cpu, ppu));
 
     let filename = options
@@ -183,6 +194,9 @@ fn get_cli_app<'a, 'b>() -> App<'a, 'b> {
                     Arg::with_name("startaddr")
                         .long("start-addr")
                         .value_name("START_ADDRESS"),
+                    Arg::with_name("palette")
+                        .long("palette")
+                        .value_name("PAL_FILE"),
                 ]),
         ])
 }